@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
-use junobuild_satellite::{AssertSetDocContext, AssertDeleteDocContext, OnSetDocContext};
-use junobuild_utils::decode_doc_data;
+use junobuild_satellite::{set_doc, AssertSetDocContext, AssertDeleteDocContext, OnSetDocContext, SetDoc};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+
+// Longest details text accepted by validate_activity_log_data
+const MAX_DETAILS_LENGTH: usize = 1000;
 
 // Activity log structure for WaqfProtocol - simplified and focused
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -68,7 +71,7 @@ fn validate_activity_log_data(log: &ActivityLog) -> std::result::Result<(), Stri
     }
     
     if let Some(ref details) = log.details {
-        if details.len() > 1000 {
+        if details.len() > MAX_DETAILS_LENGTH {
             return Err("Activity details must be 1000 characters or less".into());
         }
     }
@@ -142,3 +145,36 @@ pub fn handle_activity_log_changes(context: OnSetDocContext) -> std::result::Res
     
     Ok(())
 }
+
+// Write an audit entry on behalf of the satellite (audit logs cannot be deleted)
+pub fn record_audit_log(action: &str, user_id: &str, resource_type: &str, resource_id: &str, details: String) {
+    let now = ic_cdk::api::time();
+    let mut details = details;
+    if details.len() > MAX_DETAILS_LENGTH {
+        let mut end = MAX_DETAILS_LENGTH;
+        while !details.is_char_boundary(end) {
+            end -= 1;
+        }
+        details.truncate(end);
+    }
+
+    let log = ActivityLog {
+        id: format!("{}_{}_{}", action, resource_id, now),
+        action: action.to_string(),
+        category: "audit".to_string(),
+        level: "info".to_string(),
+        user_id: Some(user_id.to_string()),
+        user_email: None,
+        details: Some(details),
+        resource_id: Some(resource_id.to_string()),
+        resource_type: Some(resource_type.to_string()),
+        timestamp: now / 1_000_000,
+    };
+
+    match encode_doc_data(&log) {
+        Ok(data) => {
+            set_doc("activity_logs".to_string(), log.id.clone(), SetDoc { data, description: None, version: None });
+        }
+        Err(e) => ic_cdk::println!("ERROR - Cannot encode activity log {}: {}", log.id, e),
+    }
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use junobuild_satellite::{AssertSetDocContext, AssertDeleteDocContext, OnSetDocContext};
+use junobuild_satellite::{AssertSetDocContext, AssertDeleteDocContext, OnSetDocContext, get_doc};
 use junobuild_utils::decode_doc_data;
 
 // Updated roles to match frontend exactly
@@ -60,6 +60,28 @@ pub struct AdminUser {
     pub deleted_by: Option<String>,
 }
 
+// Look up the role of an active admin (admin documents are keyed by user principal)
+pub fn get_admin_role(principal: &str) -> Option<AdminRole> {
+    let doc = get_doc("admins".to_string(), principal.to_string())?;
+    let admin: AdminUser = decode_doc_data(&doc.data).ok()?;
+    
+    if !admin.active || admin.deleted.unwrap_or(false) {
+        return None;
+    }
+    
+    Some(admin.role)
+}
+
+// Check that the caller is an active admin holding one of the given roles
+pub fn caller_has_role(caller: &str, roles: &[AdminRole]) -> bool {
+    get_admin_role(caller).is_some_and(|role| roles.contains(&role))
+}
+
+// Writes made by the satellite itself (hooks and scheduled jobs run as the satellite)
+pub fn is_satellite_caller(caller: &str) -> bool {
+    caller == junobuild_satellite::id().to_text()
+}
+
 // Basic validation function
 fn validate_admin_data(admin: &AdminUser) -> std::result::Result<(), String> {
     // 1. Validate email format
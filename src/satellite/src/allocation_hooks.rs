@@ -1,6 +1,9 @@
-use crate::waqf_types::AllocationData;
-use junobuild_satellite::{OnSetDocContext, AssertSetDocContext, AssertDeleteDocContext};
-use junobuild_utils::decode_doc_data;
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::bucket_accounting;
+use crate::cause_hooks::{load_cause, record_allocation_funding, validate_allocation_against_cause};
+use crate::waqf_types::{AllocationData, TrusteePower, WaqfBucket, WaqfData};
+use junobuild_satellite::{OnSetDocContext, AssertSetDocContext, AssertDeleteDocContext, get_doc, set_doc, SetDoc};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
 
 // Validation constants
 const MIN_ALLOCATION_AMOUNT: f64 = 0.01;
@@ -16,6 +19,26 @@ pub fn assert_allocation_operations(context: AssertSetDocContext) -> std::result
     // Validate allocation data
     validate_allocation_data(&allocation)?;
     
+    // Allocations are ledger entries: the waqf buckets and cause totals were moved when they
+    // were created. Only the satellite updates them, to record a redirect.
+    if context.data.data.current.is_some() && !is_satellite_caller(&context.caller.to_text()) {
+        return Err("FORBIDDEN: Allocations cannot be changed once recorded".into());
+    }
+    
+    // New allocations must be covered by the matching bucket of the waqf
+    if context.data.data.current.is_none() {
+        let mut waqf = load_waqf(&allocation.waqf_id)?;
+        validate_allocation_caller(&waqf, &context.caller.to_text())?;
+        let bucket = allocation_bucket(&allocation, &waqf);
+        validate_allocation_against_waqf(&allocation, &waqf)?;
+        bucket_accounting::ensure_bucket_balances(&mut waqf);
+        bucket_accounting::debit(&mut waqf.financial, &allocation.cause_id, bucket, allocation.amount)?;
+        
+        // Funded causes reject or redirect further allocations
+        let cause = load_cause(&allocation.cause_id)?;
+        validate_allocation_against_cause(&cause, allocation.amount, &waqf.selected_causes)?;
+    }
+    
     ic_cdk::println!(
         "Allocation validation passed: {} - Waqf: {}, Cause: {}, Amount: {}", 
         allocation.id, allocation.waqf_id, allocation.cause_id, allocation.amount
@@ -25,21 +48,16 @@ pub fn assert_allocation_operations(context: AssertSetDocContext) -> std::result
 }
 
 pub fn assert_allocation_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
-    // Allow allocation deletion for admin purposes
-    // Could add business rules for preventing deletion of completed allocations
-    ic_cdk::println!("Allocation deletion requested");
-    Ok(())
+    // Deleting an allocation would leave the waqf buckets and cause totals it moved out of step
+    Err("FORBIDDEN: Allocations are part of the waqf ledger and cannot be deleted".into())
 }
 
 pub fn handle_allocation_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
     let allocation: AllocationData = decode_doc_data(&context.data.data.after.data)
         .map_err(|e| format!("Cannot decode allocation data: {}", e))?;
     
-    let operation_type = if context.data.data.before.is_none() {
-        "CREATE"
-    } else {
-        "UPDATE"
-    };
+    let is_new_allocation = context.data.data.before.is_none();
+    let operation_type = if is_new_allocation { "CREATE" } else { "UPDATE" };
     
     ic_cdk::println!(
         "Allocation {}: {} - Waqf: {}, Cause: {}, Amount: {}",
@@ -60,6 +78,121 @@ pub fn handle_allocation_changes(context: OnSetDocContext) -> std::result::Resul
         );
     }
     
+    // Draw new allocations from the waqf's bucket balances
+    if is_new_allocation {
+        apply_allocation_to_waqf(&allocation)?;
+        apply_allocation_to_cause(&allocation, &context)?;
+    }
+    
+    Ok(())
+}
+
+// The donor, waqf staff, the satellite, or a trustee holding the allocation power
+fn validate_allocation_caller(waqf: &WaqfData, caller: &str) -> std::result::Result<(), String> {
+    let allowed = waqf.created_by == caller
+        || is_satellite_caller(caller)
+        || caller_has_role(caller, &[AdminRole::WaqfManager, AdminRole::FinanceOfficer, AdminRole::PlatformAdmin])
+        || waqf.trustee_has_power(caller, TrusteePower::ProposeAllocations);
+
+    if !allowed {
+        return Err(format!("Caller is not allowed to allocate funds of waqf {}", waqf.id));
+    }
+
+    Ok(())
+}
+
+fn load_waqf(waqf_id: &str) -> std::result::Result<WaqfData, String> {
+    let doc = get_doc("waqfs".to_string(), waqf_id.to_string())
+        .ok_or_else(|| format!("Waqf not found: {}", waqf_id))?;
+    
+    decode_doc_data(&doc.data).map_err(|e| format!("Failed to decode waqf data: {}", e))
+}
+
+// Bucket an allocation draws from - explicit, or implied by the waqf type
+fn allocation_bucket(allocation: &AllocationData, waqf: &WaqfData) -> WaqfBucket {
+    allocation
+        .bucket
+        .unwrap_or_else(|| bucket_accounting::default_bucket(&waqf.waqf_type))
+}
+
+fn validate_allocation_against_waqf(allocation: &AllocationData, waqf: &WaqfData) -> std::result::Result<(), String> {
+    if !waqf.selected_causes.contains(&allocation.cause_id) {
+        return Err(format!(
+            "Cause {} is not one of the causes selected for waqf {}",
+            allocation.cause_id, waqf.id
+        ));
+    }
+    
+    if allocation.amount - waqf.financial.current_balance > 0.005 {
+        return Err(format!(
+            "Allocation exceeds waqf balance. Requested: {:.2}, Available: {:.2}",
+            allocation.amount, waqf.financial.current_balance
+        ));
+    }
+    
+    Ok(())
+}
+
+fn apply_allocation_to_waqf(allocation: &AllocationData) -> std::result::Result<(), String> {
+    let doc = get_doc("waqfs".to_string(), allocation.waqf_id.clone())
+        .ok_or_else(|| format!("Waqf not found: {}", allocation.waqf_id))?;
+    let mut waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+    
+    let bucket = allocation_bucket(allocation, &waqf);
+    bucket_accounting::ensure_bucket_balances(&mut waqf);
+    bucket_accounting::debit(&mut waqf.financial, &allocation.cause_id, bucket, allocation.amount)?;
+    
+    waqf.financial.total_distributed += allocation.amount;
+    waqf.financial.current_balance = (waqf.financial.current_balance - allocation.amount).max(0.0);
+    waqf.updated_at = Some(ic_cdk::api::time().to_string());
+    
+    let updated_data = encode_doc_data(&waqf)
+        .map_err(|e| format!("Failed to encode waqf data: {}", e))?;
+    
+    let set_doc_data = SetDoc {
+        data: updated_data,
+        description: doc.description,
+        version: doc.version,
+    };
+    
+    let _ = set_doc("waqfs".to_string(), allocation.waqf_id.clone(), set_doc_data);
+    
+    ic_cdk::println!(
+        "Updated waqf {} after allocation {}: -{} from {:?} bucket of cause {}, new balance: {}",
+        allocation.waqf_id,
+        allocation.id,
+        allocation.amount,
+        bucket,
+        allocation.cause_id,
+        waqf.financial.current_balance
+    );
+    
+    Ok(())
+}
+
+fn apply_allocation_to_cause(allocation: &AllocationData, context: &OnSetDocContext) -> std::result::Result<(), String> {
+    let waqf = load_waqf(&allocation.waqf_id)?;
+    let redirected = record_allocation_funding(&allocation.cause_id, allocation.amount, &waqf.selected_causes)?;
+    
+    // Record the redirect on the allocation itself
+    if let Some((redirect_cause_id, redirected_amount)) = redirected {
+        let mut updated = allocation.clone();
+        updated.redirected_cause_id = Some(redirect_cause_id);
+        updated.redirected_amount = Some(redirected_amount);
+        
+        let updated_data = encode_doc_data(&updated)
+            .map_err(|e| format!("Failed to encode allocation data: {}", e))?;
+        
+        let set_doc_data = SetDoc {
+            data: updated_data,
+            description: context.data.data.after.description.clone(),
+            version: context.data.data.after.version,
+        };
+        
+        let _ = set_doc("allocations".to_string(), allocation.id.clone(), set_doc_data);
+    }
+    
     Ok(())
 }
 
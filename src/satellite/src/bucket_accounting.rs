@@ -0,0 +1,441 @@
+use crate::waqf_types::{BucketBalances, FinancialMetrics, WaqfBucket, WaqfData, WaqfType};
+use std::collections::HashMap;
+
+const BUCKETS: [WaqfBucket; 3] = [WaqfBucket::Permanent, WaqfBucket::Consumable, WaqfBucket::Revolving];
+
+impl BucketBalances {
+    pub fn get(&self, bucket: WaqfBucket) -> f64 {
+        match bucket {
+            WaqfBucket::Permanent => self.permanent,
+            WaqfBucket::Consumable => self.consumable,
+            WaqfBucket::Revolving => self.revolving,
+        }
+    }
+
+    pub fn get_mut(&mut self, bucket: WaqfBucket) -> &mut f64 {
+        match bucket {
+            WaqfBucket::Permanent => &mut self.permanent,
+            WaqfBucket::Consumable => &mut self.consumable,
+            WaqfBucket::Revolving => &mut self.revolving,
+        }
+    }
+
+    pub fn total(&self) -> f64 {
+        self.permanent + self.consumable + self.revolving
+    }
+}
+
+/// Bucket a non-hybrid waqf keeps all of its funds in
+pub fn default_bucket(waqf_type: &WaqfType) -> WaqfBucket {
+    match waqf_type {
+        WaqfType::Permanent => WaqfBucket::Permanent,
+        WaqfType::TemporaryConsumable => WaqfBucket::Consumable,
+        WaqfType::TemporaryRevolving => WaqfBucket::Revolving,
+        // Hybrid allocations are drawn from the spendable slice unless stated otherwise
+        WaqfType::Hybrid => WaqfBucket::Consumable,
+    }
+}
+
+/// Weight of each selected cause, normalised so the weights sum to 1
+fn cause_weights(waqf: &WaqfData) -> Vec<(String, f64)> {
+    if waqf.selected_causes.is_empty() {
+        return Vec::new();
+    }
+
+    let equal_share = 100.0 / waqf.selected_causes.len() as f64;
+    let raw: Vec<(String, f64)> = waqf
+        .selected_causes
+        .iter()
+        .map(|cause_id| {
+            let pct = waqf.cause_allocation.get(cause_id).copied().unwrap_or(equal_share);
+            (cause_id.clone(), pct.max(0.0))
+        })
+        .collect();
+
+    let total: f64 = raw.iter().map(|(_, pct)| pct).sum();
+    if total <= 0.0 {
+        return raw.into_iter().map(|(id, _)| (id, 1.0 / waqf.selected_causes.len() as f64)).collect();
+    }
+
+    raw.into_iter().map(|(id, pct)| (id, pct / total)).collect()
+}
+
+/// Share of each bucket for one cause, normalised so the shares sum to 1
+fn bucket_weights(waqf: &WaqfData, cause_id: &str) -> [f64; 3] {
+    let mut weights = [0.0; 3];
+
+    if !matches!(waqf.waqf_type, WaqfType::Hybrid) {
+        let bucket = default_bucket(&waqf.waqf_type);
+        weights[BUCKETS.iter().position(|b| *b == bucket).unwrap_or(0)] = 1.0;
+        return weights;
+    }
+
+    let allocation = waqf
+        .hybrid_allocations
+        .as_ref()
+        .and_then(|allocations| allocations.iter().find(|a| a.cause_id == cause_id));
+
+    if let Some(allocation) = allocation {
+        weights = [
+            allocation.allocations.permanent.unwrap_or(0.0).max(0.0),
+            allocation.allocations.temporary_consumable.unwrap_or(0.0).max(0.0),
+            allocation.allocations.temporary_revolving.unwrap_or(0.0).max(0.0),
+        ];
+    }
+
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        // No usable split for this cause - keep the principal intact
+        ic_cdk::println!(
+            "WARN - Hybrid waqf {} has no allocation for cause {}; assigning it to the permanent bucket",
+            waqf.id, cause_id
+        );
+        return [1.0, 0.0, 0.0];
+    }
+
+    weights.map(|w| w / total)
+}
+
+/// Apportion whole cents by weight, handing out rounding remainders by largest fraction
+/// (ties go to the earlier entry) so the parts always add up to the total exactly
+pub fn apportion_cents(total_cents: u64, weights: &[f64]) -> Vec<u64> {
+    let mut parts: Vec<(u64, f64)> = weights
+        .iter()
+        .map(|weight| {
+            let exact = total_cents as f64 * weight;
+            let floor = exact.floor();
+            (floor as u64, exact - floor)
+        })
+        .collect();
+
+    if parts.is_empty() {
+        return Vec::new();
+    }
+
+    let assigned: u64 = parts.iter().map(|(cents, _)| cents).sum();
+    let mut remaining = total_cents.saturating_sub(assigned);
+
+    let mut order: Vec<usize> = (0..parts.len()).collect();
+    order.sort_by(|a, b| {
+        parts[*b].1.partial_cmp(&parts[*a].1).unwrap_or(std::cmp::Ordering::Equal).then(a.cmp(b))
+    });
+    for index in order.into_iter().cycle() {
+        if remaining == 0 {
+            break;
+        }
+        parts[index].0 += 1;
+        remaining -= 1;
+    }
+
+    parts.into_iter().map(|(cents, _)| cents).collect()
+}
+
+pub fn to_cents(amount: f64) -> u64 {
+    (amount * 100.0).round().max(0.0) as u64
+}
+
+/// Split an amount across every cause/bucket pair as cause weight × bucket percentage
+pub fn split_amount(waqf: &WaqfData, amount: f64) -> HashMap<String, BucketBalances> {
+    let mut slots: Vec<(String, WaqfBucket)> = Vec::new();
+    let mut weights: Vec<f64> = Vec::new();
+    for (cause_id, cause_weight) in cause_weights(waqf) {
+        let bucket_shares = bucket_weights(waqf, &cause_id);
+        for (bucket, bucket_weight) in BUCKETS.iter().zip(bucket_shares) {
+            slots.push((cause_id.clone(), *bucket));
+            weights.push(cause_weight * bucket_weight);
+        }
+    }
+
+    let mut result: HashMap<String, BucketBalances> = HashMap::new();
+    for ((cause_id, bucket), cents) in slots.into_iter().zip(apportion_cents(to_cents(amount), &weights)) {
+        *result.entry(cause_id).or_default().get_mut(bucket) = cents as f64 / 100.0;
+    }
+
+    result
+}
+
+/// Split an amount across causes by their cause_allocation weight only
+pub fn split_by_cause(waqf: &WaqfData, amount: f64) -> HashMap<String, f64> {
+    let (causes, weights): (Vec<String>, Vec<f64>) = cause_weights(waqf).into_iter().unzip();
+
+    causes
+        .into_iter()
+        .zip(apportion_cents(to_cents(amount), &weights))
+        .map(|(cause_id, cents)| (cause_id, cents as f64 / 100.0))
+        .collect()
+}
+
+/// Sum of one bucket across all causes of a split
+pub fn bucket_total(split: &HashMap<String, BucketBalances>, bucket: WaqfBucket) -> f64 {
+    split.values().map(|balances| balances.get(bucket)).sum()
+}
+
+/// Add a split to the waqf's per-cause bucket balances
+pub fn credit(financial: &mut FinancialMetrics, split: &HashMap<String, BucketBalances>) {
+    for (cause_id, parts) in split {
+        let balances = financial.bucket_balances.entry(cause_id.clone()).or_default();
+        for bucket in BUCKETS {
+            *balances.get_mut(bucket) += parts.get(bucket);
+        }
+    }
+}
+
+/// Seed bucket balances for waqfs created before per-bucket accounting existed
+pub fn ensure_bucket_balances(waqf: &mut WaqfData) {
+    if !waqf.financial.bucket_balances.is_empty() || waqf.financial.current_balance <= 0.0 {
+        return;
+    }
+
+    let split = split_amount(waqf, waqf.financial.current_balance);
+    credit(&mut waqf.financial, &split);
+
+    ic_cdk::println!(
+        "Seeded bucket balances for waqf {} from current balance {:.2}",
+        waqf.id, waqf.financial.current_balance
+    );
+}
+
+/// Take an amount out of one cause's bucket, enforcing that bucket's waqf-type rules
+pub fn debit(
+    financial: &mut FinancialMetrics,
+    cause_id: &str,
+    bucket: WaqfBucket,
+    amount: f64,
+) -> std::result::Result<(), String> {
+    match bucket {
+        WaqfBucket::Permanent => {
+            // Principal stays invested - only returns awaiting distribution can be allocated
+            let pending = financial.pending_distributions.get(cause_id).copied().unwrap_or(0.0);
+            if amount - pending > 0.005 {
+                return Err(format!(
+                    "Permanent waqf principal cannot be allocated - only investment returns may be distributed. \
+                    Requested: {:.2}, Distributable returns for cause {}: {:.2}",
+                    amount, cause_id, pending
+                ));
+            }
+            financial
+                .pending_distributions
+                .insert(cause_id.to_string(), (pending - amount).max(0.0));
+            return Ok(());
+        }
+        WaqfBucket::Revolving => {
+            return Err(
+                "Revolving waqf principal is owed back to the donor and cannot be allocated".to_string()
+            );
+        }
+        WaqfBucket::Consumable => {}
+    }
+
+    let balances = financial
+        .bucket_balances
+        .get_mut(cause_id)
+        .ok_or_else(|| format!("Waqf has no balance for cause {}", cause_id))?;
+
+    let available = balances.get(bucket);
+    // Allow for floating point errors
+    if amount - available > 0.005 {
+        return Err(format!(
+            "Insufficient {:?} balance for cause {}. Requested: {:.2}, Available: {:.2}",
+            bucket, cause_id, amount, available
+        ));
+    }
+
+    *balances.get_mut(bucket) = (available - amount).max(0.0);
+    Ok(())
+}
+
+/// Move an amount out of one bucket pro-rata across causes, optionally into another bucket.
+/// Used when tranche principal leaves the revolving bucket (refunds and conversions).
+pub fn transfer_bucket(
+    financial: &mut FinancialMetrics,
+    from: WaqfBucket,
+    to: Option<WaqfBucket>,
+    amount: f64,
+) -> std::result::Result<(), String> {
+    let mut causes: Vec<String> = financial.bucket_balances.keys().cloned().collect();
+    causes.sort();
+
+    let balances: Vec<f64> = causes
+        .iter()
+        .map(|cause_id| financial.bucket_balances[cause_id].get(from).max(0.0))
+        .collect();
+    let available: f64 = balances.iter().sum();
+
+    // Allow for floating point errors
+    if amount - available > 0.005 {
+        return Err(format!(
+            "Insufficient {:?} balance. Requested: {:.2}, Available: {:.2}",
+            from, amount, available
+        ));
+    }
+
+    if available <= 0.0 {
+        return Ok(());
+    }
+
+    let weights: Vec<f64> = balances.iter().map(|balance| balance / available).collect();
+    let parts = apportion_cents(to_cents(amount.min(available)), &weights);
+
+    for (cause_id, cents) in causes.iter().zip(parts) {
+        let moved = cents as f64 / 100.0;
+        if let Some(balances) = financial.bucket_balances.get_mut(cause_id) {
+            let from_balance = balances.get_mut(from);
+            *from_balance = (*from_balance - moved).max(0.0);
+            if let Some(to) = to {
+                *balances.get_mut(to) += moved;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Move tranche principal out of the revolving bucket. Principal that a rollover re-pointed
+/// to one cause is taken from that cause; otherwise it is taken pro-rata across causes.
+pub fn release_revolving(
+    financial: &mut FinancialMetrics,
+    cause_id: Option<&str>,
+    to: Option<WaqfBucket>,
+    amount: f64,
+) -> std::result::Result<(), String> {
+    if let Some(balances) = cause_id.and_then(|id| financial.bucket_balances.get_mut(id)) {
+        // Allow for floating point errors
+        if amount - balances.revolving <= 0.005 {
+            balances.revolving = (balances.revolving - amount).max(0.0);
+            if let Some(to) = to {
+                *balances.get_mut(to) += amount;
+            }
+            return Ok(());
+        }
+    }
+
+    transfer_bucket(financial, WaqfBucket::Revolving, to, amount)
+}
+
+/// Re-point revolving principal to a single cause within the same waqf
+pub fn repoint_revolving(
+    financial: &mut FinancialMetrics,
+    from_cause: Option<&str>,
+    to_cause: &str,
+    amount: f64,
+) -> std::result::Result<(), String> {
+    release_revolving(financial, from_cause, None, amount)?;
+    financial.bucket_balances.entry(to_cause.to_string()).or_default().revolving += amount;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::waqf_types::{
+        DeliveryMethod, DonorProfile, HybridAllocation, HybridCauseAllocation, NotificationPreferences,
+        ReportingFrequency, ReportingPreferences, WaqfStatus,
+    };
+
+    fn waqf(waqf_type: WaqfType, causes: &[(&str, f64)]) -> WaqfData {
+        WaqfData {
+            id: "waqf_test".into(),
+            name: "Test waqf".into(),
+            description: String::new(),
+            waqf_asset: 0.0,
+            donor: DonorProfile { name: String::new(), email: String::new(), phone: String::new(), address: String::new() },
+            selected_causes: causes.iter().map(|(id, _)| id.to_string()).collect(),
+            cause_allocation: causes.iter().map(|(id, pct)| (id.to_string(), *pct)).collect(),
+            status: WaqfStatus::Active,
+            is_donated: None,
+            notifications: NotificationPreferences { contribution_reminders: false, impact_reports: false, financial_updates: false },
+            reporting_preferences: ReportingPreferences {
+                frequency: ReportingFrequency::Yearly,
+                report_types: Vec::new(),
+                delivery_method: DeliveryMethod::Platform,
+            },
+            financial: FinancialMetrics::default(),
+            is_hybrid: matches!(waqf_type, WaqfType::Hybrid),
+            waqf_type,
+            hybrid_allocations: None,
+            consumable_details: None,
+            revolving_details: None,
+            investment_strategy: None,
+            deed_document: None,
+            created_by: String::new(),
+            created_at: "0".into(),
+            updated_at: None,
+            last_contribution_date: None,
+            next_contribution_date: None,
+            next_report_date: None,
+            causes_pending_reassignment: None,
+            converted_from: None,
+            deed_reference: None,
+            trustees: Vec::new(),
+            template: None,
+        }
+    }
+
+    fn split_cents(split: &HashMap<String, BucketBalances>) -> u64 {
+        split.values().flat_map(|balances| BUCKETS.map(|bucket| to_cents(balances.get(bucket)))).sum()
+    }
+
+    #[test]
+    fn apportion_cents_adds_up_to_the_total() {
+        assert_eq!(apportion_cents(100, &[1.0 / 3.0; 3]), vec![34, 33, 33]);
+        assert_eq!(apportion_cents(200, &[1.0 / 3.0; 3]), vec![67, 67, 66]);
+        assert_eq!(apportion_cents(1, &[0.5, 0.5]), vec![1, 0]);
+        assert_eq!(apportion_cents(0, &[0.25, 0.75]), vec![0, 0]);
+        assert!(apportion_cents(100, &[]).is_empty());
+
+        let parts = apportion_cents(123_457, &[0.1, 0.2, 0.3, 0.4]);
+        assert_eq!(parts.iter().sum::<u64>(), 123_457);
+    }
+
+    #[test]
+    fn apportion_cents_gives_remainders_to_the_largest_fractions() {
+        // Exact shares 16.6, 33.3, 50.1 cents: the single leftover cent goes to the 0.6 fraction
+        assert_eq!(apportion_cents(100, &[0.166, 0.333, 0.501]), vec![17, 33, 50]);
+    }
+
+    #[test]
+    fn split_amount_keeps_every_cent_across_causes() {
+        let waqf = waqf(WaqfType::Permanent, &[("a", 33.0), ("b", 33.0), ("c", 34.0)]);
+        let split = split_amount(&waqf, 10.01);
+
+        assert_eq!(split_cents(&split), 1_001);
+        assert_eq!(bucket_total(&split, WaqfBucket::Consumable), 0.0);
+        assert_eq!(split["c"].permanent, 3.41);
+    }
+
+    #[test]
+    fn split_amount_uses_equal_shares_without_an_allocation() {
+        let mut waqf = waqf(WaqfType::TemporaryConsumable, &[("a", 0.0), ("b", 0.0), ("c", 0.0)]);
+        waqf.cause_allocation.clear();
+        let split = split_amount(&waqf, 1.00);
+
+        assert_eq!(split_cents(&split), 100);
+        assert_eq!(split["a"].consumable, 0.34);
+        assert_eq!(split["b"].consumable, 0.33);
+        assert_eq!(split["c"].consumable, 0.33);
+    }
+
+    #[test]
+    fn split_amount_applies_hybrid_bucket_shares() {
+        let mut waqf = waqf(WaqfType::Hybrid, &[("a", 50.0), ("b", 50.0)]);
+        waqf.hybrid_allocations = Some(
+            ["a", "b"]
+                .iter()
+                .map(|cause_id| HybridCauseAllocation {
+                    cause_id: cause_id.to_string(),
+                    allocations: HybridAllocation {
+                        permanent: Some(50.0),
+                        temporary_consumable: Some(30.0),
+                        temporary_revolving: Some(20.0),
+                    },
+                })
+                .collect(),
+        );
+        let split = split_amount(&waqf, 100.01);
+
+        assert_eq!(split_cents(&split), 10_001);
+        assert_eq!(to_cents(bucket_total(&split, WaqfBucket::Permanent)), 5_001);
+        assert_eq!(bucket_total(&split, WaqfBucket::Consumable), 30.0);
+        assert_eq!(bucket_total(&split, WaqfBucket::Revolving), 20.0);
+    }
+}
@@ -0,0 +1,154 @@
+// Calendar arithmetic on nanosecond timestamps (UTC).
+//
+// Lock periods, installment schedules and distribution dates are expressed in whole
+// calendar months: adding a month keeps the day of month and time of day, clamping the
+// day to the end of shorter months (31 January + 1 month = 28/29 February).
+//
+// Maturity dates already stored on tranches are kept as they are - they were shown to
+// the donor when the contribution was made - and only new dates use calendar months.
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ if is_leap_year(year) => 29,
+        _ => 28,
+    }
+}
+
+// Days since 1970-01-01 to (year, month, day), proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// (year, month, day) to days since 1970-01-01
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Add whole calendar months to a timestamp, clamping the day at month end
+pub fn add_months(timestamp_nanos: u64, months: u32) -> u64 {
+    let days = (timestamp_nanos / NANOS_PER_DAY) as i64;
+    let time_of_day = timestamp_nanos % NANOS_PER_DAY;
+
+    let (year, month, day) = civil_from_days(days);
+    let month_index = year * 12 + (month as i64 - 1) + months as i64;
+    let target_year = month_index.div_euclid(12);
+    let target_month = (month_index.rem_euclid(12) + 1) as u32;
+    let target_day = day.min(days_in_month(target_year, target_month));
+
+    let target_days = days_from_civil(target_year, target_month, target_day);
+    (target_days as u64) * NANOS_PER_DAY + time_of_day
+}
+
+/// Number of months between payments for an installment or distribution frequency
+pub fn frequency_months(frequency: &str) -> Option<u32> {
+    match frequency {
+        "monthly" => Some(1),
+        "quarterly" => Some(3),
+        "semiannually" => Some(6),
+        "annually" | "yearly" => Some(12),
+        _ => None,
+    }
+}
+
+/// Whole calendar months elapsed from `start` to `end` (0 if `end` is not after `start`)
+pub fn whole_months_between(start_nanos: u64, end_nanos: u64) -> u32 {
+    if end_nanos <= start_nanos {
+        return 0;
+    }
+    let (start_year, start_month, _) = civil_from_days((start_nanos / NANOS_PER_DAY) as i64);
+    let (end_year, end_month, _) = civil_from_days((end_nanos / NANOS_PER_DAY) as i64);
+    let mut months = ((end_year * 12 + end_month as i64) - (start_year * 12 + start_month as i64)).max(0) as u32;
+    if months > 0 && add_months(start_nanos, months) > end_nanos {
+        months -= 1;
+    }
+    months
+}
+
+/// Calendar month of a timestamp as "YYYY-MM"
+pub fn month_key(timestamp_nanos: u64) -> String {
+    let (year, month, _) = civil_from_days((timestamp_nanos / NANOS_PER_DAY) as i64);
+    format!("{:04}-{:02}", year, month)
+}
+
+/// Calendar year of a timestamp
+pub fn year_of(timestamp_nanos: u64) -> i64 {
+    civil_from_days((timestamp_nanos / NANOS_PER_DAY) as i64).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 60 * 60 * 1_000_000_000;
+
+    fn date(days_since_epoch: u64) -> u64 {
+        days_since_epoch * NANOS_PER_DAY
+    }
+
+    #[test]
+    fn add_months_clamps_to_month_end() {
+        // 31 January + 1 month = 29 February in a leap year, 28 February otherwise
+        assert_eq!(add_months(date(19_753), 1), date(19_782));
+        assert_eq!(add_months(date(19_388), 1), date(19_416));
+        // 30 November + 2 months keeps the 30th
+        assert_eq!(add_months(date(20_057), 2), date(20_057 + 31 + 30));
+        // Clamping does not carry over: 31 January + 1 + 1 month is 29 March, + 2 months is 31 March
+        assert_eq!(add_months(add_months(date(19_753), 1), 1), date(19_782 + 29));
+        assert_eq!(add_months(date(19_753), 2), date(19_782 + 31));
+    }
+
+    #[test]
+    fn add_months_crosses_years_and_keeps_time_of_day() {
+        assert_eq!(add_months(date(20_088) + 5 * HOUR, 3), date(20_178) + 5 * HOUR);
+        assert_eq!(add_months(date(11_016), 12), date(11_016 + 365));
+        assert_eq!(add_months(date(19_753), 0), date(19_753));
+    }
+
+    #[test]
+    fn year_of_handles_year_boundaries() {
+        assert_eq!(year_of(0), 1970);
+        assert_eq!(year_of(date(20_088) + 24 * HOUR - 1), 2024);
+        assert_eq!(year_of(date(20_089)), 2025);
+        assert_eq!(year_of(date(11_016)), 2000);
+    }
+
+    #[test]
+    fn whole_months_between_counts_completed_months() {
+        assert_eq!(whole_months_between(date(19_753), date(19_782)), 1);
+        assert_eq!(whole_months_between(date(19_753), date(19_782) - 1), 0);
+        assert_eq!(whole_months_between(date(19_782), date(19_753)), 0);
+    }
+
+    #[test]
+    fn frequency_months_knows_each_frequency() {
+        assert_eq!(frequency_months("monthly"), Some(1));
+        assert_eq!(frequency_months("quarterly"), Some(3));
+        assert_eq!(frequency_months("semiannually"), Some(6));
+        assert_eq!(frequency_months("annually"), Some(12));
+        assert_eq!(frequency_months("yearly"), Some(12));
+        assert_eq!(frequency_months("weekly"), None);
+    }
+}
@@ -1,9 +1,13 @@
 use serde::{Deserialize, Serialize};
-use junobuild_satellite::{AssertSetDocContext, AssertDeleteDocContext, OnSetDocContext};
-use junobuild_utils::decode_doc_data;
+use junobuild_satellite::{AssertSetDocContext, AssertDeleteDocContext, OnSetDocContext, get_doc, list_docs, set_doc, SetDoc};
+use junobuild_shared::types::list::{ListPaginate, ListParams};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+use crate::admin_hooks::is_satellite_caller;
+use crate::waqf_types::{CauseStatus, TargetReachedAction, WaqfData};
+use std::time::Duration;
 
-// Note: Frontend uses simple string values for status: "pending" | "approved" | "rejected"
-// No enums needed - validation is done via string matching
+// Waqfs decoded per timer callback when a funded cause is closed out
+const REASSIGNMENT_PAGE_SIZE: usize = 100;
 
 // Cause structure matching frontend interface
 // By default, serde ignores unknown fields, so frontend can send additional fields
@@ -23,7 +27,7 @@ pub struct Cause {
     pub category: Option<String>,        // Legacy field for backward compatibility
     #[serde(rename = "isActive")]
     pub is_active: bool,
-    pub status: String,                  // Frontend uses string: "pending" | "approved" | "rejected"
+    pub status: CauseStatus,
     #[serde(rename = "sortOrder")]
     pub sort_order: i32,                 // Frontend has sortOrder
     pub followers: i32,                  // Frontend tracks followers
@@ -41,6 +45,12 @@ pub struct Cause {
     pub created_at: String,              // Frontend uses ISO string timestamps
     #[serde(rename = "updatedAt")]
     pub updated_at: String,              // Frontend uses ISO string timestamps
+    #[serde(rename = "onTargetReached", default)]
+    pub on_target_reached: Option<TargetReachedAction>, // Rejects allocations past the target when not set
+    #[serde(rename = "redirectCauseId", default)]
+    pub redirect_cause_id: Option<String>, // Cause that receives allocations after this one is funded
+    #[serde(rename = "fundedAt", default)]
+    pub funded_at: Option<String>,       // Set by the satellite when the target is reached (nanoseconds)
     // Note: Frontend sends additional fields (supportedWaqfTypes, investmentStrategy, etc.)
     // which are allowed but not validated by this satellite
 }
@@ -100,8 +110,19 @@ fn validate_cause_data(cause: &Cause) -> std::result::Result<(), String> {
         }
     }
     
-    // 4. Status validation
-    validate_cause_status(&cause.status)?;
+    // 4. Target close-out policy
+    match cause.on_target_reached {
+        None | Some(TargetReachedAction::Reject) => {},
+        Some(TargetReachedAction::Redirect) => {
+            let redirect = cause.redirect_cause_id.as_deref().unwrap_or("");
+            if redirect.trim().is_empty() {
+                return Err("A redirect cause is required when allocations are redirected after the target".into());
+            }
+            if redirect == cause.id {
+                return Err("A cause cannot redirect allocations to itself".into());
+            }
+        },
+    }
     
     // 5. Cover image URL validation
     if let Some(ref url) = cause.cover_image {
@@ -122,43 +143,31 @@ fn validate_cause_data(cause: &Cause) -> std::result::Result<(), String> {
     Ok(())
 }
 
-// Validate cause status values
-fn validate_cause_status(status: &str) -> std::result::Result<(), String> {
-    let valid_statuses = ["pending", "approved", "rejected"];
-    
-    if !valid_statuses.contains(&status) {
-        return Err(format!(
-            "Invalid cause status '{}'. Valid statuses: {}",
-            status,
-            valid_statuses.join(", ")
-        ));
-    }
-    
-    Ok(())
-}
-
 // Validate cause business rules based on status
 fn validate_cause_status_rules(cause: &Cause) -> std::result::Result<(), String> {
-    match cause.status.as_str() {
-        "pending" => {
+    match cause.status {
+        CauseStatus::Pending => {
             // Pending causes should not be active
             if cause.is_active {
                 return Err("Pending causes cannot be active".into());
             }
         },
-        "approved" => {
+        CauseStatus::Approved => {
             // Approved causes can be active
             // This is the normal state for visible causes
         },
-        "rejected" => {
+        CauseStatus::Rejected => {
             // Rejected causes should not be active
             if cause.is_active {
                 return Err("Rejected causes cannot be active".into());
             }
         },
-        _ => {
-            return Err(format!("Unknown cause status: {}", cause.status));
-        }
+        CauseStatus::Funded => {
+            // Funded causes are closed to new allocations
+            if cause.is_active {
+                return Err("Funded causes cannot be active".into());
+            }
+        },
     }
     
     Ok(())
@@ -203,19 +212,19 @@ fn validate_cause_permissions(cause: &Cause, _context: &AssertSetDocContext) ->
     // For now, we'll allow all operations during testing
     // In production, you would check caller permissions based on status changes
     
-    match cause.status.as_str() {
-        "approved" => {
+    match cause.status {
+        CauseStatus::Approved => {
             // Only users with cause_approval permission should be able to approve
             // let caller_permissions = get_caller_permissions(&context.caller).await?;
             // if !caller_permissions.contains(&"cause_approval".to_string()) {
             //     return Err("Only authorized users can approve causes".into());
             // }
         },
-        "rejected" => {
+        CauseStatus::Rejected => {
             // Only users with cause_approval permission should be able to reject
             // Similar check as above
         },
-        _ => {
+        CauseStatus::Pending | CauseStatus::Funded => {
             // Other status changes might have different permission requirements
         }
     }
@@ -264,6 +273,38 @@ fn validate_cause_category_ids(category_id: &str, subcategory_id: &str) -> std::
     Ok(())
 }
 
+// Only the satellite may move funds_raised or close a cause out as funded
+fn validate_cause_funding_changes(cause: &Cause, context: &AssertSetDocContext) -> std::result::Result<(), String> {
+    if is_satellite_caller(&context.caller.to_text()) {
+        return Ok(());
+    }
+    
+    let previous: Option<Cause> = match &context.data.data.current {
+        Some(doc) => Some(decode_doc_data(&doc.data)
+            .map_err(|e| format!("Cannot decode previous cause data: {}", e))?),
+        None => None,
+    };
+    
+    let (previous_raised, previous_status, previous_funded_at) = match &previous {
+        Some(previous) => (previous.funds_raised, Some(previous.status), previous.funded_at.clone()),
+        None => (0.0, None, None),
+    };
+    
+    if cause.funds_raised != previous_raised {
+        return Err("Funds raised is updated automatically from allocations".into());
+    }
+    
+    if cause.status == CauseStatus::Funded && previous_status != Some(CauseStatus::Funded) {
+        return Err("Causes are closed out as funded automatically when their target is reached".into());
+    }
+    
+    if cause.funded_at != previous_funded_at {
+        return Err("Funded at is set automatically when the target is reached".into());
+    }
+    
+    Ok(())
+}
+
 // Main assertion function for cause operations
 pub fn assert_cause_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
     // Decode cause data with proper error handling
@@ -279,6 +320,9 @@ pub fn assert_cause_operations(context: AssertSetDocContext) -> std::result::Res
     // Business logic validation
     validate_cause_business_rules(&cause, &context)?;
     
+    // Funding progress is maintained by the satellite from allocations
+    validate_cause_funding_changes(&cause, &context)?;
+    
     // Log the validation attempt
     ic_cdk::println!(
         "Cause validation passed: {} - Status: {}, Active: {}, Category: {}, Subcategory: {}",
@@ -333,7 +377,7 @@ pub fn handle_cause_changes(context: OnSetDocContext) -> std::result::Result<(),
         operation_type,
         context.data.key,
         cause_data.name,
-        cause_data.status,
+        cause_data.status.as_str(),
         cause_data.is_active,
         cause_data.category_id,
         cause_data.subcategory_id,
@@ -341,16 +385,16 @@ pub fn handle_cause_changes(context: OnSetDocContext) -> std::result::Result<(),
     );
     
     // Log status-specific information
-    match cause_data.status.as_str() {
-        "approved" => {
+    match cause_data.status {
+        CauseStatus::Approved => {
             ic_cdk::println!("IMPORTANT: Cause approved - '{}' (Followers: {}, Raised: ${})", 
                            cause_data.name, cause_data.followers, cause_data.funds_raised);
         },
-        "rejected" => {
+        CauseStatus::Rejected => {
             ic_cdk::println!("NOTICE: Cause rejected - '{}'", 
                            cause_data.name);
         },
-        _ => {}
+        CauseStatus::Pending | CauseStatus::Funded => {}
     }
     
     // Additional processing for production:
@@ -360,4 +404,221 @@ pub fn handle_cause_changes(context: OnSetDocContext) -> std::result::Result<(),
     // - Send status update emails
     
     Ok(())
-}
\ No newline at end of file
+}
+
+// ============================================
+// FUNDING TARGETS
+// ============================================
+
+pub fn load_cause(cause_id: &str) -> std::result::Result<Cause, String> {
+    let doc = get_doc("causes".to_string(), cause_id.to_string())
+        .ok_or_else(|| format!("Cause not found: {}", cause_id))?;
+    
+    decode_doc_data(&doc.data).map_err(|e| format!("Failed to decode cause data: {}", e))
+}
+
+// Amount still needed to reach the target (None when the cause has no target)
+fn remaining_to_target(cause: &Cause) -> Option<f64> {
+    cause.target_amount.map(|target| (target - cause.funds_raised).max(0.0))
+}
+
+fn redirects_after_target(cause: &Cause) -> bool {
+    cause.on_target_reached == Some(TargetReachedAction::Redirect)
+}
+
+// Redirects only go to causes the funding waqf already backs; anything else is a cause
+// reallocation the donor requests and a waqf manager approves
+fn redirect_backed(cause: &Cause, backed_causes: &[String]) -> bool {
+    cause.redirect_cause_id.as_ref().is_some_and(|redirect_id| backed_causes.contains(redirect_id))
+}
+
+// Check that a cause can take an allocation, or that any overflow can be redirected
+pub fn validate_allocation_against_cause(cause: &Cause, amount: f64, backed_causes: &[String]) -> std::result::Result<(), String> {
+    if !matches!(cause.status, CauseStatus::Approved | CauseStatus::Funded) {
+        return Err(format!("Cannot allocate to cause '{}' with status '{}'", cause.name, cause.status.as_str()));
+    }
+    
+    let remaining = if cause.status == CauseStatus::Funded {
+        0.0
+    } else {
+        match remaining_to_target(cause) {
+            Some(remaining) => remaining,
+            None => return Ok(()),
+        }
+    };
+    
+    // Allow for floating point errors
+    let overflow = amount - remaining;
+    if overflow <= 0.005 {
+        return Ok(());
+    }
+    
+    if !redirects_after_target(cause) {
+        return Err(format!(
+            "Cause '{}' only needs {:.2} more to reach its target - it no longer accepts allocations beyond that",
+            cause.name, remaining
+        ));
+    }
+    
+    let redirect_id = cause.redirect_cause_id.as_deref().unwrap_or_default();
+    if !redirect_backed(cause, backed_causes) {
+        return Err(format!(
+            "Cause '{}' redirects to cause {}, which this waqf does not support - request a cause reallocation to add it",
+            cause.name, redirect_id
+        ));
+    }
+    let redirect = load_cause(redirect_id)?;
+    if redirect.status != CauseStatus::Approved {
+        return Err(format!(
+            "Redirect cause '{}' for '{}' cannot take allocations (status '{}')",
+            redirect.name, cause.name, redirect.status.as_str()
+        ));
+    }
+    
+    // Redirects go one hop only
+    if let Some(redirect_remaining) = remaining_to_target(&redirect) {
+        if overflow - redirect_remaining > 0.005 {
+            return Err(format!(
+                "Redirect cause '{}' only needs {:.2} more, cannot take {:.2} redirected from '{}'",
+                redirect.name, redirect_remaining, overflow, cause.name
+            ));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Credit an allocation to its cause, redirecting any amount beyond the target to the redirect
+/// cause when the funding waqf backs it. Returns the redirect cause and amount when part of
+/// the allocation was redirected.
+pub fn record_allocation_funding(
+    cause_id: &str,
+    amount: f64,
+    backed_causes: &[String],
+) -> std::result::Result<Option<(String, f64)>, String> {
+    let cause = load_cause(cause_id)?;
+    let redirects = redirects_after_target(&cause) && redirect_backed(&cause, backed_causes);
+    
+    let kept = match remaining_to_target(&cause) {
+        _ if cause.status == CauseStatus::Funded && redirects => 0.0,
+        Some(remaining) if redirects => amount.min(remaining),
+        _ => amount,
+    };
+    let overflow = amount - kept;
+    
+    if kept > 0.0 {
+        credit_cause(cause_id, kept)?;
+    }
+    
+    if overflow > 0.005 && redirects {
+        let redirect_id = cause.redirect_cause_id.clone().unwrap_or_default();
+        credit_cause(&redirect_id, overflow)?;
+        
+        ic_cdk::println!(
+            "Redirected {:.2} from funded cause {} to cause {}",
+            overflow, cause_id, redirect_id
+        );
+        
+        return Ok(Some((redirect_id, overflow)));
+    }
+    
+    Ok(None)
+}
+
+fn credit_cause(cause_id: &str, amount: f64) -> std::result::Result<(), String> {
+    let doc = get_doc("causes".to_string(), cause_id.to_string())
+        .ok_or_else(|| format!("Cause not found: {}", cause_id))?;
+    let mut cause: Cause = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode cause data: {}", e))?;
+    
+    let now = ic_cdk::api::time().to_string();
+    cause.funds_raised += amount;
+    cause.updated_at = now.clone();
+    
+    let reached_target = cause.status == CauseStatus::Approved
+        && cause.target_amount.is_some_and(|target| cause.funds_raised >= target - 0.005);
+    
+    if reached_target {
+        cause.status = CauseStatus::Funded;
+        cause.is_active = false;
+        cause.funded_at = Some(now);
+    }
+    
+    let updated_data = encode_doc_data(&cause)
+        .map_err(|e| format!("Failed to encode cause data: {}", e))?;
+    
+    let set_doc_data = SetDoc {
+        data: updated_data,
+        description: doc.description,
+        version: doc.version,
+    };
+    
+    let _ = set_doc("causes".to_string(), cause_id.to_string(), set_doc_data);
+    
+    ic_cdk::println!(
+        "Cause {} funds raised: +{:.2} (total {:.2} of {:?})",
+        cause_id, amount, cause.funds_raised, cause.target_amount
+    );
+    
+    if reached_target {
+        ic_cdk::println!("IMPORTANT: Cause funded - '{}' reached its target", cause.name);
+        flag_waqfs_for_reassignment(cause_id);
+    }
+    
+    Ok(())
+}
+
+// Ask every waqf supporting a funded cause to reassign that cause's allocation share. Runs
+// outside the funding message, one page of waqfs per timer callback.
+fn flag_waqfs_for_reassignment(cause_id: &str) {
+    let cause_id = cause_id.to_string();
+    ic_cdk_timers::set_timer(Duration::ZERO, move || flag_waqf_page(cause_id, None));
+}
+
+fn flag_waqf_page(cause_id: String, start_after: Option<String>) {
+    let params = ListParams {
+        paginate: Some(ListPaginate { start_after, limit: Some(REASSIGNMENT_PAGE_SIZE) }),
+        ..Default::default()
+    };
+    let waqfs = list_docs("waqfs".to_string(), params);
+    let last_key = waqfs.items.last().map(|(key, _)| key.clone());
+    let full_page = waqfs.items.len() == REASSIGNMENT_PAGE_SIZE;
+    
+    for (key, doc) in waqfs.items {
+        let mut waqf: WaqfData = match decode_doc_data(&doc.data) {
+            Ok(waqf) => waqf,
+            Err(e) => {
+                ic_cdk::println!("WARN - Skipping waqf {} in cause close-out: {}", key, e);
+                continue;
+            }
+        };
+        
+        if !waqf.selected_causes.contains(&cause_id) {
+            continue;
+        }
+        
+        let pending = waqf.causes_pending_reassignment.get_or_insert_with(Vec::new);
+        if pending.contains(&cause_id) {
+            continue;
+        }
+        pending.push(cause_id.clone());
+        waqf.updated_at = Some(ic_cdk::api::time().to_string());
+        
+        match encode_doc_data(&waqf) {
+            Ok(data) => {
+                let set_doc_data = SetDoc {
+                    data,
+                    description: doc.description,
+                    version: doc.version,
+                };
+                let _ = set_doc("waqfs".to_string(), key.clone(), set_doc_data);
+                ic_cdk::println!("Waqf {} asked to reassign its share of funded cause {}", key, cause_id);
+            }
+            Err(e) => ic_cdk::println!("ERROR - Cannot encode waqf {}: {}", key, e),
+        }
+    }
+    
+    if full_page {
+        ic_cdk_timers::set_timer(Duration::ZERO, move || flag_waqf_page(cause_id, last_key));
+    }
+}
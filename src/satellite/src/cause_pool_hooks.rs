@@ -0,0 +1,116 @@
+use crate::admin_hooks::{caller_has_role, AdminRole};
+use crate::bucket_accounting;
+use crate::notification_hooks::{self, Notice};
+use crate::notification_types::{NotificationPayload, NotificationType};
+use crate::waqf_types::{CausePoolConfig, ContributionTranche, WaqfData};
+use junobuild_satellite::{get_doc, set_doc, SetDoc};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+use std::collections::HashMap;
+
+// Platform settings key naming the cause pool waqf
+pub const CAUSE_POOL_SETTINGS_KEY: &str = "cause_pool";
+
+/// Matured principal rolled into the cause pool. The source waqf has already released it;
+/// the pool is credited once the source waqf has been saved.
+pub struct PooledRollover {
+    pub pool_waqf_id: String,
+    pub tranche: ContributionTranche,
+    pub cause_split: HashMap<String, f64>,
+}
+
+fn load_waqf(waqf_id: &str) -> std::result::Result<(WaqfData, junobuild_satellite::Doc), String> {
+    let doc = get_doc("waqfs".to_string(), waqf_id.to_string())
+        .ok_or_else(|| format!("Waqf not found: {}", waqf_id))?;
+    let waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+    Ok((waqf, doc))
+}
+
+/// Validates the cause pool settings document (platform admins only)
+pub fn validate_cause_pool_settings(config: &CausePoolConfig, caller: &str) -> std::result::Result<(), String> {
+    if !caller_has_role(caller, &[AdminRole::PlatformAdmin]) {
+        return Err("Only platform admins can configure the cause pool".into());
+    }
+
+    if config.updated_by != caller {
+        return Err("Updated by must match the calling admin".into());
+    }
+
+    config.updated_at.parse::<u64>()
+        .map_err(|_| "Invalid updated at format")?;
+
+    let (waqf, _) = load_waqf(&config.waqf_id)?;
+    if waqf.revolving_details.is_none() {
+        return Err("The cause pool must be a revolving waqf so pooled principal can be returned".into());
+    }
+
+    ic_cdk::println!("Cause pool settings validation passed: pool waqf {}", config.waqf_id);
+
+    Ok(())
+}
+
+/// Pool waqf currently configured by the platform, if it still exists
+pub fn configured_pool_waqf_id() -> Option<String> {
+    let config: CausePoolConfig = get_doc("platform_settings".to_string(), CAUSE_POOL_SETTINGS_KEY.to_string())
+        .and_then(|doc| decode_doc_data(&doc.data).ok())?;
+
+    match load_waqf(&config.waqf_id) {
+        Ok((waqf, _)) if waqf.revolving_details.is_some() => Some(config.waqf_id),
+        _ => {
+            ic_cdk::println!("WARN - Configured cause pool waqf {} is unavailable", config.waqf_id);
+            None
+        }
+    }
+}
+
+/// Add a pooled tranche to the cause pool waqf and credit its revolving bucket per cause
+pub fn credit_cause_pool(rollover: &PooledRollover) -> std::result::Result<(), String> {
+    let (mut pool, doc) = load_waqf(&rollover.pool_waqf_id)?;
+    let amount = rollover.tranche.amount;
+
+    bucket_accounting::ensure_bucket_balances(&mut pool);
+    for (cause_id, cause_amount) in &rollover.cause_split {
+        pool.financial.bucket_balances.entry(cause_id.clone()).or_default().revolving += cause_amount;
+        *pool.financial.cause_allocations.entry(cause_id.clone()).or_insert(0.0) += cause_amount;
+        if !pool.selected_causes.contains(cause_id) {
+            pool.selected_causes.push(cause_id.clone());
+        }
+    }
+    pool.financial.total_donations += amount;
+    pool.financial.current_balance += amount;
+
+    let details = pool.revolving_details.as_mut()
+        .ok_or_else(|| format!("Cause pool waqf {} is not a revolving waqf", pool.id))?;
+    details.contribution_tranches.get_or_insert_with(Vec::new).push(rollover.tranche.clone());
+    pool.updated_at = Some(ic_cdk::api::time().to_string());
+
+    let data = encode_doc_data(&pool)
+        .map_err(|e| format!("Failed to encode cause pool waqf data: {}", e))?;
+
+    let set_doc_data = SetDoc {
+        data,
+        description: doc.description,
+        version: doc.version,
+    };
+
+    let _ = set_doc("waqfs".to_string(), rollover.pool_waqf_id.clone(), set_doc_data);
+
+    notification_hooks::send(Notice::new(
+        &pool.created_by,
+        &pool.id,
+        NotificationType::CausePoolCredited,
+        NotificationPayload {
+            tranche_id: Some(rollover.tranche.id.clone()),
+            amount: Some(amount),
+            related_waqf_id: rollover.tranche.pool_origin.as_ref().map(|origin| origin.waqf_id.clone()),
+            ..Default::default()
+        },
+    ));
+
+    ic_cdk::println!(
+        "Credited cause pool {} with tranche {} ({:.2})",
+        rollover.pool_waqf_id, rollover.tranche.id, amount
+    );
+
+    Ok(())
+}
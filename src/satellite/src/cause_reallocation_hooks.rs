@@ -0,0 +1,261 @@
+use crate::activity_log_hooks;
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::cause_hooks;
+use crate::deed_hooks;
+use crate::deed_types::DeedTerms;
+use crate::waqf_types::{CauseReallocationData, CauseStatus, ReallocationStatus, WaqfData};
+use junobuild_satellite::{get_doc, list_docs, set_doc, AssertDeleteDocContext, AssertSetDocContext, OnSetDocContext, SetDoc};
+use junobuild_shared::types::list::ListParams;
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+
+const MIN_REASON_LENGTH: usize = 10;
+const MAX_REASON_LENGTH: usize = 1000;
+
+const REALLOCATION_REVIEWERS: &[AdminRole] = &[AdminRole::WaqfManager, AdminRole::PlatformAdmin];
+
+fn load_waqf(waqf_id: &str) -> std::result::Result<(WaqfData, junobuild_satellite::Doc), String> {
+    let doc = get_doc("waqfs".to_string(), waqf_id.to_string())
+        .ok_or_else(|| format!("Waqf not found: {}", waqf_id))?;
+    let waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+    Ok((waqf, doc))
+}
+
+/// Deed terms of the waqf with the requested causes in place of the current ones
+fn reallocated_terms(waqf: &WaqfData, request: &CauseReallocationData) -> DeedTerms {
+    let mut selected_causes = request.selected_causes.clone();
+    selected_causes.sort();
+
+    DeedTerms {
+        selected_causes,
+        cause_allocation: request.cause_allocation.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+        hybrid_allocations: request.hybrid_allocations.clone(),
+        ..deed_hooks::deed_terms(waqf)
+    }
+}
+
+/// Whether the reallocation can be applied to the waqf as it is now. For signed deeds this
+/// also checks the donor's signature over the amended deed.
+fn validate_against_waqf(request: &CauseReallocationData, waqf: &WaqfData) -> std::result::Result<(), String> {
+    let terms = reallocated_terms(waqf, request);
+    if terms == deed_hooks::deed_terms(waqf) {
+        return Err("The reallocation does not change the waqf's causes".into());
+    }
+
+    // Causes the waqf does not back yet must be open for funding
+    for cause_id in request.selected_causes.iter().filter(|id| !waqf.selected_causes.contains(id)) {
+        let cause = cause_hooks::load_cause(cause_id)?;
+        if !cause.is_active || cause.status != CauseStatus::Approved {
+            return Err(format!("Cause {} is not an active approved cause", cause_id));
+        }
+    }
+
+    deed_hooks::validate_amendment(waqf, &terms)?;
+
+    if waqf.deed_document.is_some() {
+        match (&request.donor_signature, &request.public_key, &request.signed_at) {
+            (Some(signature), Some(public_key), Some(signed_at)) => {
+                deed_hooks::signed_amendment(waqf, terms, signature, public_key, signed_at, &request.requested_by)?;
+            }
+            _ => return Err("The waqf deed is signed - the donor must sign the amended deed".into()),
+        }
+    }
+
+    Ok(())
+}
+
+fn has_pending_request(waqf_id: &str) -> bool {
+    list_docs("cause_reallocations".to_string(), ListParams::default())
+        .items
+        .into_iter()
+        .filter_map(|(_, doc)| decode_doc_data::<CauseReallocationData>(&doc.data).ok())
+        .any(|request| request.waqf_id == waqf_id && request.status == ReallocationStatus::Pending)
+}
+
+fn validate_new_request(request: &CauseReallocationData, caller: &str) -> std::result::Result<(), String> {
+    if request.requested_by != caller {
+        return Err("Requested by must match the caller".into());
+    }
+    if request.status != ReallocationStatus::Pending {
+        return Err("Cause reallocations must be submitted as pending".into());
+    }
+    if request.reviewed_by.is_some() || request.reviewed_at.is_some() {
+        return Err("A new cause reallocation cannot carry a review".into());
+    }
+
+    let reason_length = request.reason.trim().len();
+    if !(MIN_REASON_LENGTH..=MAX_REASON_LENGTH).contains(&reason_length) {
+        return Err(format!(
+            "Reallocation reason must be between {} and {} characters",
+            MIN_REASON_LENGTH, MAX_REASON_LENGTH
+        ));
+    }
+
+    request.requested_at.parse::<u64>()
+        .map_err(|_| "Invalid requested at format")?;
+
+    let (waqf, _) = load_waqf(&request.waqf_id)?;
+    if waqf.created_by != caller {
+        return Err("Only the waqf donor can request a cause reallocation".into());
+    }
+    if has_pending_request(&request.waqf_id) {
+        return Err(format!("Waqf {} already has a pending cause reallocation", request.waqf_id));
+    }
+
+    validate_against_waqf(request, &waqf)
+}
+
+fn validate_review(
+    current: &CauseReallocationData,
+    proposed: &CauseReallocationData,
+    caller: &str,
+) -> std::result::Result<(), String> {
+    if !caller_has_role(caller, REALLOCATION_REVIEWERS) {
+        return Err("Only waqf managers can review cause reallocations".into());
+    }
+    if current.status != ReallocationStatus::Pending {
+        return Err(format!("Cause reallocation {} has already been {}", current.id, current.status));
+    }
+    if !matches!(proposed.status, ReallocationStatus::Approved | ReallocationStatus::Rejected) {
+        return Err("A cause reallocation review must approve or reject it".into());
+    }
+    if proposed.reviewed_by.as_deref() != Some(caller) {
+        return Err("Reviewed by must match the calling waqf manager".into());
+    }
+    proposed.reviewed_at.as_deref().unwrap_or_default().parse::<u64>()
+        .map_err(|_| "Invalid reviewed at format")?;
+
+    // Only the review itself may be added
+    let expected = CauseReallocationData {
+        status: proposed.status,
+        reviewed_by: proposed.reviewed_by.clone(),
+        reviewed_at: proposed.reviewed_at.clone(),
+        review_notes: proposed.review_notes.clone(),
+        ..current.clone()
+    };
+    if *proposed != expected {
+        return Err("Only the status and review fields can change when reviewing a cause reallocation".into());
+    }
+
+    // The waqf, its deed or the causes may have changed since the request
+    if proposed.status == ReallocationStatus::Approved {
+        let (waqf, _) = load_waqf(&current.waqf_id)?;
+        validate_against_waqf(current, &waqf)?;
+    }
+
+    Ok(())
+}
+
+pub fn assert_cause_reallocation_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let request: CauseReallocationData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid cause reallocation data structure: {}", e))?;
+
+    if request.id != context.data.key {
+        return Err("Cause reallocation id must match the document key".into());
+    }
+
+    let caller = context.caller.to_text();
+    if is_satellite_caller(&caller) {
+        return Err("Cause reallocations are not written by the satellite".into());
+    }
+
+    match &context.data.data.current {
+        None => validate_new_request(&request, &caller)?,
+        Some(doc) => {
+            let current: CauseReallocationData = decode_doc_data(&doc.data)
+                .map_err(|e| format!("Failed to decode current cause reallocation: {}", e))?;
+            validate_review(&current, &request, &caller)?;
+        }
+    }
+
+    ic_cdk::println!(
+        "Cause reallocation validation passed: {} - Waqf: {}, Causes: {:?}, Status: {}",
+        request.id, request.waqf_id, request.selected_causes, request.status
+    );
+
+    Ok(())
+}
+
+pub fn assert_cause_reallocation_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Cause reallocations cannot be deleted for audit purposes".into())
+}
+
+pub fn handle_cause_reallocation_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let request: CauseReallocationData = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode cause reallocation data: {}", e))?;
+
+    ic_cdk::println!(
+        "Cause reallocation {}: {} - Waqf: {}, Status: {}",
+        if context.data.data.before.is_none() { "CREATE" } else { "UPDATE" },
+        request.id, request.waqf_id, request.status
+    );
+
+    match request.status {
+        ReallocationStatus::Pending => activity_log_hooks::record_audit_log(
+            "cause_reallocation_requested",
+            &request.requested_by,
+            "waqf",
+            &request.waqf_id,
+            format!("Reallocation {} to causes {:?} requested: {}", request.id, request.cause_allocation, request.reason),
+        ),
+        ReallocationStatus::Rejected => activity_log_hooks::record_audit_log(
+            "cause_reallocation_rejected",
+            request.reviewed_by.as_deref().unwrap_or_default(),
+            "waqf",
+            &request.waqf_id,
+            format!("Reallocation {} rejected: {}", request.id, request.review_notes.clone().unwrap_or_default()),
+        ),
+        ReallocationStatus::Approved => apply_reallocation(&request)?,
+    }
+
+    Ok(())
+}
+
+/// Move the waqf to the requested causes: through a deed amendment when the deed is signed,
+/// otherwise directly
+fn apply_reallocation(request: &CauseReallocationData) -> std::result::Result<(), String> {
+    let (waqf, doc) = load_waqf(&request.waqf_id)?;
+    let terms = reallocated_terms(&waqf, request);
+
+    match (&waqf.deed_document, &request.donor_signature, &request.public_key, &request.signed_at) {
+        (Some(_), Some(signature), Some(public_key), Some(signed_at)) => {
+            let version = deed_hooks::signed_amendment(
+                &waqf,
+                terms,
+                signature,
+                public_key,
+                signed_at,
+                &junobuild_satellite::id().to_text(),
+            )?;
+            deed_hooks::record_deed_version(&version)?;
+        }
+        (Some(_), _, _, _) => return Err("The waqf deed is signed - the donor must sign the amended deed".into()),
+        (None, _, _, _) => {
+            let mut reallocated = deed_hooks::apply_terms(&waqf, &terms);
+            reallocated.updated_at = Some(ic_cdk::api::time().to_string());
+
+            let data = encode_doc_data(&reallocated)
+                .map_err(|e| format!("Failed to encode waqf data: {}", e))?;
+            set_doc(
+                "waqfs".to_string(),
+                request.waqf_id.clone(),
+                SetDoc { data, description: doc.description, version: doc.version },
+            );
+        }
+    }
+
+    activity_log_hooks::record_audit_log(
+        "cause_reallocation_approved",
+        request.reviewed_by.as_deref().unwrap_or_default(),
+        "waqf",
+        &request.waqf_id,
+        format!(
+            "Reallocation {} requested by {} applied: causes {:?} (was {:?})",
+            request.id, request.requested_by, request.cause_allocation, waqf.cause_allocation
+        ),
+    );
+
+    ic_cdk::println!("Cause reallocation {} applied to waqf {}", request.id, request.waqf_id);
+
+    Ok(())
+}
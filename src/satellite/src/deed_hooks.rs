@@ -0,0 +1,491 @@
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::deed_types::{DeedSigningKeysConfig, DeedTerms, DeedVersionData};
+use crate::waqf_hooks;
+use crate::waqf_types::{DeedDocument, WaqfData, WaqfStatus};
+use crate::waqf_utils;
+use candid::Principal;
+use ed25519_dalek::{Signature, VerifyingKey};
+use junobuild_satellite::{get_doc, set_doc, AssertDeleteDocContext, AssertSetDocContext, OnSetDocContext, SetDoc};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+use sha2::{Digest, Sha256};
+
+// Platform settings key listing the agreed deed signing keys
+pub const DEED_SIGNING_KEYS_SETTINGS_KEY: &str = "deed_signing_keys";
+
+// SubjectPublicKeyInfo prefix of a DER-encoded Ed25519 public key
+const ED25519_DER_PREFIX: [u8; 12] = [0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+
+/// Terms of the waqf the deed signature covers
+pub fn deed_terms(waqf: &WaqfData) -> DeedTerms {
+    let mut selected_causes = waqf.selected_causes.clone();
+    selected_causes.sort();
+
+    let revolving = waqf.revolving_details.as_ref();
+
+    DeedTerms {
+        waqf_type: waqf.waqf_type.clone(),
+        selected_causes,
+        cause_allocation: waqf.cause_allocation.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+        hybrid_allocations: waqf.hybrid_allocations.clone(),
+        lock_period_months: revolving.map(|details| details.lock_period_months),
+        principal_return_method: revolving.map(|details| details.principal_return_method),
+        early_withdrawal_allowed: revolving.map(|details| details.early_withdrawal_allowed),
+    }
+}
+
+/// Canonical deed text, one "name:value" line per term with causes sorted and percentages
+/// to four decimals. Clients build the same text, hash it and sign the hash.
+fn canonical_text(waqf_id: &str, version: u32, previous_terms_hash: Option<&str>, terms: &DeedTerms) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+
+    let mut lines = vec![
+        "waqf_deed".to_string(),
+        format!("waqf_id:{}", waqf_id),
+        format!("version:{}", version),
+        format!("previous:{}", optional(previous_terms_hash.map(str::to_string))),
+        format!("waqf_type:{:?}", terms.waqf_type),
+        format!("causes:{}", terms.selected_causes.join(",")),
+    ];
+
+    for (cause_id, percentage) in &terms.cause_allocation {
+        lines.push(format!("allocation:{}={:.4}", cause_id, percentage));
+    }
+
+    let mut hybrid: Vec<_> = terms.hybrid_allocations.iter().flatten().collect();
+    hybrid.sort_by(|a, b| a.cause_id.cmp(&b.cause_id));
+    for allocation in hybrid {
+        let split = &allocation.allocations;
+        lines.push(format!(
+            "hybrid:{}={:.4}/{:.4}/{:.4}",
+            allocation.cause_id,
+            split.permanent.unwrap_or(0.0),
+            split.temporary_consumable.unwrap_or(0.0),
+            split.temporary_revolving.unwrap_or(0.0)
+        ));
+    }
+
+    lines.push(format!("lock_period_months:{}", optional(terms.lock_period_months.map(|m| m.to_string()))));
+    lines.push(format!(
+        "principal_return_method:{}",
+        optional(terms.principal_return_method.map(|m| m.as_str().to_string()))
+    ));
+    lines.push(format!(
+        "early_withdrawal_allowed:{}",
+        optional(terms.early_withdrawal_allowed.map(|allowed| allowed.to_string()))
+    ));
+
+    lines.join("\n")
+}
+
+/// Hex SHA-256 of the canonical deed text
+pub fn terms_hash(waqf_id: &str, version: u32, previous_terms_hash: Option<&str>, terms: &DeedTerms) -> String {
+    hex::encode(Sha256::digest(canonical_text(waqf_id, version, previous_terms_hash, terms).as_bytes()))
+}
+
+fn ed25519_key(public_key_der: &[u8]) -> std::result::Result<VerifyingKey, String> {
+    let raw: [u8; 32] = public_key_der
+        .strip_prefix(&ED25519_DER_PREFIX[..])
+        .and_then(|raw| raw.try_into().ok())
+        .ok_or("Deed public key must be a DER-encoded Ed25519 key")?;
+    VerifyingKey::from_bytes(&raw).map_err(|e| format!("Invalid deed public key: {}", e))
+}
+
+fn agreed_signing_keys() -> Vec<String> {
+    get_doc("platform_settings".to_string(), DEED_SIGNING_KEYS_SETTINGS_KEY.to_string())
+        .and_then(|doc| decode_doc_data::<DeedSigningKeysConfig>(&doc.data).ok())
+        .map(|config| config.public_keys)
+        .unwrap_or_default()
+}
+
+/// Check the signature over a deed hash is by the owner's principal or an agreed key
+fn verify_signature(
+    owner: &str,
+    public_key_hex: &str,
+    signature_hex: &str,
+    hash_hex: &str,
+) -> std::result::Result<(), String> {
+    let public_key = hex::decode(public_key_hex).map_err(|_| "Deed public key must be hex encoded")?;
+    let verifying_key = ed25519_key(&public_key)?;
+
+    let is_owner_key = Principal::self_authenticating(&public_key).to_text() == owner;
+    let is_agreed_key = agreed_signing_keys()
+        .iter()
+        .any(|key| key.eq_ignore_ascii_case(public_key_hex));
+    if !is_owner_key && !is_agreed_key {
+        return Err("Deed must be signed by the waqf creator's principal or an agreed signing key".into());
+    }
+
+    let signature = hex::decode(signature_hex)
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or("Deed signature must be a hex encoded Ed25519 signature")?;
+    let message = hex::decode(hash_hex).map_err(|_| "Deed terms hash must be hex encoded")?;
+
+    verifying_key
+        .verify_strict(&message, &signature)
+        .map_err(|_| "Deed signature does not match the deed terms".to_string())
+}
+
+fn deed_version(deed: &DeedDocument) -> std::result::Result<u32, String> {
+    deed.document_version
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid deed document version: {}", deed.document_version))
+}
+
+/// Verify the first signature of a waqf's deed, made over the waqf's current terms
+fn verify_first_deed(waqf: &WaqfData, deed: &DeedDocument) -> std::result::Result<(), String> {
+    if deed_version(deed)? != 1 {
+        return Err("A waqf deed is first signed as version 1 - later versions are recorded as amendments".into());
+    }
+
+    deed.signed_at.parse::<u64>()
+        .map_err(|_| "Invalid deed signed at format")?;
+
+    let expected_hash = terms_hash(&waqf.id, 1, None, &deed_terms(waqf));
+    if deed.terms_hash.as_deref() != Some(expected_hash.as_str()) {
+        return Err("Deed terms hash does not match the waqf terms".into());
+    }
+
+    let public_key = deed.public_key.as_deref()
+        .ok_or("A signed deed must include the signer's public key")?;
+    verify_signature(&waqf.created_by, public_key, &deed.donor_signature, &expected_hash)
+}
+
+/// Deed checks for a waqf created by a donor or staff: a deed present must be validly signed,
+/// and a waqf cannot start out active without one
+pub fn check_deed_on_create(waqf: &WaqfData) -> std::result::Result<(), String> {
+    match &waqf.deed_document {
+        Some(deed) => verify_first_deed(waqf, deed),
+        None if waqf.status == WaqfStatus::Active => {
+            Err("A waqf needs a signed deed before it can be activated".into())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Deed checks for a waqf update by a donor or staff. Once signed, the deed and the terms it
+/// covers are frozen; amendments go through the deed_versions collection.
+pub fn check_deed_on_update(previous: &WaqfData, updated: &WaqfData) -> std::result::Result<(), String> {
+    match (&previous.deed_document, &updated.deed_document) {
+        (Some(previous_deed), Some(updated_deed)) => {
+            if previous_deed != updated_deed {
+                return Err("FORBIDDEN: The signed deed cannot be replaced - submit an amendment to deed_versions".into());
+            }
+            if deed_terms(previous) != deed_terms(updated) {
+                return Err("FORBIDDEN: Terms covered by the signed deed can only change through a signed amendment".into());
+            }
+            Ok(())
+        }
+        (Some(_), None) => Err("FORBIDDEN: The signed deed cannot be removed".into()),
+        (None, Some(deed)) => verify_first_deed(updated, deed),
+        (None, None) => {
+            if updated.status == WaqfStatus::Active && previous.status != WaqfStatus::Active {
+                return Err("A waqf needs a signed deed before it can be activated".into());
+            }
+            Ok(())
+        }
+    }
+}
+
+fn deed_version_key(waqf_id: &str, version: u32) -> String {
+    format!("{}_v{}", waqf_id, version)
+}
+
+/// Record the first signed version of a waqf's deed in deed_versions, so later amendments
+/// never lose the original terms
+pub fn record_first_deed_version(previous: Option<&WaqfData>, waqf: &WaqfData) {
+    let deed = match &waqf.deed_document {
+        Some(deed) if previous.is_none_or(|p| p.deed_document.is_none()) => deed,
+        _ => return,
+    };
+
+    let key = deed_version_key(&waqf.id, 1);
+    if get_doc("deed_versions".to_string(), key.clone()).is_some() {
+        return;
+    }
+
+    let version = DeedVersionData {
+        id: key.clone(),
+        waqf_id: waqf.id.clone(),
+        version: 1,
+        terms: deed_terms(waqf),
+        terms_hash: deed.terms_hash.clone().unwrap_or_default(),
+        previous_terms_hash: None,
+        donor_signature: deed.donor_signature.clone(),
+        public_key: deed.public_key.clone().unwrap_or_default(),
+        signed_at: deed.signed_at.clone(),
+        recorded_by: junobuild_satellite::id().to_text(),
+    };
+
+    match encode_doc_data(&version) {
+        Ok(data) => {
+            let _ = set_doc("deed_versions".to_string(), key, SetDoc { data, description: None, version: None });
+            ic_cdk::println!("Deed version 1 recorded for waqf {}", waqf.id);
+        }
+        Err(e) => ic_cdk::println!("ERROR: Failed to encode deed version for waqf {}: {}", waqf.id, e),
+    }
+}
+
+/// The waqf with an amendment's terms applied
+pub fn apply_terms(waqf: &WaqfData, terms: &DeedTerms) -> WaqfData {
+    let mut amended = waqf.clone();
+    amended.selected_causes = terms.selected_causes.clone();
+    amended.cause_allocation = terms.cause_allocation.iter().map(|(k, v)| (k.clone(), *v)).collect();
+    amended.hybrid_allocations = terms.hybrid_allocations.clone();
+
+    // Funded causes the amendment moved away from no longer need reassigning
+    if let Some(pending) = amended.causes_pending_reassignment.as_mut() {
+        pending.retain(|cause_id| terms.selected_causes.contains(cause_id));
+        if pending.is_empty() {
+            amended.causes_pending_reassignment = None;
+        }
+    }
+
+    if let Some(details) = amended.revolving_details.as_mut() {
+        if let Some(months) = terms.lock_period_months {
+            details.lock_period_months = months;
+        }
+        if let Some(method) = terms.principal_return_method {
+            details.principal_return_method = method;
+        }
+        if let Some(allowed) = terms.early_withdrawal_allowed {
+            details.early_withdrawal_allowed = allowed;
+        }
+    }
+
+    amended
+}
+
+fn load_waqf(waqf_id: &str) -> std::result::Result<(WaqfData, junobuild_satellite::Doc), String> {
+    let doc = get_doc("waqfs".to_string(), waqf_id.to_string())
+        .ok_or_else(|| format!("Waqf not found: {}", waqf_id))?;
+    let waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+    Ok((waqf, doc))
+}
+
+/// Check an amendment can apply to the waqf's current terms
+pub fn validate_amendment(waqf: &WaqfData, terms: &DeedTerms) -> std::result::Result<(), String> {
+    match waqf.status {
+        WaqfStatus::Active | WaqfStatus::Paused | WaqfStatus::Inactive => {}
+        WaqfStatus::Completed | WaqfStatus::Archived | WaqfStatus::Terminated | WaqfStatus::Matured => {
+            return Err(format!("The deed of a {} waqf can no longer be amended", waqf.status));
+        }
+    }
+
+    let current = deed_terms(waqf);
+    if terms.waqf_type != current.waqf_type {
+        return Err("The waqf type cannot be amended".into());
+    }
+    if terms.lock_period_months.is_some() != current.lock_period_months.is_some()
+        || terms.principal_return_method.is_some() != current.principal_return_method.is_some()
+        || terms.early_withdrawal_allowed.is_some() != current.early_withdrawal_allowed.is_some()
+    {
+        return Err("Lock terms can only be amended on waqfs with revolving details".into());
+    }
+    if let (Some(amended), Some(existing)) = (terms.lock_period_months, current.lock_period_months) {
+        if amended < existing {
+            return Err(format!(
+                "Lock period cannot be reduced from {} to {} months",
+                existing, amended
+            ));
+        }
+    }
+
+    let amended = apply_terms(waqf, terms);
+    waqf_utils::validate_waqf_data(&amended)?;
+    waqf_hooks::validate_waqf_type_and_details(&amended)
+}
+
+pub fn assert_deed_version_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let version: DeedVersionData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid deed version data structure: {}", e))?;
+
+    if context.data.data.current.is_some() {
+        return Err("Deed versions are immutable once recorded".into());
+    }
+
+    if version.id != context.data.key || version.id != deed_version_key(&version.waqf_id, version.version) {
+        return Err("Deed version id must be \"{waqf_id}_v{version}\" and match the document key".into());
+    }
+
+    let caller = context.caller.to_text();
+    if version.recorded_by != caller {
+        return Err("Recorded by must match the caller".into());
+    }
+
+    let (waqf, _) = load_waqf(&version.waqf_id)?;
+    let current_deed = waqf.deed_document.as_ref()
+        .ok_or("The waqf deed has not been signed yet - sign it on the waqf first")?;
+
+    // The first version mirrors the deed signed on the waqf; later satellite versions are
+    // approved cause reallocations and are checked like any other amendment
+    let satellite = is_satellite_caller(&caller);
+    if satellite && version.version == 1 {
+        if Some(&version.terms_hash) != current_deed.terms_hash.as_ref() {
+            return Err("The satellite only records the deed signed on the waqf".into());
+        }
+        return Ok(());
+    }
+
+    if !satellite
+        && waqf.created_by != caller
+        && !caller_has_role(&caller, &[AdminRole::WaqfManager, AdminRole::PlatformAdmin])
+    {
+        return Err("Only the waqf creator or waqf managers can submit deed amendments".into());
+    }
+
+    let current_version = deed_version(current_deed)?;
+    if version.version != current_version + 1 {
+        return Err(format!(
+            "Deed amendment must be version {} (current version is {})",
+            current_version + 1, current_version
+        ));
+    }
+    if version.previous_terms_hash.as_ref() != current_deed.terms_hash.as_ref() {
+        return Err("Previous terms hash must match the current deed version".into());
+    }
+
+    version.signed_at.parse::<u64>()
+        .map_err(|_| "Invalid signed at format")?;
+
+    let expected_hash = terms_hash(
+        &version.waqf_id,
+        version.version,
+        version.previous_terms_hash.as_deref(),
+        &version.terms,
+    );
+    if version.terms_hash != expected_hash {
+        return Err("Deed terms hash does not match the amended terms".into());
+    }
+
+    validate_amendment(&waqf, &version.terms)?;
+
+    // Donors move causes through a cause reallocation request reviewed by a waqf manager
+    let current_terms = deed_terms(&waqf);
+    let moves_causes = version.terms.selected_causes != current_terms.selected_causes
+        || version.terms.cause_allocation != current_terms.cause_allocation
+        || version.terms.hybrid_allocations != current_terms.hybrid_allocations;
+    if moves_causes && !satellite && !caller_has_role(&caller, &[AdminRole::WaqfManager, AdminRole::PlatformAdmin]) {
+        return Err("Cause changes go through a cause reallocation request".into());
+    }
+
+    verify_signature(&waqf.created_by, &version.public_key, &version.donor_signature, &expected_hash)?;
+
+    ic_cdk::println!(
+        "Deed amendment validation passed: waqf {} version {}",
+        version.waqf_id, version.version
+    );
+
+    Ok(())
+}
+
+pub fn assert_deed_version_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Deed versions cannot be deleted".into())
+}
+
+/// Apply a recorded amendment to the waqf and make it the waqf's current deed
+pub fn handle_deed_version_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let version: DeedVersionData = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode deed version: {}", e))?;
+
+    let (waqf, doc) = load_waqf(&version.waqf_id)?;
+    let current_version = match &waqf.deed_document {
+        Some(deed) => deed_version(deed)?,
+        None => 0,
+    };
+    if current_version >= version.version {
+        ic_cdk::println!("Deed version {} of waqf {} recorded", version.version, version.waqf_id);
+        return Ok(());
+    }
+
+    let mut amended = apply_terms(&waqf, &version.terms);
+    amended.deed_document = Some(DeedDocument {
+        signed_at: version.signed_at.clone(),
+        donor_signature: version.donor_signature.clone(),
+        document_version: version.version.to_string(),
+        public_key: Some(version.public_key.clone()),
+        terms_hash: Some(version.terms_hash.clone()),
+    });
+    amended.updated_at = Some(ic_cdk::api::time().to_string());
+
+    let data = encode_doc_data(&amended)
+        .map_err(|e| format!("Failed to encode amended waqf: {}", e))?;
+    let _ = set_doc(
+        "waqfs".to_string(),
+        version.waqf_id.clone(),
+        SetDoc { data, description: doc.description, version: doc.version },
+    );
+
+    ic_cdk::println!(
+        "Deed of waqf {} amended to version {} by {}",
+        version.waqf_id, version.version, version.recorded_by
+    );
+
+    Ok(())
+}
+
+/// Validates the agreed deed signing keys (platform admins and compliance officers only)
+/// Next deed version for an amendment the donor signed outside deed_versions (an approved
+/// cause reallocation), after checking the signature over it
+pub fn signed_amendment(
+    waqf: &WaqfData,
+    terms: DeedTerms,
+    donor_signature: &str,
+    public_key: &str,
+    signed_at: &str,
+    recorded_by: &str,
+) -> std::result::Result<DeedVersionData, String> {
+    let current_deed = waqf.deed_document.as_ref()
+        .ok_or("The waqf deed has not been signed yet")?;
+    let version = deed_version(current_deed)? + 1;
+
+    signed_at.parse::<u64>()
+        .map_err(|_| "Invalid signed at format")?;
+
+    let hash = terms_hash(&waqf.id, version, current_deed.terms_hash.as_deref(), &terms);
+    verify_signature(&waqf.created_by, public_key, donor_signature, &hash)?;
+
+    Ok(DeedVersionData {
+        id: deed_version_key(&waqf.id, version),
+        waqf_id: waqf.id.clone(),
+        version,
+        terms,
+        terms_hash: hash,
+        previous_terms_hash: current_deed.terms_hash.clone(),
+        donor_signature: donor_signature.to_string(),
+        public_key: public_key.to_string(),
+        signed_at: signed_at.to_string(),
+        recorded_by: recorded_by.to_string(),
+    })
+}
+
+/// Write a deed version; its on_set_doc hook applies the terms to the waqf
+pub fn record_deed_version(version: &DeedVersionData) -> std::result::Result<(), String> {
+    let data = encode_doc_data(version)
+        .map_err(|e| format!("Failed to encode deed version: {}", e))?;
+    set_doc("deed_versions".to_string(), version.id.clone(), SetDoc { data, description: None, version: None });
+    Ok(())
+}
+
+pub fn validate_deed_signing_keys(config: &DeedSigningKeysConfig, caller: &str) -> std::result::Result<(), String> {
+    if !caller_has_role(caller, &[AdminRole::PlatformAdmin, AdminRole::ComplianceOfficer]) {
+        return Err("Only platform admins and compliance officers can manage deed signing keys".into());
+    }
+
+    if config.updated_by != caller {
+        return Err("Updated by must match the calling admin".into());
+    }
+
+    config.updated_at.parse::<u64>()
+        .map_err(|_| "Invalid updated at format")?;
+
+    for key in &config.public_keys {
+        let der = hex::decode(key).map_err(|_| format!("Signing key {} must be hex encoded", key))?;
+        ed25519_key(&der)?;
+    }
+
+    ic_cdk::println!("Deed signing keys validation passed: {} keys", config.public_keys.len());
+
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use crate::admin_hooks::{get_admin_role, is_satellite_caller};
+use crate::calendar;
+use crate::deed_types::{DeedReferenceCounter, DeedReferenceData, DeedReferenceMatch};
+use crate::waqf_types::WaqfData;
+use junobuild_satellite::{get_doc, list_docs, set_doc, AssertDeleteDocContext, AssertSetDocContext, SetDoc};
+use junobuild_shared::types::list::ListParams;
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+
+const DEED_REFERENCE_PREFIX: &str = "WQF";
+
+// Shortest search term, so a search cannot list every deed
+const MIN_SEARCH_LENGTH: usize = 3;
+const MAX_SEARCH_RESULTS: usize = 50;
+
+fn counter_key(year: i64) -> String {
+    format!("counter_{}", year)
+}
+
+/// Hand out the next deed reference of the waqf's creation year ("WQF-2026-000123") and
+/// index it. The counter and index are written in the same message as the waqf, so a failed
+/// waqf write rolls the sequence back and numbers stay gap-free.
+pub fn assign_deed_reference(waqf_id: &str, created_at: u64) -> std::result::Result<String, String> {
+    let now = ic_cdk::api::time();
+    let year = calendar::year_of(created_at);
+    let key = counter_key(year);
+
+    let counter_doc = get_doc("deed_references".to_string(), key.clone());
+    let last_sequence = match &counter_doc {
+        Some(doc) => decode_doc_data::<DeedReferenceCounter>(&doc.data)
+            .map_err(|e| format!("Failed to decode deed reference counter: {}", e))?
+            .last_sequence,
+        None => 0,
+    };
+    let sequence = last_sequence + 1;
+    let reference = format!("{}-{}-{:06}", DEED_REFERENCE_PREFIX, year, sequence);
+
+    let counter = DeedReferenceCounter {
+        year,
+        last_sequence: sequence,
+        updated_at: now.to_string(),
+    };
+    let data = encode_doc_data(&counter)
+        .map_err(|e| format!("Failed to encode deed reference counter: {}", e))?;
+    set_doc(
+        "deed_references".to_string(),
+        key,
+        SetDoc { data, description: None, version: counter_doc.and_then(|doc| doc.version) },
+    );
+
+    let entry = DeedReferenceData {
+        reference: reference.clone(),
+        waqf_id: waqf_id.to_string(),
+        year,
+        sequence,
+        assigned_at: now.to_string(),
+    };
+    let data = encode_doc_data(&entry)
+        .map_err(|e| format!("Failed to encode deed reference: {}", e))?;
+    set_doc("deed_references".to_string(), reference.clone(), SetDoc { data, description: None, version: None });
+
+    ic_cdk::println!("Deed reference {} assigned to waqf {}", reference, waqf_id);
+
+    Ok(reference)
+}
+
+/// Deed references are only written by the satellite, once
+pub fn assert_deed_reference_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    if !is_satellite_caller(&context.caller.to_text()) {
+        return Err("Deed references are assigned by the satellite".into());
+    }
+
+    if context.data.key.starts_with("counter_") {
+        let counter: DeedReferenceCounter = decode_doc_data(&context.data.data.proposed.data)
+            .map_err(|e| format!("Invalid deed reference counter structure: {}", e))?;
+        if context.data.key != counter_key(counter.year) {
+            return Err("Deed reference counter key must match its year".into());
+        }
+        return Ok(());
+    }
+
+    let entry: DeedReferenceData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid deed reference structure: {}", e))?;
+
+    if context.data.data.current.is_some() {
+        return Err(format!("Deed reference {} has already been assigned", entry.reference));
+    }
+    if entry.reference != context.data.key {
+        return Err("Deed reference must match the document key".into());
+    }
+
+    Ok(())
+}
+
+pub fn assert_deed_reference_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Deed references cannot be deleted".into())
+}
+
+/// Waqfs whose deed reference contains the search term (case-insensitive). Platform staff
+/// search every deed; donors only their own.
+pub fn search_deed_references(caller: &str, query: &str) -> std::result::Result<Vec<DeedReferenceMatch>, String> {
+    let term = query.trim().to_uppercase();
+    if term.len() < MIN_SEARCH_LENGTH {
+        return Err(format!("Search term must be at least {} characters", MIN_SEARCH_LENGTH));
+    }
+
+    let is_staff = get_admin_role(caller).is_some();
+    let mut matches = Vec::new();
+
+    for (key, doc) in list_docs("deed_references".to_string(), ListParams::default()).items {
+        if !key.starts_with(DEED_REFERENCE_PREFIX) || !key.contains(&term) {
+            continue;
+        }
+        let entry: DeedReferenceData = match decode_doc_data(&doc.data) {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let waqf: WaqfData = match get_doc("waqfs".to_string(), entry.waqf_id.clone())
+            .and_then(|doc| decode_doc_data(&doc.data).ok())
+        {
+            Some(waqf) => waqf,
+            None => continue,
+        };
+        if !is_staff && waqf.created_by != caller {
+            continue;
+        }
+
+        matches.push(DeedReferenceMatch {
+            reference: entry.reference,
+            waqf_id: waqf.id,
+            waqf_name: waqf.name,
+            status: waqf.status,
+        });
+    }
+
+    matches.sort_by(|a, b| a.reference.cmp(&b.reference));
+    matches.truncate(MAX_SEARCH_RESULTS);
+
+    Ok(matches)
+}
@@ -0,0 +1,67 @@
+use crate::waqf_types::{HybridCauseAllocation, PrincipalReturnMethod, WaqfStatus, WaqfType};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Terms of a waqf covered by the donor's deed signature
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeedTerms {
+    pub waqf_type: WaqfType,
+    pub selected_causes: Vec<String>,
+    pub cause_allocation: BTreeMap<String, f64>,                  // Percentage allocation per cause
+    pub hybrid_allocations: Option<Vec<HybridCauseAllocation>>,
+    pub lock_period_months: Option<u32>,                          // Revolving lock terms
+    pub principal_return_method: Option<PrincipalReturnMethod>,
+    pub early_withdrawal_allowed: Option<bool>,
+}
+
+/// A signed version of a waqf deed. Version 1 is recorded by the satellite when the deed is
+/// first signed on the waqf; later versions are amendments submitted by the donor.
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct DeedVersionData {
+    pub id: String,                            // "{waqf_id}_v{version}"
+    pub waqf_id: String,
+    pub version: u32,
+    pub terms: DeedTerms,
+    pub terms_hash: String,                    // Hex SHA-256 of the canonical deed text
+    pub previous_terms_hash: Option<String>,   // Hash of the version this one amends
+    pub donor_signature: String,               // Hex Ed25519 signature over the terms hash
+    pub public_key: String,                    // Hex DER-encoded Ed25519 public key
+    pub signed_at: String,                     // Nanosecond timestamp
+    pub recorded_by: String,
+}
+
+/// Platform-agreed keys that may sign deeds on a donor's behalf (e.g. for donors signing
+/// with a key other than their own principal's)
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct DeedSigningKeysConfig {
+    pub public_keys: Vec<String>, // Hex DER-encoded Ed25519 public keys
+    pub updated_by: String,
+    pub updated_at: String,       // Nanosecond timestamp
+}
+
+/// Deed reference assigned to a waqf, stored under the reference itself
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct DeedReferenceData {
+    pub reference: String,   // "WQF-{year}-{sequence:06}"
+    pub waqf_id: String,
+    pub year: i64,
+    pub sequence: u64,
+    pub assigned_at: String, // Nanosecond timestamp
+}
+
+/// Last deed reference sequence handed out in a year
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct DeedReferenceCounter {
+    pub year: i64,
+    pub last_sequence: u64,
+    pub updated_at: String,  // Nanosecond timestamp
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct DeedReferenceMatch {
+    pub reference: String,
+    pub waqf_id: String,
+    pub waqf_name: String,
+    pub status: WaqfStatus,
+}
@@ -1,11 +1,13 @@
-use crate::waqf_types::{DonationData, WaqfData};
+use crate::bucket_accounting;
+use crate::calendar;
+use crate::termination_hooks;
+use crate::waqf_types::{DonationData, DonationStatus, TrancheStatus, WaqfBucket, WaqfData};
 use junobuild_satellite::{OnSetDocContext, AssertSetDocContext, AssertDeleteDocContext, get_doc, set_doc, SetDoc};
 use junobuild_utils::{decode_doc_data, encode_doc_data};
 
 // Validation constants
 const MIN_DONATION_AMOUNT: f64 = 0.01;
 const MAX_DONATION_AMOUNT: f64 = 1_000_000.0;
-const VALID_DONATION_STATUSES: &[&str] = &["completed", "pending", "failed"];
 const VALID_CURRENCIES: &[&str] = &["USD", "EUR", "GBP", "SAR", "AED", "NGN", "KES", "GHS", "ZAR", "INR"];
 
 pub fn assert_donation_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
@@ -15,6 +17,10 @@ pub fn assert_donation_operations(context: AssertSetDocContext) -> std::result::
     
     // Validate donation data
     validate_donation_data(&donation)?;
+
+    if context.data.data.current.is_none() {
+        termination_hooks::check_accepts_donations(&donation.waqf_id)?;
+    }
     
     ic_cdk::println!(
         "Donation validation passed: {} - Amount: {} {}, Status: {}", 
@@ -49,7 +55,7 @@ pub fn handle_donation_changes(context: OnSetDocContext) -> std::result::Result<
     );
     
     // Only update waqf financial metrics for new completed donations
-    if is_new_donation && donation.status == "completed" {
+    if is_new_donation && donation.status == DonationStatus::Completed {
         update_waqf_financials(&donation)?;
     }
     
@@ -86,15 +92,6 @@ fn validate_donation_data(donation: &DonationData) -> std::result::Result<(), St
         ));
     }
 
-    // Validate status
-    if !VALID_DONATION_STATUSES.contains(&donation.status.as_str()) {
-        return Err(format!(
-            "Invalid donation status: {}. Valid statuses: {}",
-            donation.status,
-            VALID_DONATION_STATUSES.join(", ")
-        ));
-    }
-
     // Validate date format (should be ISO string)
     if donation.date.trim().is_empty() {
         return Err("Donation date cannot be empty".into());
@@ -130,6 +127,9 @@ fn update_waqf_financials(donation: &DonationData) -> std::result::Result<(), St
     let mut waqf: WaqfData = decode_doc_data(&doc.data)
         .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
 
+    // Seed bucket balances for waqfs that predate per-bucket accounting
+    bucket_accounting::ensure_bucket_balances(&mut waqf);
+
     // Update financial metrics
     waqf.financial.total_donations += donation_amount;
     waqf.financial.current_balance += donation_amount;
@@ -147,80 +147,21 @@ fn update_waqf_financials(donation: &DonationData) -> std::result::Result<(), St
         donation.lock_period_months
     );
 
-    // If this waqf has a revolving slice, create a new contribution tranche
-    use crate::waqf_types::{WaqfType, ContributionTranche};
+    // Split the donation exactly across each cause's permanent/consumable/revolving buckets
+    let split = bucket_accounting::split_amount(&waqf, donation_amount);
+    bucket_accounting::credit(&mut waqf.financial, &split);
 
-    // Determine how much of this donation should be treated as "revolving"
-    let mut revolving_donation_amount = 0.0;
+    for (cause_id, parts) in &split {
+        ic_cdk::println!(
+            "DEBUG - Donation split for cause {}: permanent={:.2}, consumable={:.2}, revolving={:.2}",
+            cause_id, parts.permanent, parts.consumable, parts.revolving
+        );
+    }
 
-    match waqf.waqf_type {
-        WaqfType::TemporaryRevolving => {
-            // Pure revolving waqf: entire donation is revolving
-            revolving_donation_amount = donation_amount;
-            ic_cdk::println!(
-                "DEBUG - Pure TemporaryRevolving waqf: full donation is revolving slice"
-            );
-        }
-        WaqfType::Hybrid => {
-            // Hybrid: try to infer revolving ratio from existing tranches first
-            let mut used_tranche_ratio = false;
-
-            if let Some(ref revolving_details) = waqf.revolving_details {
-                if let Some(ref tranches) = revolving_details.contribution_tranches {
-                    let total_revolving: f64 = tranches.iter().map(|t| t.amount).sum();
-
-                    if waqf.waqf_asset > 0.0 && total_revolving > 0.0 {
-                        let ratio = total_revolving / waqf.waqf_asset;
-                        revolving_donation_amount = donation_amount * ratio;
-                        used_tranche_ratio = true;
-
-                        ic_cdk::println!(
-                            "DEBUG - Hybrid waqf donation (from tranches): donation_amount={}, total_revolving={}, waqf_asset={}, ratio={:.4}, slice={}",
-                            donation_amount,
-                            total_revolving,
-                            waqf.waqf_asset,
-                            ratio,
-                            revolving_donation_amount
-                        );
-                    }
-                }
-            }
+    // If this waqf has a revolving slice, create a new contribution tranche
+    use crate::waqf_types::ContributionTranche;
 
-            if !used_tranche_ratio {
-                // Fallback: use hybrid_allocations percentages if available
-                if let Some(ref allocations) = waqf.hybrid_allocations {
-                    let mut total_revolving_pct = 0.0;
-                    for alloc in allocations {
-                        total_revolving_pct +=
-                            alloc.allocations.temporary_revolving.unwrap_or(0.0);
-                    }
-
-                    let avg_revolving_pct = if !allocations.is_empty() {
-                        total_revolving_pct / allocations.len() as f64
-                    } else {
-                        0.0
-                    };
-
-                    revolving_donation_amount = (donation_amount * avg_revolving_pct) / 100.0;
-
-                    ic_cdk::println!(
-                        "DEBUG - Hybrid waqf donation (from hybrid_allocations): donation_amount={}, avg_revolving_pct={:.2}, revolving_slice={}",
-                        donation_amount,
-                        avg_revolving_pct,
-                        revolving_donation_amount
-                    );
-                } else {
-                    ic_cdk::println!(
-                        "WARN - Hybrid waqf has no hybrid_allocations; skipping revolving tranche for donation on waqf {}",
-                        waqf_id
-                    );
-                }
-            }
-        }
-        _ => {
-            // Non-revolving waqf types don't get tranches
-        }
-    }
+    let revolving_donation_amount = bucket_accounting::bucket_total(&split, WaqfBucket::Revolving);
 
     if revolving_donation_amount > 0.0 {
         if let Some(ref mut revolving_details) = waqf.revolving_details {
@@ -236,14 +177,8 @@ fn update_waqf_financials(donation: &DonationData) -> std::result::Result<(), St
                 .lock_period_months
                 .unwrap_or(revolving_details.lock_period_months);
 
-            // Calculate maturity date for this tranche
-            let lock_period_nanos = (effective_lock_months as u64)
-                * 30
-                * 24
-                * 60
-                * 60
-                * 1_000_000_000; // Convert months to nanoseconds
-            let maturity_time_nanos = current_time_nanos + lock_period_nanos;
+            // Calculate maturity date for this tranche (calendar months)
+            let maturity_time_nanos = calendar::add_months(current_time_nanos, effective_lock_months);
             let maturity_date = maturity_time_nanos.to_string();
 
             // Create new tranche with expiration preference from waqf defaults
@@ -255,7 +190,7 @@ fn update_waqf_financials(donation: &DonationData) -> std::result::Result<(), St
                 maturity_date: maturity_date.clone(),
                 is_returned: false,
                 returned_date: None,
-                status: Some("locked".to_string()),
+                status: Some(TrancheStatus::Locked),
                 penalty_applied: None,
                 rollover_origin_id: None,
                 rollover_target_id: None,
@@ -264,6 +199,15 @@ fn update_waqf_financials(donation: &DonationData) -> std::result::Result<(), St
                     .default_expiration_preference
                     .clone(),
                 conversion_details: None,
+                matured_at: None,
+                penalty_waiver: None,
+                cause_id: None,
+                rollover_target_waqf_id: None,
+                pool_origin: None,
+                currency: Some(donation.currency.clone()),
+                split_from: None,
+                split_into: None,
+                expiration_failure: None,
             };
 
             ic_cdk::println!(
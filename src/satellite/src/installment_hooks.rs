@@ -0,0 +1,401 @@
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::bucket_accounting;
+use crate::notification_hooks::{self, Notice};
+use crate::notification_types::{NotificationPayload, NotificationType};
+use crate::scheduler;
+use crate::waqf_types::{
+    ContributionTranche, DueInstallmentData, DueInstallmentStatus, InstallmentPayoutData, InstallmentStatus,
+    TrancheStatus, WaqfData,
+};
+use junobuild_satellite::{
+    get_doc, set_doc, AssertDeleteDocContext, AssertSetDocContext, Doc, OnSetDocContext, SetDoc,
+};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+// Time after the due date before an unpaid installment is flagged as missed
+const MISSED_INSTALLMENT_GRACE_NANOS: u64 = 7 * NANOS_PER_DAY;
+
+const MAX_PAYOUT_REFERENCE_LENGTH: usize = 200;
+
+fn due_installment_key(waqf_id: &str, installment_id: &str) -> String {
+    format!("{}_{}", waqf_id, installment_id)
+}
+
+/// One payout document per installment, so a second payout is rejected as an existing document
+fn installment_payout_key(waqf_id: &str, installment_id: &str) -> String {
+    format!("payout_{}_{}", waqf_id, installment_id)
+}
+
+fn find_tranche_mut<'a>(waqf: &'a mut WaqfData, tranche_id: &str) -> std::result::Result<&'a mut ContributionTranche, String> {
+    waqf.revolving_details
+        .as_mut()
+        .and_then(|details| details.contribution_tranches.as_mut())
+        .and_then(|tranches| tranches.iter_mut().find(|t| t.id == tranche_id))
+        .ok_or_else(|| format!("Tranche {} not found", tranche_id))
+}
+
+fn upsert_due_installment(entry: &DueInstallmentData) {
+    let existing = get_doc("due_installments".to_string(), entry.id.clone());
+
+    match encode_doc_data(entry) {
+        Ok(data) => {
+            let set_doc_data = SetDoc {
+                data,
+                description: existing.as_ref().and_then(|doc| doc.description.clone()),
+                version: existing.as_ref().and_then(|doc| doc.version),
+            };
+            let _ = set_doc("due_installments".to_string(), entry.id.clone(), set_doc_data);
+        }
+        Err(e) => ic_cdk::println!("ERROR - Cannot encode due installment {}: {}", entry.id, e),
+    }
+}
+
+// ============================================
+// DUE INSTALLMENT QUEUE
+// ============================================
+
+pub fn assert_due_installment_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    if !is_satellite_caller(&context.caller.to_text()) {
+        return Err("The due installment queue is maintained by the satellite only".into());
+    }
+
+    Ok(())
+}
+
+pub fn assert_due_installment_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Due installments cannot be deleted - record a payout instead".into())
+}
+
+/// Queue installments that have fallen due and flag those unpaid past the grace window.
+/// Donor notifications are added to `notices`. Returns whether the waqf was changed.
+fn queue_waqf_installments(waqf: &mut WaqfData, now: u64, notices: &mut Vec<Notice>) -> bool {
+    let waqf_id = waqf.id.clone();
+    let donor = waqf.created_by.clone();
+    let details = match waqf.revolving_details.as_mut() {
+        Some(details) => details,
+        None => return false,
+    };
+
+    let mut changed = false;
+
+    for tranche in details.contribution_tranches.iter_mut().flatten() {
+        if tranche.status != Some(TrancheStatus::ReturnScheduled) {
+            continue;
+        }
+        let owner = tranche.pool_origin.as_ref()
+            .map(|origin| origin.owner.clone())
+            .unwrap_or_else(|| donor.clone());
+
+        for installment in tranche.installment_payments.iter_mut().flatten() {
+            if installment.status == InstallmentStatus::Paid {
+                continue;
+            }
+
+            let due_date = match installment.due_date.parse::<u64>() {
+                Ok(date) => date,
+                Err(_) => continue,
+            };
+            if now < due_date {
+                continue;
+            }
+
+            if installment.status == InstallmentStatus::Scheduled && now >= due_date + MISSED_INSTALLMENT_GRACE_NANOS {
+                installment.status = InstallmentStatus::Missed;
+                ic_cdk::println!("Waqf {}: installment {} of tranche {} flagged as missed", waqf_id, installment.id, tranche.id);
+                changed = true;
+            }
+
+            let queue_status = match installment.status {
+                InstallmentStatus::Missed => DueInstallmentStatus::Missed,
+                InstallmentStatus::Scheduled | InstallmentStatus::Paid => DueInstallmentStatus::Due,
+            };
+            let key = due_installment_key(&waqf_id, &installment.id);
+            let already_queued = get_doc("due_installments".to_string(), key.clone())
+                .and_then(|doc| decode_doc_data::<DueInstallmentData>(&doc.data).ok())
+                .is_some_and(|entry| entry.status == queue_status);
+
+            if !already_queued {
+                upsert_due_installment(&DueInstallmentData {
+                    id: key,
+                    waqf_id: waqf_id.clone(),
+                    tranche_id: tranche.id.clone(),
+                    installment_id: installment.id.clone(),
+                    amount: installment.amount,
+                    due_date: installment.due_date.clone(),
+                    status: queue_status,
+                    updated_at: now.to_string(),
+                });
+
+                let notification_type = if queue_status == DueInstallmentStatus::Missed {
+                    NotificationType::InstallmentMissed
+                } else {
+                    NotificationType::InstallmentDue
+                };
+                notices.push(Notice::new(
+                    &owner,
+                    &waqf_id,
+                    notification_type,
+                    NotificationPayload {
+                        tranche_id: Some(tranche.id.clone()),
+                        installment_id: Some(installment.id.clone()),
+                        amount: Some(installment.amount),
+                        date: Some(installment.due_date.clone()),
+                        ..Default::default()
+                    },
+                ));
+            }
+        }
+    }
+
+    changed
+}
+
+/// Scheduled job: publish due installments for finance officers and flag missed ones
+pub fn queue_due_installments() {
+    scheduler::sweep_waqfs(queue_installments_page);
+}
+
+fn queue_installments_page(waqfs: Vec<(String, Doc)>) {
+    let now = ic_cdk::api::time();
+
+    for (key, doc) in waqfs {
+        let mut waqf: WaqfData = match decode_doc_data(&doc.data) {
+            Ok(waqf) => waqf,
+            Err(e) => {
+                ic_cdk::println!("WARN - Skipping waqf {} in installment sweep: {}", key, e);
+                continue;
+            }
+        };
+
+        // Reminders for newly queued installments go out even when the waqf itself is unchanged
+        let mut notices = Vec::new();
+        if queue_waqf_installments(&mut waqf, now, &mut notices) {
+            waqf.updated_at = Some(now.to_string());
+            match encode_doc_data(&waqf) {
+                Ok(data) => {
+                    let set_doc_data = SetDoc {
+                        data,
+                        description: doc.description,
+                        version: doc.version,
+                    };
+                    let _ = set_doc("waqfs".to_string(), key.clone(), set_doc_data);
+                }
+                Err(e) => ic_cdk::println!("ERROR - Cannot encode waqf {}: {}", key, e),
+            }
+        }
+        notification_hooks::send_all(notices);
+    }
+}
+
+// ============================================
+// PAYOUTS
+// ============================================
+
+fn validate_payout_data(payout: &InstallmentPayoutData) -> std::result::Result<(), String> {
+    if payout.id.trim().is_empty() {
+        return Err("Payout ID cannot be empty".into());
+    }
+
+    if payout.waqf_id.trim().is_empty() || payout.tranche_id.trim().is_empty() || payout.installment_id.trim().is_empty() {
+        return Err("Payout must reference a waqf, tranche and installment".into());
+    }
+
+    let reference = payout.payout_reference.trim();
+    if reference.is_empty() {
+        return Err("Payout reference cannot be empty".into());
+    }
+    if reference.len() > MAX_PAYOUT_REFERENCE_LENGTH {
+        return Err(format!("Payout reference too long: maximum {} characters", MAX_PAYOUT_REFERENCE_LENGTH));
+    }
+
+    if payout.paid_by.trim().is_empty() {
+        return Err("Paid by field cannot be empty".into());
+    }
+
+    payout.paid_at
+        .parse::<u64>()
+        .map_err(|_| "Invalid paid at format")?;
+
+    Ok(())
+}
+
+pub fn assert_installment_payout_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let payout: InstallmentPayoutData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid installment payout data structure: {}", e))?;
+
+    if context.data.data.current.is_some() {
+        return Err("Installment payouts are immutable once recorded".into());
+    }
+
+    let caller = context.caller.to_text();
+    if !caller_has_role(&caller, &[AdminRole::FinanceOfficer]) {
+        return Err("Only finance officers can record installment payouts".into());
+    }
+
+    if payout.paid_by != caller {
+        return Err("Paid by must match the calling finance officer".into());
+    }
+
+    validate_payout_data(&payout)?;
+
+    let expected_key = installment_payout_key(&payout.waqf_id, &payout.installment_id);
+    if context.data.key != expected_key || payout.id != expected_key {
+        return Err(format!("Installment payout key and id must be {}", expected_key));
+    }
+
+    let doc = get_doc("waqfs".to_string(), payout.waqf_id.clone())
+        .ok_or_else(|| format!("Waqf not found: {}", payout.waqf_id))?;
+    let mut waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+
+    let tranche = find_tranche_mut(&mut waqf, &payout.tranche_id)?;
+    if tranche.status != Some(TrancheStatus::ReturnScheduled) {
+        return Err(format!("Tranche {} is not being returned in installments", tranche.id));
+    }
+
+    let installment = tranche.installment_payments
+        .iter()
+        .flatten()
+        .find(|i| i.id == payout.installment_id)
+        .ok_or_else(|| format!("Installment {} not found", payout.installment_id))?;
+
+    if installment.status == InstallmentStatus::Paid {
+        return Err(format!("Installment {} has already been paid", installment.id));
+    }
+
+    let due_date = installment.due_date
+        .parse::<u64>()
+        .map_err(|_| "Invalid installment due date format")?;
+    if ic_cdk::api::time() < due_date {
+        return Err(format!("Installment {} is not due yet", installment.id));
+    }
+
+    // Allow for floating point errors
+    if (payout.amount - installment.amount).abs() > 0.005 {
+        return Err(format!(
+            "Payout amount {:.2} does not match installment amount {:.2}",
+            payout.amount, installment.amount
+        ));
+    }
+
+    ic_cdk::println!(
+        "Installment payout validation passed: {} - Waqf: {}, Installment: {}, Amount: {:.2}",
+        payout.id, payout.waqf_id, payout.installment_id, payout.amount
+    );
+
+    Ok(())
+}
+
+pub fn assert_installment_payout_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Installment payouts cannot be deleted for audit purposes".into())
+}
+
+pub fn handle_installment_payout_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let payout: InstallmentPayoutData = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode installment payout data: {}", e))?;
+
+    ic_cdk::println!(
+        "Installment payout CREATE: {} - Waqf: {}, Tranche: {}, Installment: {}, Amount: {:.2}, Reference: {}",
+        payout.id, payout.waqf_id, payout.tranche_id, payout.installment_id, payout.amount, payout.payout_reference
+    );
+
+    if context.data.data.before.is_none() {
+        apply_payout_to_waqf(&payout)?;
+    }
+
+    Ok(())
+}
+
+fn apply_payout_to_waqf(payout: &InstallmentPayoutData) -> std::result::Result<(), String> {
+    let doc = get_doc("waqfs".to_string(), payout.waqf_id.clone())
+        .ok_or_else(|| format!("Waqf not found: {}", payout.waqf_id))?;
+    let mut waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+
+    let donor = waqf.created_by.clone();
+    let (due_date, fully_returned, tranche_cause, owner) = {
+        let tranche = find_tranche_mut(&mut waqf, &payout.tranche_id)?;
+        let installment = tranche.installment_payments
+            .iter_mut()
+            .flatten()
+            .find(|i| i.id == payout.installment_id)
+            .ok_or_else(|| format!("Installment {} not found", payout.installment_id))?;
+
+        installment.status = InstallmentStatus::Paid;
+        installment.paid_date = Some(payout.paid_at.clone());
+        installment.payout_reference = Some(payout.payout_reference.clone());
+        let due_date = installment.due_date.clone();
+
+        // Close the tranche once every installment has been paid
+        let fully_returned = tranche.installment_payments
+            .iter()
+            .flatten()
+            .all(|i| i.status == InstallmentStatus::Paid);
+        if fully_returned {
+            tranche.is_returned = true;
+            tranche.returned_date = Some(payout.paid_at.clone());
+            tranche.status = Some(TrancheStatus::Returned);
+        }
+
+        let owner = tranche.pool_origin.as_ref()
+            .map(|origin| origin.owner.clone())
+            .unwrap_or(donor);
+        (due_date, fully_returned, tranche.cause_id.clone(), owner)
+    };
+
+    bucket_accounting::ensure_bucket_balances(&mut waqf);
+    bucket_accounting::release_revolving(&mut waqf.financial, tranche_cause.as_deref(), None, payout.amount)?;
+    waqf.financial.current_balance = (waqf.financial.current_balance - payout.amount).max(0.0);
+    waqf.financial.principal_released += payout.amount;
+    waqf.updated_at = Some(ic_cdk::api::time().to_string());
+
+    let updated_data = encode_doc_data(&waqf)
+        .map_err(|e| format!("Failed to encode waqf data: {}", e))?;
+
+    let set_doc_data = SetDoc {
+        data: updated_data,
+        description: doc.description,
+        version: doc.version,
+    };
+
+    let _ = set_doc("waqfs".to_string(), payout.waqf_id.clone(), set_doc_data);
+
+    upsert_due_installment(&DueInstallmentData {
+        id: due_installment_key(&payout.waqf_id, &payout.installment_id),
+        waqf_id: payout.waqf_id.clone(),
+        tranche_id: payout.tranche_id.clone(),
+        installment_id: payout.installment_id.clone(),
+        amount: payout.amount,
+        due_date,
+        status: DueInstallmentStatus::Paid,
+        updated_at: payout.paid_at.clone(),
+    });
+
+    // The final installment closes the tranche
+    let notification_type = if fully_returned {
+        NotificationType::TrancheReturned
+    } else {
+        NotificationType::InstallmentPaid
+    };
+    notification_hooks::send(Notice::new(
+        &owner,
+        &payout.waqf_id,
+        notification_type,
+        NotificationPayload {
+            tranche_id: Some(payout.tranche_id.clone()),
+            installment_id: Some(payout.installment_id.clone()),
+            amount: Some(payout.amount),
+            reference: Some(payout.payout_reference.clone()),
+            ..Default::default()
+        },
+    ));
+
+    ic_cdk::println!(
+        "Installment {} paid for waqf {}: -{:.2}, new balance: {:.2}",
+        payout.installment_id, payout.waqf_id, payout.amount, waqf.financial.current_balance
+    );
+
+    Ok(())
+}
@@ -0,0 +1,469 @@
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::bucket_accounting::{apportion_cents, to_cents};
+use crate::investment_pool_types::{InvestmentPoolData, PoolMember, PoolTransactionData, PoolTransactionType};
+use crate::investment_return_hooks::{validate_investment_return_data, validate_return_against_waqf};
+use crate::waqf_types::{InvestmentReturnData, WaqfData, WaqfStatus};
+use junobuild_satellite::{
+    get_doc, set_doc, AssertDeleteDocContext, AssertSetDocContext, OnSetDocContext, SetDoc,
+};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+
+// Validation constants
+const MIN_POOL_NAME_LENGTH: usize = 2;
+const MAX_POOL_NAME_LENGTH: usize = 100;
+const MAX_TRANSACTION_AMOUNT: f64 = 100_000_000.0;
+const UNIT_EPSILON: f64 = 1e-9;
+
+// ============================================
+// HELPERS
+// ============================================
+
+fn load_pool(pool_id: &str) -> std::result::Result<InvestmentPoolData, String> {
+    let doc = get_doc("investment_pools".to_string(), pool_id.to_string())
+        .ok_or_else(|| format!("Investment pool not found: {}", pool_id))?;
+
+    decode_doc_data(&doc.data).map_err(|e| format!("Failed to decode investment pool data: {}", e))
+}
+
+fn load_waqf(waqf_id: &str) -> std::result::Result<WaqfData, String> {
+    let doc = get_doc("waqfs".to_string(), waqf_id.to_string())
+        .ok_or_else(|| format!("Waqf not found: {}", waqf_id))?;
+
+    decode_doc_data(&doc.data).map_err(|e| format!("Failed to decode waqf data: {}", e))
+}
+
+fn member_units(pool: &InvestmentPoolData, waqf_id: &str) -> f64 {
+    pool.members
+        .iter()
+        .find(|m| m.waqf_id == waqf_id)
+        .map(|m| m.units)
+        .unwrap_or(0.0)
+}
+
+/// Split a pooled return across members pro-rata by units, in whole cents.
+/// Remainders go to the largest fractional share, ties to the earliest member.
+pub fn pool_return_shares(pool: &InvestmentPoolData, amount: f64) -> Vec<(String, f64)> {
+    if pool.total_units <= 0.0 {
+        return Vec::new();
+    }
+
+    let weights: Vec<f64> = pool.members.iter().map(|m| m.units / pool.total_units).collect();
+
+    pool.members
+        .iter()
+        .zip(apportion_cents(to_cents(amount), &weights))
+        .filter(|(_, cents)| *cents > 0)
+        .map(|(member, cents)| (member.waqf_id.clone(), cents as f64 / 100.0))
+        .collect()
+}
+
+fn member_return_record(tx: &PoolTransactionData, waqf_id: &str, amount: f64) -> InvestmentReturnData {
+    InvestmentReturnData {
+        id: format!("{}_{}", tx.id, waqf_id),
+        waqf_id: waqf_id.to_string(),
+        period_start: tx.period_start.clone().unwrap_or_default(),
+        period_end: tx.period_end.clone().unwrap_or_default(),
+        amount,
+        notes: Some(format!("Pro-rata share of pool {} return {}", tx.pool_id, tx.id)),
+        recorded_by: tx.recorded_by.clone(),
+        recorded_at: tx.recorded_at.clone(),
+        pool_transaction_id: Some(tx.id.clone()),
+    }
+}
+
+// ============================================
+// POOL VALIDATION
+// ============================================
+
+fn validate_pool_data(pool: &InvestmentPoolData) -> std::result::Result<(), String> {
+    if pool.id.trim().is_empty() {
+        return Err("Investment pool ID cannot be empty".into());
+    }
+
+    let name_len = pool.name.trim().len();
+    if !(MIN_POOL_NAME_LENGTH..=MAX_POOL_NAME_LENGTH).contains(&name_len) {
+        return Err(format!(
+            "Pool name must be between {} and {} characters",
+            MIN_POOL_NAME_LENGTH, MAX_POOL_NAME_LENGTH
+        ));
+    }
+
+    if pool.instrument.trim().is_empty() {
+        return Err("Pool instrument cannot be empty".into());
+    }
+
+    if pool.created_by.trim().is_empty() {
+        return Err("Created by field cannot be empty".into());
+    }
+
+    if pool.total_units < 0.0 || pool.total_value < 0.0 || pool.nav_per_unit <= 0.0 {
+        return Err("Pool units and value cannot be negative and NAV must be positive".into());
+    }
+
+    let member_total: f64 = pool.members.iter().map(|m| m.units).sum();
+    if (member_total - pool.total_units).abs() > UNIT_EPSILON * pool.members.len().max(1) as f64 {
+        return Err("Pool total units must equal the sum of member units".into());
+    }
+
+    for (index, member) in pool.members.iter().enumerate() {
+        if member.units <= 0.0 {
+            return Err(format!("Pool member {} must hold a positive number of units", member.waqf_id));
+        }
+        if pool.members[..index].iter().any(|m| m.waqf_id == member.waqf_id) {
+            return Err(format!("Waqf {} is listed twice in the pool", member.waqf_id));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn assert_investment_pool_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let pool: InvestmentPoolData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid investment pool data structure: {}", e))?;
+
+    validate_pool_data(&pool)?;
+
+    let caller = context.caller.to_text();
+
+    // Holdings are only moved by the pool transaction hook
+    if is_satellite_caller(&caller) {
+        return Ok(());
+    }
+
+    if !caller_has_role(&caller, &[AdminRole::FinanceOfficer]) {
+        return Err("Only finance officers can manage investment pools".into());
+    }
+
+    match &context.data.data.current {
+        None => {
+            if pool.created_by != caller {
+                return Err("Created by must match the calling finance officer".into());
+            }
+            if !pool.members.is_empty() || pool.total_units != 0.0 || pool.total_value != 0.0 {
+                return Err("A new pool starts empty - add members through pool contributions".into());
+            }
+            if (pool.nav_per_unit - 1.0).abs() > f64::EPSILON {
+                return Err("A new pool starts with a NAV of 1.0 per unit".into());
+            }
+        }
+        Some(current_doc) => {
+            let previous: InvestmentPoolData = decode_doc_data(&current_doc.data)
+                .map_err(|e| format!("Cannot decode previous investment pool data: {}", e))?;
+
+            if previous.members != pool.members
+                || previous.total_units != pool.total_units
+                || previous.total_value != pool.total_value
+                || previous.nav_per_unit != pool.nav_per_unit
+            {
+                return Err("Pool holdings can only change through pool transactions".into());
+            }
+
+            if previous.created_by != pool.created_by || previous.created_at != pool.created_at {
+                return Err("Pool creator and creation timestamp are immutable".into());
+            }
+        }
+    }
+
+    ic_cdk::println!(
+        "Investment pool validation passed: {} - {} ({} members, NAV {:.6})",
+        pool.id, pool.name, pool.members.len(), pool.nav_per_unit
+    );
+
+    Ok(())
+}
+
+pub fn assert_investment_pool_deletion(context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    let current_doc = context.data.data.current.as_ref()
+        .ok_or("No current document found for deletion")?;
+    let pool: InvestmentPoolData = decode_doc_data(&current_doc.data)
+        .map_err(|e| format!("Cannot decode investment pool data for deletion: {}", e))?;
+
+    if !pool.members.is_empty() {
+        return Err("Cannot delete an investment pool that still has members".into());
+    }
+
+    Ok(())
+}
+
+pub fn handle_investment_pool_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let pool: InvestmentPoolData = decode_doc_data(&context.data.data.after.data)?;
+
+    let operation_type = if context.data.data.before.is_none() { "CREATE" } else { "UPDATE" };
+
+    ic_cdk::println!(
+        "Investment pool {}: {} - Members: {}, Units: {:.6}, Value: {:.2}, NAV: {:.6}",
+        operation_type,
+        pool.id,
+        pool.members.len(),
+        pool.total_units,
+        pool.total_value,
+        pool.nav_per_unit
+    );
+
+    Ok(())
+}
+
+// ============================================
+// POOL TRANSACTION VALIDATION
+// ============================================
+
+fn validate_pool_transaction_data(tx: &PoolTransactionData) -> std::result::Result<(), String> {
+    if tx.id.trim().is_empty() {
+        return Err("Pool transaction ID cannot be empty".into());
+    }
+
+    if tx.pool_id.trim().is_empty() {
+        return Err("Pool ID cannot be empty".into());
+    }
+
+    if !tx.amount.is_finite() || tx.amount > MAX_TRANSACTION_AMOUNT {
+        return Err(format!("Pool transaction amount must be a finite number up to {}", MAX_TRANSACTION_AMOUNT));
+    }
+
+    if tx.recorded_by.trim().is_empty() {
+        return Err("Recorded by field cannot be empty".into());
+    }
+
+    tx.recorded_at
+        .parse::<u64>()
+        .map_err(|_| "Invalid recorded at format")?;
+
+    match tx.transaction_type {
+        PoolTransactionType::Contribution | PoolTransactionType::Withdrawal => {
+            if tx.waqf_id.as_deref().unwrap_or("").trim().is_empty() {
+                return Err("Contributions and withdrawals require a waqf ID".into());
+            }
+            if tx.amount <= 0.0 {
+                return Err("Contribution and withdrawal amounts must be positive".into());
+            }
+        }
+        PoolTransactionType::Return => {
+            if tx.period_start.is_none() || tx.period_end.is_none() {
+                return Err("Pool returns require a return period".into());
+            }
+            if tx.amount <= 0.0 {
+                return Err("Pool return amount must be positive - record losses as a revaluation".into());
+            }
+        }
+        PoolTransactionType::Revaluation => {
+            if tx.amount < 0.0 {
+                return Err("Pool value cannot be negative".into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_transaction_against_pool(tx: &PoolTransactionData, pool: &InvestmentPoolData) -> std::result::Result<(), String> {
+    match tx.transaction_type {
+        PoolTransactionType::Contribution => {
+            let waqf_id = tx.waqf_id.as_deref().unwrap_or_default();
+            let waqf = load_waqf(waqf_id)?;
+
+            if waqf.status != WaqfStatus::Active {
+                return Err(format!("Waqf {} is not active", waqf_id));
+            }
+            if waqf.investment_strategy.is_none() {
+                return Err(format!("Waqf {} has no investment strategy", waqf_id));
+            }
+
+            let already_pooled = pool.members
+                .iter()
+                .find(|m| m.waqf_id == waqf_id)
+                .map(|m| m.principal)
+                .unwrap_or(0.0);
+            if already_pooled + tx.amount > waqf.financial.current_balance + 0.005 {
+                return Err(format!(
+                    "Contribution exceeds waqf balance. Pooled: {:.2}, Requested: {:.2}, Balance: {:.2}",
+                    already_pooled, tx.amount, waqf.financial.current_balance
+                ));
+            }
+        }
+        PoolTransactionType::Withdrawal => {
+            let waqf_id = tx.waqf_id.as_deref().unwrap_or_default();
+            let held = member_units(pool, waqf_id);
+            let units = tx.amount / pool.nav_per_unit;
+            if held <= 0.0 {
+                return Err(format!("Waqf {} holds no units in pool {}", waqf_id, pool.id));
+            }
+            if units > held + UNIT_EPSILON {
+                return Err(format!(
+                    "Withdrawal of {:.2} needs {:.6} units but waqf {} holds {:.6}",
+                    tx.amount, units, waqf_id, held
+                ));
+            }
+        }
+        PoolTransactionType::Return => {
+            if pool.members.is_empty() {
+                return Err("Cannot record a return for a pool without members".into());
+            }
+
+            // Every member share must be a valid investment return for its waqf
+            for (waqf_id, share) in pool_return_shares(pool, tx.amount) {
+                let record = member_return_record(tx, &waqf_id, share);
+                validate_investment_return_data(&record)?;
+                validate_return_against_waqf(&record, &load_waqf(&waqf_id)?)
+                    .map_err(|e| format!("Member waqf {}: {}", waqf_id, e))?;
+            }
+        }
+        PoolTransactionType::Revaluation => {
+            if pool.total_units <= 0.0 {
+                return Err("Cannot revalue a pool without units".into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn assert_pool_transaction_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let tx: PoolTransactionData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid pool transaction data structure: {}", e))?;
+
+    if context.data.data.current.is_some() {
+        return Err("Pool transactions are immutable once recorded".into());
+    }
+
+    let caller = context.caller.to_text();
+    if !caller_has_role(&caller, &[AdminRole::FinanceOfficer]) {
+        return Err("Only finance officers can record pool transactions".into());
+    }
+
+    if tx.recorded_by != caller {
+        return Err("Recorded by must match the calling finance officer".into());
+    }
+
+    validate_pool_transaction_data(&tx)?;
+
+    let pool = load_pool(&tx.pool_id)?;
+    validate_transaction_against_pool(&tx, &pool)?;
+
+    ic_cdk::println!(
+        "Pool transaction validation passed: {} - Pool: {}, Type: {:?}, Amount: {}",
+        tx.id, tx.pool_id, tx.transaction_type, tx.amount
+    );
+
+    Ok(())
+}
+
+pub fn assert_pool_transaction_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Pool transactions cannot be deleted for audit purposes".into())
+}
+
+// ============================================
+// ON SET HOOK
+// ============================================
+
+pub fn handle_pool_transaction_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let tx: PoolTransactionData = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode pool transaction data: {}", e))?;
+
+    ic_cdk::println!(
+        "Pool transaction CREATE: {} - Pool: {}, Type: {:?}, Waqf: {:?}, Amount: {}",
+        tx.id, tx.pool_id, tx.transaction_type, tx.waqf_id, tx.amount
+    );
+
+    if context.data.data.before.is_some() {
+        return Ok(());
+    }
+
+    let pool_doc = get_doc("investment_pools".to_string(), tx.pool_id.clone())
+        .ok_or_else(|| format!("Investment pool not found: {}", tx.pool_id))?;
+    let mut pool: InvestmentPoolData = decode_doc_data(&pool_doc.data)
+        .map_err(|e| format!("Failed to decode investment pool data: {}", e))?;
+
+    match tx.transaction_type {
+        PoolTransactionType::Contribution => {
+            let waqf_id = tx.waqf_id.clone().unwrap_or_default();
+            let units = tx.amount / pool.nav_per_unit;
+
+            match pool.members.iter_mut().find(|m| m.waqf_id == waqf_id) {
+                Some(member) => {
+                    member.units += units;
+                    member.principal += tx.amount;
+                }
+                None => pool.members.push(PoolMember {
+                    waqf_id: waqf_id.clone(),
+                    units,
+                    principal: tx.amount,
+                }),
+            }
+            pool.total_units += units;
+            pool.total_value += tx.amount;
+
+            ic_cdk::println!(
+                "Minted {:.6} units at NAV {:.6} for waqf {} in pool {}",
+                units, pool.nav_per_unit, waqf_id, pool.id
+            );
+        }
+        PoolTransactionType::Withdrawal => {
+            let waqf_id = tx.waqf_id.clone().unwrap_or_default();
+            let member = pool.members
+                .iter_mut()
+                .find(|m| m.waqf_id == waqf_id)
+                .ok_or_else(|| format!("Waqf {} holds no units in pool {}", waqf_id, pool.id))?;
+
+            let units = (tx.amount / pool.nav_per_unit).min(member.units);
+            let share_redeemed = units / member.units;
+            member.principal = (member.principal * (1.0 - share_redeemed)).max(0.0);
+            member.units -= units;
+            pool.total_units = (pool.total_units - units).max(0.0);
+            pool.total_value = (pool.total_value - tx.amount).max(0.0);
+
+            // Drop members whose holding rounds away to nothing
+            pool.members.retain(|m| m.units > UNIT_EPSILON);
+
+            ic_cdk::println!(
+                "Burned {:.6} units at NAV {:.6} for waqf {} in pool {}",
+                units, pool.nav_per_unit, waqf_id, pool.id
+            );
+        }
+        PoolTransactionType::Revaluation => {
+            pool.total_value = tx.amount;
+        }
+        PoolTransactionType::Return => {
+            for (waqf_id, share) in pool_return_shares(&pool, tx.amount) {
+                let record = member_return_record(&tx, &waqf_id, share);
+                let data = encode_doc_data(&record)
+                    .map_err(|e| format!("Failed to encode investment return data: {}", e))?;
+
+                let set_doc_data = SetDoc {
+                    data,
+                    description: None,
+                    version: None,
+                };
+
+                let _ = set_doc("investment_returns".to_string(), record.id.clone(), set_doc_data);
+
+                ic_cdk::println!(
+                    "Pool {} return {}: waqf {} receives {:.2}",
+                    pool.id, tx.id, waqf_id, share
+                );
+            }
+        }
+    }
+
+    // Units only change at the current NAV, so NAV moves with revaluations alone
+    pool.nav_per_unit = if pool.total_units > UNIT_EPSILON {
+        pool.total_value / pool.total_units
+    } else {
+        pool.total_units = 0.0;
+        pool.total_value = 0.0;
+        1.0
+    };
+    pool.updated_at = Some(ic_cdk::api::time().to_string());
+
+    let updated_data = encode_doc_data(&pool)
+        .map_err(|e| format!("Failed to encode investment pool data: {}", e))?;
+
+    let set_doc_data = SetDoc {
+        data: updated_data,
+        description: pool_doc.description,
+        version: pool_doc.version,
+    };
+
+    let _ = set_doc("investment_pools".to_string(), tx.pool_id.clone(), set_doc_data);
+
+    Ok(())
+}
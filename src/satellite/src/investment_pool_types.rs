@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use candid::CandidType;
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PoolMember {
+    pub waqf_id: String,
+    pub units: f64,      // Units held in the pool
+    pub principal: f64,  // Net principal contributed (contributions minus withdrawals)
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct InvestmentPoolData {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub instrument: String,        // e.g. "sukuk"
+    pub members: Vec<PoolMember>,  // Maintained by the satellite from pool transactions
+    pub total_units: f64,
+    pub total_value: f64,          // Current value of the pool's holdings
+    pub nav_per_unit: f64,         // total_value / total_units (1.0 for an empty pool)
+    pub created_by: String,
+    pub created_at: String,        // Nanosecond timestamp
+    pub updated_at: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolTransactionType {
+    Contribution, // Waqf buys units at the current NAV
+    Withdrawal,   // Waqf redeems units at the current NAV
+    Return,       // Realised return shared pro-rata by units
+    Revaluation,  // New market value of the pool's holdings
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct PoolTransactionData {
+    pub id: String,
+    pub pool_id: String,
+    pub transaction_type: PoolTransactionType,
+    pub waqf_id: Option<String>,      // Member waqf for contributions and withdrawals
+    pub amount: f64,                  // Cash amount, or the new pool value for revaluations
+    pub period_start: Option<String>, // Return period (nanoseconds), required for returns
+    pub period_end: Option<String>,
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub recorded_at: String,          // Nanosecond timestamp
+}
@@ -0,0 +1,311 @@
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::bucket_accounting;
+use crate::calendar;
+use crate::scheduler;
+use crate::waqf_types::{InvestmentReturnData, TrusteePower, WaqfData, WaqfStatus};
+use junobuild_satellite::{
+    get_doc, set_doc, AssertDeleteDocContext, AssertSetDocContext, Doc, OnSetDocContext, SetDoc,
+};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+
+// Validation constants
+const MAX_RETURN_AMOUNT: f64 = 100_000_000.0;
+const MAX_NOTES_LENGTH: usize = 1000;
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+fn parse_nanos(value: &str, field: &str) -> std::result::Result<u64, String> {
+    value
+        .parse::<u64>()
+        .map_err(|_| format!("Invalid {} format: {}", field, value))
+}
+
+pub fn validate_investment_return_data(record: &InvestmentReturnData) -> std::result::Result<(), String> {
+    if record.id.trim().is_empty() {
+        return Err("Investment return ID cannot be empty".into());
+    }
+
+    if record.waqf_id.trim().is_empty() {
+        return Err("Waqf ID cannot be empty".into());
+    }
+
+    if !record.amount.is_finite() {
+        return Err("Investment return amount must be a finite number".into());
+    }
+
+    if record.amount.abs() > MAX_RETURN_AMOUNT {
+        return Err(format!("Investment return amount too large: maximum {}", MAX_RETURN_AMOUNT));
+    }
+
+    let start = parse_nanos(&record.period_start, "period start")?;
+    let end = parse_nanos(&record.period_end, "period end")?;
+    if end <= start {
+        return Err("Return period end must be after period start".into());
+    }
+
+    if end > ic_cdk::api::time() {
+        return Err("Cannot record a return for a period that has not ended yet".into());
+    }
+
+    if record.recorded_by.trim().is_empty() {
+        return Err("Recorded by field cannot be empty".into());
+    }
+
+    parse_nanos(&record.recorded_at, "recorded at")?;
+
+    if let Some(ref notes) = record.notes {
+        if notes.len() > MAX_NOTES_LENGTH {
+            return Err(format!("Notes too long: maximum {} characters", MAX_NOTES_LENGTH));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_return_against_waqf(record: &InvestmentReturnData, waqf: &WaqfData) -> std::result::Result<(), String> {
+    waqf
+        .investment_strategy
+        .as_ref()
+        .ok_or_else(|| format!("Waqf {} has no investment strategy", waqf.id))?;
+
+    if waqf.status != WaqfStatus::Active {
+        return Err(format!("Cannot record returns for a waqf with status '{}'", waqf.status));
+    }
+
+    // Periods must follow each other without overlapping
+    if let Some(ref last_end) = waqf.financial.last_return_period_end {
+        let last_end = parse_nanos(last_end, "last return period end")?;
+        let start = parse_nanos(&record.period_start, "period start")?;
+        if start < last_end {
+            return Err(format!(
+                "Return period overlaps a period already recorded (previous period ended at {})",
+                last_end
+            ));
+        }
+    }
+
+    // A loss cannot eat into the preserved principal
+    if record.amount < 0.0 && -record.amount > waqf.financial.undistributed_returns + 0.005 {
+        return Err(format!(
+            "Recorded loss {:.2} exceeds undistributed returns {:.2}; principal losses must be handled by a financial audit",
+            -record.amount, waqf.financial.undistributed_returns
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn assert_investment_return_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let record: InvestmentReturnData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid investment return data structure: {}", e))?;
+
+    // Recorded returns are part of the financial history and never rewritten
+    if context.data.data.current.is_some() {
+        return Err("Investment return records are immutable once recorded".into());
+    }
+
+    let caller = context.caller.to_text();
+    if is_satellite_caller(&caller) {
+        // Member shares of a pooled return are written by the pool transaction hook
+        if record.pool_transaction_id.is_none() {
+            return Err("Satellite-recorded returns must reference a pool transaction".into());
+        }
+    } else {
+        if record.recorded_by != caller {
+            return Err("Recorded by must match the caller".into());
+        }
+
+        if record.pool_transaction_id.is_some() {
+            return Err("Pooled returns are recorded through the investment pool".into());
+        }
+    }
+
+    validate_investment_return_data(&record)?;
+
+    let doc = get_doc("waqfs".to_string(), record.waqf_id.clone())
+        .ok_or_else(|| format!("Waqf not found: {}", record.waqf_id))?;
+    let waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+
+    // Finance officers record returns for any waqf, trustees only for the waqfs they manage
+    if !is_satellite_caller(&caller)
+        && !caller_has_role(&caller, &[AdminRole::FinanceOfficer])
+        && !waqf.trustee_has_power(&caller, TrusteePower::RecordReturns)
+    {
+        return Err("Only finance officers and trustees with the record returns power can record investment returns".into());
+    }
+
+    validate_return_against_waqf(&record, &waqf)?;
+
+    ic_cdk::println!(
+        "Investment return validation passed: {} - Waqf: {}, Amount: {}",
+        record.id, record.waqf_id, record.amount
+    );
+
+    Ok(())
+}
+
+pub fn assert_investment_return_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Investment return records cannot be deleted for audit purposes".into())
+}
+
+pub fn handle_investment_return_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let record: InvestmentReturnData = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode investment return data: {}", e))?;
+
+    ic_cdk::println!(
+        "Investment return CREATE: {} - Waqf: {}, Amount: {}, Period: {} - {}",
+        record.id, record.waqf_id, record.amount, record.period_start, record.period_end
+    );
+
+    if context.data.data.before.is_none() {
+        apply_return_to_waqf(&record)?;
+    }
+
+    Ok(())
+}
+
+fn apply_return_to_waqf(record: &InvestmentReturnData) -> std::result::Result<(), String> {
+    let doc = get_doc("waqfs".to_string(), record.waqf_id.clone())
+        .ok_or_else(|| format!("Waqf not found: {}", record.waqf_id))?;
+    let mut waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+
+    let frequency = waqf
+        .investment_strategy
+        .as_ref()
+        .map(|s| s.distribution_frequency)
+        .ok_or_else(|| format!("Waqf {} has no investment strategy", waqf.id))?;
+
+    let start = parse_nanos(&record.period_start, "period start")?;
+    let end = parse_nanos(&record.period_end, "period end")?;
+
+    // Annualised growth on the balance invested during the period
+    let invested = waqf.financial.current_balance;
+    let period_days = ((end - start) / NANOS_PER_DAY).max(1) as f64;
+    waqf.financial.growth_rate = if invested > 0.0 {
+        (record.amount / invested) * (365.0 / period_days) * 100.0
+    } else {
+        0.0
+    };
+
+    waqf.financial.investment_returns.push(record.amount);
+    waqf.financial.total_investment_return += record.amount;
+    waqf.financial.current_balance += record.amount;
+    waqf.financial.undistributed_returns = (waqf.financial.undistributed_returns + record.amount).max(0.0);
+    waqf.financial.last_return_period_end = Some(record.period_end.clone());
+
+    if waqf.financial.next_distribution_date.is_none() {
+        let next = calendar::add_months(end, frequency.months());
+        waqf.financial.next_distribution_date = Some(next.to_string());
+    }
+
+    let now = ic_cdk::api::time();
+    distribute_if_due(&mut waqf, now)?;
+    waqf.updated_at = Some(now.to_string());
+
+    let updated_data = encode_doc_data(&waqf)
+        .map_err(|e| format!("Failed to encode waqf data: {}", e))?;
+
+    let set_doc_data = SetDoc {
+        data: updated_data,
+        description: doc.description,
+        version: doc.version,
+    };
+
+    let _ = set_doc("waqfs".to_string(), record.waqf_id.clone(), set_doc_data);
+
+    ic_cdk::println!(
+        "Updated waqf {} returns: +{} (total {:.2}, growth {:.2}%), undistributed: {:.2}",
+        waqf.id,
+        record.amount,
+        waqf.financial.total_investment_return,
+        waqf.financial.growth_rate,
+        waqf.financial.undistributed_returns
+    );
+
+    Ok(())
+}
+
+/// Split undistributed returns into per-cause pending distributions once the
+/// distribution date has passed, then move the date on by the strategy frequency.
+/// Returns whether the waqf was changed.
+fn distribute_if_due(waqf: &mut WaqfData, now: u64) -> std::result::Result<bool, String> {
+    let frequency = match waqf.investment_strategy.as_ref() {
+        Some(strategy) => strategy.distribution_frequency,
+        None => return Ok(false),
+    };
+
+    let next_date = match waqf.financial.next_distribution_date.as_ref() {
+        Some(date) => parse_nanos(date, "next distribution date")?,
+        None => return Ok(false),
+    };
+
+    if now < next_date {
+        return Ok(false);
+    }
+
+    let distributable = waqf.financial.undistributed_returns;
+    if distributable > 0.0 {
+        let shares = bucket_accounting::split_by_cause(waqf, distributable);
+        for (cause_id, share) in &shares {
+            *waqf.financial.pending_distributions.entry(cause_id.clone()).or_insert(0.0) += share;
+            ic_cdk::println!(
+                "Return distribution for waqf {}: cause {} receives {:.2}",
+                waqf.id, cause_id, share
+            );
+        }
+        waqf.financial.undistributed_returns = 0.0;
+    }
+
+    // Skip any periods missed while the satellite was idle; count from the original date
+    // so month-end clamping does not drift the schedule
+    let interval = frequency.months();
+    let mut periods = 1;
+    let mut next = calendar::add_months(next_date, interval);
+    while next <= now {
+        periods += 1;
+        next = calendar::add_months(next_date, interval * periods);
+    }
+    waqf.financial.next_distribution_date = Some(next.to_string());
+
+    Ok(true)
+}
+
+/// Scheduled job: run due return distributions across all waqfs
+pub fn distribute_due_returns() {
+    scheduler::sweep_waqfs(distribute_returns_page);
+}
+
+fn distribute_returns_page(waqfs: Vec<(String, Doc)>) {
+    let now = ic_cdk::api::time();
+
+    for (key, doc) in waqfs {
+        let mut waqf: WaqfData = match decode_doc_data(&doc.data) {
+            Ok(waqf) => waqf,
+            Err(e) => {
+                ic_cdk::println!("WARN - Skipping waqf {} in return distribution: {}", key, e);
+                continue;
+            }
+        };
+
+        match distribute_if_due(&mut waqf, now) {
+            Ok(true) => {
+                waqf.updated_at = Some(now.to_string());
+                match encode_doc_data(&waqf) {
+                    Ok(data) => {
+                        let set_doc_data = SetDoc {
+                            data,
+                            description: doc.description,
+                            version: doc.version,
+                        };
+                        let _ = set_doc("waqfs".to_string(), key.clone(), set_doc_data);
+                        ic_cdk::println!("Scheduled return distribution completed for waqf {}", key);
+                    }
+                    Err(e) => ic_cdk::println!("ERROR - Cannot encode waqf {}: {}", key, e),
+                }
+            }
+            Ok(false) => {}
+            Err(e) => ic_cdk::println!("ERROR - Return distribution failed for waqf {}: {}", key, e),
+        }
+    }
+}
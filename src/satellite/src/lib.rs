@@ -1,10 +1,12 @@
 use junobuild_macros::{
-    assert_delete_doc, assert_set_doc, on_set_doc,
+    assert_delete_doc, assert_set_doc, on_init, on_post_upgrade, on_set_doc,
 };
 use junobuild_satellite::{
     include_satellite, AssertDeleteDocContext, AssertSetDocContext, OnSetDocContext,
 };
 
+#[macro_use]
+mod wire_enum;
 mod admin_hooks;
 mod cause_hooks;
 mod category_hooks;
@@ -13,11 +15,40 @@ mod activity_log_hooks;
 mod donation_hooks;
 mod allocation_hooks;
 mod impact_event_hooks;
+mod investment_return_hooks;
+mod investment_pool_hooks;
+mod management_fee_hooks;
+mod settings_hooks;
+mod installment_hooks;
+mod penalty_waiver_hooks;
+mod cause_pool_hooks;
+mod tranche_lineage;
+mod maturity_calendar;
+mod notification_hooks;
+mod deed_hooks;
+mod deed_reference;
+mod termination_hooks;
+mod trustee_hooks;
+mod cause_reallocation_hooks;
+mod template_hooks;
+mod report_hooks;
+mod scheduler;
 pub mod waqf_types;
 pub mod waqf_hooks;
+pub mod tranche_hooks;
 pub mod impact_event_types;
+pub mod investment_pool_types;
+pub mod management_fee_types;
+pub mod notification_types;
+pub mod deed_types;
+pub mod termination_types;
+pub mod trustee_types;
+pub mod template_types;
+pub mod report_types;
 
 mod waqf_utils;
+mod bucket_accounting;
+mod calendar;
 
 // Import all validation hooks
 use crate::admin_hooks::{
@@ -77,8 +108,116 @@ use crate::impact_event_hooks::{
     handle_impact_event_changes,
 };
 
+use crate::investment_return_hooks::{
+    assert_investment_return_operations,
+    assert_investment_return_deletion,
+    handle_investment_return_changes,
+};
+
+use crate::investment_pool_hooks::{
+    assert_investment_pool_operations,
+    assert_investment_pool_deletion,
+    handle_investment_pool_changes,
+    assert_pool_transaction_operations,
+    assert_pool_transaction_deletion,
+    handle_pool_transaction_changes,
+};
+
+use crate::settings_hooks::{
+    assert_platform_settings_operations,
+    assert_platform_settings_deletion,
+    handle_platform_settings_changes,
+};
+
+use crate::management_fee_hooks::{
+    assert_fee_charge_operations,
+    assert_fee_charge_deletion,
+    handle_fee_charge_changes,
+    assert_platform_account_operations,
+    assert_platform_account_deletion,
+};
+
+use crate::installment_hooks::{
+    assert_due_installment_operations,
+    assert_due_installment_deletion,
+    assert_installment_payout_operations,
+    assert_installment_payout_deletion,
+    handle_installment_payout_changes,
+};
+
+use crate::tranche_hooks::{
+    assert_tranche_return_operations,
+    assert_tranche_return_deletion,
+    handle_tranche_return_changes,
+};
+
+use crate::penalty_waiver_hooks::{
+    assert_penalty_waiver_operations,
+    assert_penalty_waiver_deletion,
+    handle_penalty_waiver_changes,
+};
+
+use crate::notification_hooks::{
+    assert_notification_operations,
+    assert_notification_deletion,
+    tag_untracked_notifications,
+};
+
+use crate::deed_hooks::{
+    assert_deed_version_operations,
+    assert_deed_version_deletion,
+    handle_deed_version_changes,
+};
+
+use crate::deed_reference::{
+    assert_deed_reference_operations,
+    assert_deed_reference_deletion,
+};
+
+use crate::termination_hooks::{
+    assert_waqf_termination_operations,
+    assert_waqf_termination_deletion,
+    handle_waqf_termination_changes,
+};
+
+use crate::trustee_hooks::{
+    assert_trustee_appointment_operations,
+    assert_trustee_appointment_deletion,
+    handle_trustee_appointment_changes,
+};
+
+use crate::cause_reallocation_hooks::{
+    assert_cause_reallocation_operations,
+    assert_cause_reallocation_deletion,
+    handle_cause_reallocation_changes,
+};
+
+use crate::template_hooks::{
+    assert_waqf_template_operations,
+    assert_waqf_template_deletion,
+    handle_waqf_template_changes,
+};
+
+use crate::report_hooks::{
+    assert_report_operations,
+    assert_report_deletion,
+};
+
+use crate::scheduler::start_scheduler;
+
+#[on_init]
+fn on_init() {
+    start_scheduler();
+}
+
+#[on_post_upgrade]
+fn on_post_upgrade() {
+    start_scheduler();
+    tag_untracked_notifications();
+}
+
 // Main on_set_doc handler
-#[on_set_doc(collections = ["admins", "causes", "categories", "subcategories", "admin_requests", "activity_logs", "waqfs", "donations", "allocations", "impact_events"])]
+#[on_set_doc(collections = ["admins", "causes", "categories", "subcategories", "admin_requests", "activity_logs", "waqfs", "donations", "allocations", "impact_events", "investment_returns", "investment_pools", "pool_transactions", "platform_settings", "fee_charges", "platform_accounts", "due_installments", "installment_payouts", "tranche_returns", "penalty_waivers", "notifications", "deed_versions", "waqf_terminations", "trustee_appointments", "cause_reallocations", "waqf_templates"])]
 fn on_set_doc(context: OnSetDocContext) -> std::result::Result<(), String> {
     // Route to appropriate change handler based on collection
     match context.data.collection.as_str() {
@@ -112,6 +251,45 @@ fn on_set_doc(context: OnSetDocContext) -> std::result::Result<(), String> {
         "impact_events" => {
             handle_impact_event_changes(context)?
         },
+        "investment_returns" => {
+            handle_investment_return_changes(context)?
+        },
+        "investment_pools" => {
+            handle_investment_pool_changes(context)?
+        },
+        "pool_transactions" => {
+            handle_pool_transaction_changes(context)?
+        },
+        "platform_settings" => {
+            handle_platform_settings_changes(context)?
+        },
+        "fee_charges" => {
+            handle_fee_charge_changes(context)?
+        },
+        "installment_payouts" => {
+            handle_installment_payout_changes(context)?
+        },
+        "tranche_returns" => {
+            handle_tranche_return_changes(context)?
+        },
+        "penalty_waivers" => {
+            handle_penalty_waiver_changes(context)?
+        },
+        "deed_versions" => {
+            handle_deed_version_changes(context)?
+        },
+        "waqf_terminations" => {
+            handle_waqf_termination_changes(context)?
+        },
+        "trustee_appointments" => {
+            handle_trustee_appointment_changes(context)?
+        },
+        "cause_reallocations" => {
+            handle_cause_reallocation_changes(context)?
+        },
+        "waqf_templates" => {
+            handle_waqf_template_changes(context)?
+        },
         _ => {
             // Log unknown collection access
             ic_cdk::println!("Document change in unhandled collection: {}", context.data.collection);
@@ -121,7 +299,7 @@ fn on_set_doc(context: OnSetDocContext) -> std::result::Result<(), String> {
     Ok(())
 }
 
-#[assert_set_doc(collections = ["admins", "causes", "categories", "subcategories", "admin_requests", "activity_logs", "waqfs", "donations", "allocations", "impact_events"])]
+#[assert_set_doc(collections = ["admins", "causes", "categories", "subcategories", "admin_requests", "activity_logs", "waqfs", "donations", "allocations", "impact_events", "investment_returns", "investment_pools", "pool_transactions", "platform_settings", "fee_charges", "platform_accounts", "due_installments", "installment_payouts", "tranche_returns", "penalty_waivers", "notifications", "deed_versions", "deed_references", "waqf_terminations", "trustee_appointments", "cause_reallocations", "waqf_templates", "reports"])]
 fn assert_set_doc(context: AssertSetDocContext) -> std::result::Result<(), String> {
     // Route to appropriate assertion handler based on collection
     match context.data.collection.as_str() {
@@ -155,6 +333,60 @@ fn assert_set_doc(context: AssertSetDocContext) -> std::result::Result<(), Strin
         "impact_events" => {
             assert_impact_event_operations(context)
         },
+        "investment_returns" => {
+            assert_investment_return_operations(context)
+        },
+        "investment_pools" => {
+            assert_investment_pool_operations(context)
+        },
+        "pool_transactions" => {
+            assert_pool_transaction_operations(context)
+        },
+        "platform_settings" => {
+            assert_platform_settings_operations(context)
+        },
+        "fee_charges" => {
+            assert_fee_charge_operations(context)
+        },
+        "platform_accounts" => {
+            assert_platform_account_operations(context)
+        },
+        "due_installments" => {
+            assert_due_installment_operations(context)
+        },
+        "installment_payouts" => {
+            assert_installment_payout_operations(context)
+        },
+        "tranche_returns" => {
+            assert_tranche_return_operations(context)
+        },
+        "penalty_waivers" => {
+            assert_penalty_waiver_operations(context)
+        },
+        "notifications" => {
+            assert_notification_operations(context)
+        },
+        "deed_versions" => {
+            assert_deed_version_operations(context)
+        },
+        "deed_references" => {
+            assert_deed_reference_operations(context)
+        },
+        "waqf_terminations" => {
+            assert_waqf_termination_operations(context)
+        },
+        "trustee_appointments" => {
+            assert_trustee_appointment_operations(context)
+        },
+        "cause_reallocations" => {
+            assert_cause_reallocation_operations(context)
+        },
+        "waqf_templates" => {
+            assert_waqf_template_operations(context)
+        },
+        "reports" => {
+            assert_report_operations(context)
+        },
         _ => {
             // Log unknown collection validation attempt
             ic_cdk::println!("Validation attempt on unhandled collection: {}", context.data.collection);
@@ -163,7 +395,7 @@ fn assert_set_doc(context: AssertSetDocContext) -> std::result::Result<(), Strin
     }
 }
 
-#[assert_delete_doc(collections = ["admins", "causes", "categories", "subcategories", "admin_requests", "activity_logs", "waqfs", "donations", "allocations", "impact_events"])]
+#[assert_delete_doc(collections = ["admins", "causes", "categories", "subcategories", "admin_requests", "activity_logs", "waqfs", "donations", "allocations", "impact_events", "investment_returns", "investment_pools", "pool_transactions", "platform_settings", "fee_charges", "platform_accounts", "due_installments", "installment_payouts", "tranche_returns", "penalty_waivers", "notifications", "deed_versions", "deed_references", "waqf_terminations", "trustee_appointments", "cause_reallocations", "waqf_templates", "reports"])]
 fn assert_delete_doc(context: AssertDeleteDocContext) -> std::result::Result<(), String> {
     // Route to appropriate deletion assertion handler based on collection
     match context.data.collection.as_str() {
@@ -197,6 +429,60 @@ fn assert_delete_doc(context: AssertDeleteDocContext) -> std::result::Result<(),
         "impact_events" => {
             assert_impact_event_deletion(context)
         },
+        "investment_returns" => {
+            assert_investment_return_deletion(context)
+        },
+        "investment_pools" => {
+            assert_investment_pool_deletion(context)
+        },
+        "pool_transactions" => {
+            assert_pool_transaction_deletion(context)
+        },
+        "platform_settings" => {
+            assert_platform_settings_deletion(context)
+        },
+        "fee_charges" => {
+            assert_fee_charge_deletion(context)
+        },
+        "platform_accounts" => {
+            assert_platform_account_deletion(context)
+        },
+        "due_installments" => {
+            assert_due_installment_deletion(context)
+        },
+        "installment_payouts" => {
+            assert_installment_payout_deletion(context)
+        },
+        "tranche_returns" => {
+            assert_tranche_return_deletion(context)
+        },
+        "penalty_waivers" => {
+            assert_penalty_waiver_deletion(context)
+        },
+        "notifications" => {
+            assert_notification_deletion(context)
+        },
+        "deed_versions" => {
+            assert_deed_version_deletion(context)
+        },
+        "deed_references" => {
+            assert_deed_reference_deletion(context)
+        },
+        "waqf_terminations" => {
+            assert_waqf_termination_deletion(context)
+        },
+        "trustee_appointments" => {
+            assert_trustee_appointment_deletion(context)
+        },
+        "cause_reallocations" => {
+            assert_cause_reallocation_deletion(context)
+        },
+        "waqf_templates" => {
+            assert_waqf_template_deletion(context)
+        },
+        "reports" => {
+            assert_report_deletion(context)
+        },
         _ => {
             // Log unknown collection deletion attempt
             ic_cdk::println!("Deletion attempt on unhandled collection: {}", context.data.collection);
@@ -205,4 +491,25 @@ fn assert_delete_doc(context: AssertDeleteDocContext) -> std::result::Result<(),
     }
 }
 
+// ============================================
+// CUSTOM QUERIES
+// ============================================
+
+/// Rollover chain of a tranche with returned, converted and outstanding principal
+#[ic_cdk::query]
+fn get_tranche_lineage(waqf_id: String, tranche_id: String) -> std::result::Result<waqf_types::TrancheLineage, String> {
+    tranche_lineage::get_tranche_lineage(&ic_cdk::api::msg_caller().to_text(), &waqf_id, &tranche_id)
+}
+
+#[ic_cdk::query]
+fn get_maturity_calendar(from: String, to: String) -> std::result::Result<waqf_types::MaturityCalendar, String> {
+    maturity_calendar::get_maturity_calendar(&ic_cdk::api::msg_caller().to_text(), &from, &to)
+}
+
+/// Waqfs whose deed reference contains the search term
+#[ic_cdk::query]
+fn search_deed_references(query: String) -> std::result::Result<Vec<deed_types::DeedReferenceMatch>, String> {
+    deed_reference::search_deed_references(&ic_cdk::api::msg_caller().to_text(), &query)
+}
+
 include_satellite!();
\ No newline at end of file
@@ -0,0 +1,375 @@
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::bucket_accounting::to_cents;
+use crate::management_fee_types::{FeeChargeData, ManagementFeeConfig, PlatformAccountData};
+use crate::scheduler;
+use crate::waqf_types::{FinancialMetrics, WaqfData, WaqfStatus};
+use junobuild_satellite::{
+    get_doc, set_doc, AssertDeleteDocContext, AssertSetDocContext, Doc, OnSetDocContext, SetDoc,
+};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+
+// Settings document and platform account keys
+pub const MANAGEMENT_FEE_SETTINGS_KEY: &str = "management_fee";
+pub const MANAGEMENT_FEE_ACCOUNT_KEY: &str = "management_fees";
+
+// Defaults used until a platform admin stores the fee settings
+const DEFAULT_ANNUAL_RATE_PERCENT: f64 = 1.5;
+const DEFAULT_MAX_SHARE_OF_RETURNS_PERCENT: f64 = 20.0;
+
+// Validation constants
+const MAX_ANNUAL_RATE_PERCENT: f64 = 10.0;
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+const NANOS_PER_YEAR: f64 = 365.0 * NANOS_PER_DAY as f64;
+const MIN_ACCRUAL_INTERVAL_NANOS: u64 = NANOS_PER_DAY;
+
+// ============================================
+// SETTINGS
+// ============================================
+
+/// Current fee settings, falling back to the platform defaults
+pub fn load_fee_config() -> ManagementFeeConfig {
+    get_doc("platform_settings".to_string(), MANAGEMENT_FEE_SETTINGS_KEY.to_string())
+        .and_then(|doc| decode_doc_data::<ManagementFeeConfig>(&doc.data).ok())
+        .unwrap_or_else(|| ManagementFeeConfig {
+            annual_rate_percent: DEFAULT_ANNUAL_RATE_PERCENT,
+            max_share_of_returns_percent: DEFAULT_MAX_SHARE_OF_RETURNS_PERCENT,
+            updated_by: String::new(),
+            updated_at: String::new(),
+        })
+}
+
+fn validate_fee_config(config: &ManagementFeeConfig) -> std::result::Result<(), String> {
+    if !config.annual_rate_percent.is_finite()
+        || config.annual_rate_percent < 0.0
+        || config.annual_rate_percent > MAX_ANNUAL_RATE_PERCENT
+    {
+        return Err(format!(
+            "Annual management fee rate must be between 0 and {}%",
+            MAX_ANNUAL_RATE_PERCENT
+        ));
+    }
+
+    if !config.max_share_of_returns_percent.is_finite()
+        || config.max_share_of_returns_percent < 0.0
+        || config.max_share_of_returns_percent > 100.0
+    {
+        return Err("Maximum share of returns must be between 0 and 100%".into());
+    }
+
+    if config.updated_by.trim().is_empty() {
+        return Err("Updated by field cannot be empty".into());
+    }
+
+    config
+        .updated_at
+        .parse::<u64>()
+        .map_err(|_| "Invalid updated at format")?;
+
+    Ok(())
+}
+
+pub fn assert_fee_settings_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let config: ManagementFeeConfig = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid management fee settings structure: {}", e))?;
+
+    let caller = context.caller.to_text();
+    if !caller_has_role(&caller, &[AdminRole::PlatformAdmin, AdminRole::FinanceOfficer]) {
+        return Err("Only platform admins and finance officers can change fee settings".into());
+    }
+
+    if config.updated_by != caller {
+        return Err("Updated by must match the calling admin".into());
+    }
+
+    validate_fee_config(&config)?;
+
+    ic_cdk::println!(
+        "Management fee settings validation passed: {}% per year, capped at {}% of returns",
+        config.annual_rate_percent, config.max_share_of_returns_percent
+    );
+
+    Ok(())
+}
+
+pub fn handle_fee_settings_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let config: ManagementFeeConfig = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode management fee settings: {}", e))?;
+
+    ic_cdk::println!(
+        "Management fee settings updated by {}: {}% per year, capped at {}% of returns",
+        config.updated_by, config.annual_rate_percent, config.max_share_of_returns_percent
+    );
+
+    Ok(())
+}
+
+// ============================================
+// FEE CHARGES AND PLATFORM ACCOUNT
+// ============================================
+
+pub fn assert_fee_charge_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    if !is_satellite_caller(&context.caller.to_text()) {
+        return Err("Management fees are charged by the satellite only".into());
+    }
+
+    if context.data.data.current.is_some() {
+        return Err("Fee charges are immutable once recorded".into());
+    }
+
+    let charge: FeeChargeData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid fee charge data structure: {}", e))?;
+
+    if charge.amount <= 0.0 || !charge.amount.is_finite() {
+        return Err("Fee charge amount must be positive".into());
+    }
+
+    Ok(())
+}
+
+pub fn assert_fee_charge_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Fee charges cannot be deleted for audit purposes".into())
+}
+
+pub fn handle_fee_charge_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let charge: FeeChargeData = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode fee charge data: {}", e))?;
+
+    ic_cdk::println!(
+        "Fee charge CREATE: {} - Waqf: {}, Amount: {:.2}",
+        charge.id, charge.waqf_id, charge.amount
+    );
+
+    if context.data.data.before.is_none() {
+        credit_fee_account(&charge)?;
+    }
+
+    Ok(())
+}
+
+fn credit_fee_account(charge: &FeeChargeData) -> std::result::Result<(), String> {
+    let existing = get_doc("platform_accounts".to_string(), MANAGEMENT_FEE_ACCOUNT_KEY.to_string());
+
+    let mut account: PlatformAccountData = match &existing {
+        Some(doc) => decode_doc_data(&doc.data)
+            .map_err(|e| format!("Failed to decode platform account data: {}", e))?,
+        None => PlatformAccountData {
+            id: MANAGEMENT_FEE_ACCOUNT_KEY.to_string(),
+            name: "Management fees".to_string(),
+            ..Default::default()
+        },
+    };
+
+    account.balance += charge.amount;
+    account.total_credited += charge.amount;
+    account.updated_at = Some(charge.charged_at.clone());
+
+    let data = encode_doc_data(&account)
+        .map_err(|e| format!("Failed to encode platform account data: {}", e))?;
+
+    let set_doc_data = SetDoc {
+        data,
+        description: existing.as_ref().and_then(|doc| doc.description.clone()),
+        version: existing.as_ref().and_then(|doc| doc.version),
+    };
+
+    let _ = set_doc("platform_accounts".to_string(), MANAGEMENT_FEE_ACCOUNT_KEY.to_string(), set_doc_data);
+
+    ic_cdk::println!(
+        "Platform fee account credited {:.2} from waqf {} - balance {:.2}",
+        charge.amount, charge.waqf_id, account.balance
+    );
+
+    Ok(())
+}
+
+pub fn assert_platform_account_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    if !is_satellite_caller(&context.caller.to_text()) {
+        return Err("Platform accounts are maintained by the satellite only".into());
+    }
+
+    Ok(())
+}
+
+pub fn assert_platform_account_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Platform accounts cannot be deleted".into())
+}
+
+// ============================================
+// ACCRUAL
+// ============================================
+
+/// Funds a fee may be paid from: realised returns first, then spendable balances.
+/// Permanent and revolving principal are never touched.
+fn fee_funding_available(financial: &FinancialMetrics) -> f64 {
+    financial.undistributed_returns
+        + financial.pending_distributions.values().sum::<f64>()
+        + financial.bucket_balances.values().map(|b| b.consumable).sum::<f64>()
+}
+
+fn take(available: &mut f64, remaining: &mut f64) {
+    let taken = available.min(*remaining).max(0.0);
+    *available -= taken;
+    *remaining -= taken;
+}
+
+fn deduct_fee(financial: &mut FinancialMetrics, amount: f64) {
+    let mut remaining = amount;
+
+    take(&mut financial.undistributed_returns, &mut remaining);
+
+    let mut causes: Vec<String> = financial.pending_distributions.keys().cloned().collect();
+    causes.sort();
+    for cause_id in causes {
+        if let Some(pending) = financial.pending_distributions.get_mut(&cause_id) {
+            take(pending, &mut remaining);
+        }
+    }
+
+    let mut causes: Vec<String> = financial.bucket_balances.keys().cloned().collect();
+    causes.sort();
+    for cause_id in causes {
+        if let Some(balances) = financial.bucket_balances.get_mut(&cause_id) {
+            take(&mut balances.consumable, &mut remaining);
+        }
+    }
+}
+
+/// Accrue the management fee on a waqf's managed balance since its last accrual.
+/// Moves the accrual clock and returns the charge to post, if any.
+fn accrue_fee(
+    waqf: &mut WaqfData,
+    config: &ManagementFeeConfig,
+    now: u64,
+) -> std::result::Result<Option<FeeChargeData>, String> {
+    let last = match waqf.financial.last_fee_accrual_at.as_ref() {
+        Some(last) => last
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid last fee accrual format: {}", last))?,
+        None => {
+            // Start the clock - fees accrue from the first sweep that sees the waqf
+            waqf.financial.last_fee_accrual_at = Some(now.to_string());
+            return Ok(None);
+        }
+    };
+
+    if now.saturating_sub(last) < MIN_ACCRUAL_INTERVAL_NANOS {
+        return Ok(None);
+    }
+
+    if waqf.status != WaqfStatus::Active {
+        // No fees while the waqf is not being managed
+        waqf.financial.last_fee_accrual_at = Some(now.to_string());
+        return Ok(None);
+    }
+
+    let managed_balance = waqf.financial.current_balance.max(0.0);
+    let elapsed_years = (now - last) as f64 / NANOS_PER_YEAR;
+    let accrued = managed_balance * config.annual_rate_percent / 100.0 * elapsed_years;
+
+    // Let sub-cent accruals build up instead of rounding them away every sweep
+    if to_cents(accrued) == 0 {
+        return Ok(None);
+    }
+
+    let cap_headroom = (waqf.financial.total_investment_return.max(0.0) * config.max_share_of_returns_percent / 100.0
+        - waqf.financial.management_fees_charged)
+        .max(0.0);
+    let available = fee_funding_available(&waqf.financial);
+    let fee = to_cents(accrued.min(cap_headroom).min(available)) as f64 / 100.0;
+
+    // Any accrual above the cap or the available funds is waived, not carried forward
+    waqf.financial.last_fee_accrual_at = Some(now.to_string());
+
+    if fee < accrued - 0.005 {
+        ic_cdk::println!(
+            "Management fee for waqf {} limited to {:.2} of {:.2} accrued (cap headroom {:.2}, available {:.2})",
+            waqf.id, fee, accrued, cap_headroom, available
+        );
+    }
+
+    if fee <= 0.0 {
+        return Ok(None);
+    }
+
+    deduct_fee(&mut waqf.financial, fee);
+    waqf.financial.current_balance -= fee;
+    waqf.financial.management_fees_charged += fee;
+
+    Ok(Some(FeeChargeData {
+        id: format!("fee_{}_{}", waqf.id, now),
+        waqf_id: waqf.id.clone(),
+        amount: fee,
+        managed_balance,
+        annual_rate_percent: config.annual_rate_percent,
+        period_start: last.to_string(),
+        period_end: now.to_string(),
+        charged_at: now.to_string(),
+    }))
+}
+
+/// Scheduled job: accrue management fees across all waqfs
+pub fn accrue_management_fees() {
+    scheduler::sweep_waqfs(accrue_fees_page);
+}
+
+fn accrue_fees_page(waqfs: Vec<(String, Doc)>) {
+    let now = ic_cdk::api::time();
+    let config = load_fee_config();
+
+    for (key, doc) in waqfs {
+        let mut waqf: WaqfData = match decode_doc_data(&doc.data) {
+            Ok(waqf) => waqf,
+            Err(e) => {
+                ic_cdk::println!("WARN - Skipping waqf {} in fee accrual: {}", key, e);
+                continue;
+            }
+        };
+
+        let previous_accrual = waqf.financial.last_fee_accrual_at.clone();
+        let charge = match accrue_fee(&mut waqf, &config, now) {
+            Ok(charge) => charge,
+            Err(e) => {
+                ic_cdk::println!("ERROR - Fee accrual failed for waqf {}: {}", key, e);
+                continue;
+            }
+        };
+
+        if waqf.financial.last_fee_accrual_at == previous_accrual {
+            continue;
+        }
+
+        if charge.is_some() {
+            waqf.updated_at = Some(now.to_string());
+        }
+
+        let data = match encode_doc_data(&waqf) {
+            Ok(data) => data,
+            Err(e) => {
+                ic_cdk::println!("ERROR - Cannot encode waqf {}: {}", key, e);
+                continue;
+            }
+        };
+
+        let set_doc_data = SetDoc {
+            data,
+            description: doc.description,
+            version: doc.version,
+        };
+        let _ = set_doc("waqfs".to_string(), key.clone(), set_doc_data);
+
+        if let Some(charge) = charge {
+            match encode_doc_data(&charge) {
+                Ok(data) => {
+                    let set_doc_data = SetDoc {
+                        data,
+                        description: None,
+                        version: None,
+                    };
+                    let _ = set_doc("fee_charges".to_string(), charge.id.clone(), set_doc_data);
+                    ic_cdk::println!("Charged management fee {:.2} to waqf {}", charge.amount, key);
+                }
+                Err(e) => ic_cdk::println!("ERROR - Cannot encode fee charge for waqf {}: {}", key, e),
+            }
+        }
+    }
+}
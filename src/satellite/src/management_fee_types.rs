@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use candid::CandidType;
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct ManagementFeeConfig {
+    pub annual_rate_percent: f64,          // Charged per year on the managed balance
+    pub max_share_of_returns_percent: f64, // Lifetime fees never exceed this share of investment returns
+    pub updated_by: String,
+    pub updated_at: String,                // Nanosecond timestamp
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct FeeChargeData {
+    pub id: String,
+    pub waqf_id: String,
+    pub amount: f64,
+    pub managed_balance: f64,       // Balance the fee accrued on
+    pub annual_rate_percent: f64,
+    pub period_start: String,       // Nanosecond timestamps
+    pub period_end: String,
+    pub charged_at: String,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PlatformAccountData {
+    pub id: String,
+    pub name: String,
+    pub balance: f64,
+    pub total_credited: f64,
+    pub updated_at: Option<String>,
+}
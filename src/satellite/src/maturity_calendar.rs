@@ -0,0 +1,153 @@
+use crate::admin_hooks::{caller_has_role, AdminRole};
+use crate::calendar;
+use crate::notification_hooks::tranche_owner;
+use crate::tranche_hooks::{effective_expiration_preference, is_settled};
+use crate::waqf_types::{
+    InstallmentStatus, MaturityCalendar, MaturityCalendarItem, MaturityCalendarMonth, TrancheStatus, WaqfData,
+};
+use junobuild_satellite::list_docs;
+use junobuild_shared::types::list::ListParams;
+use junobuild_utils::decode_doc_data;
+use std::collections::BTreeMap;
+
+// Longest window a single query may cover
+const MAX_WINDOW_MONTHS: u32 = 60;
+
+// Grouping key for tranches created before currencies were recorded on tranches
+const UNSPECIFIED_CURRENCY: &str = "unspecified";
+
+fn parse_window(from: &str, to: &str) -> std::result::Result<(u64, u64), String> {
+    let from_nanos = from.parse::<u64>().map_err(|_| "Invalid window start format")?;
+    let to_nanos = to.parse::<u64>().map_err(|_| "Invalid window end format")?;
+
+    if to_nanos <= from_nanos {
+        return Err("Window end must be after its start".into());
+    }
+    if to_nanos > calendar::add_months(from_nanos, MAX_WINDOW_MONTHS) {
+        return Err(format!("Window cannot span more than {} months", MAX_WINDOW_MONTHS));
+    }
+
+    Ok((from_nanos, to_nanos))
+}
+
+/// Principal falling due in [from, to) from one waqf: maturing tranches, and the unpaid
+/// installments of tranches being returned in installments
+fn waqf_obligations(
+    waqf: &WaqfData,
+    owner_filter: Option<&str>,
+    from: u64,
+    to: u64,
+) -> Vec<(String, MaturityCalendarItem)> {
+    let details = match waqf.revolving_details.as_ref() {
+        Some(details) => details,
+        None => return Vec::new(),
+    };
+    let in_window = |date: &str| date.parse::<u64>().is_ok_and(|date| date >= from && date < to);
+    let mut items = Vec::new();
+
+    for tranche in details.contribution_tranches.iter().flatten() {
+        let owner = tranche_owner(waqf, tranche);
+        if owner_filter.is_some_and(|caller| caller != owner) {
+            continue;
+        }
+        let currency = tranche.currency.clone().unwrap_or_else(|| UNSPECIFIED_CURRENCY.to_string());
+        let item = |installment_id: Option<String>, amount: f64, due_date: &str, status: String| MaturityCalendarItem {
+            kind: if installment_id.is_some() { "installment" } else { "tranche" }.to_string(),
+            waqf_id: waqf.id.clone(),
+            waqf_name: waqf.name.clone(),
+            owner: owner.clone(),
+            tranche_id: tranche.id.clone(),
+            installment_id,
+            amount,
+            due_date: due_date.to_string(),
+            status,
+            expiration_action: None,
+            cause_id: tranche.cause_id.clone(),
+        };
+
+        if tranche.status == Some(TrancheStatus::ReturnScheduled) {
+            for installment in tranche.installment_payments.iter().flatten() {
+                if installment.status != InstallmentStatus::Paid && in_window(&installment.due_date) {
+                    items.push((
+                        currency.clone(),
+                        item(Some(installment.id.clone()), installment.amount, &installment.due_date, installment.status.to_string()),
+                    ));
+                }
+            }
+            continue;
+        }
+
+        if is_settled(tranche) || !in_window(&tranche.maturity_date) {
+            continue;
+        }
+
+        let mut maturing = item(
+            None,
+            tranche.amount,
+            &tranche.maturity_date,
+            tranche.status.unwrap_or(TrancheStatus::Locked).to_string(),
+        );
+        maturing.expiration_action = Some(effective_expiration_preference(tranche, details).action);
+        items.push((currency, maturing));
+    }
+
+    items
+}
+
+/// Tranche principal and installments falling due between `from` and `to` (nanosecond
+/// timestamps), grouped by calendar month and currency. Finance officers and platform
+/// admins see every waqf; donors see the principal they own.
+pub fn get_maturity_calendar(caller: &str, from: &str, to: &str) -> std::result::Result<MaturityCalendar, String> {
+    let (from_nanos, to_nanos) = parse_window(from, to)?;
+    let sees_all = caller_has_role(caller, &[AdminRole::FinanceOfficer, AdminRole::PlatformAdmin]);
+    let owner_filter = if sees_all { None } else { Some(caller) };
+
+    let mut grouped: BTreeMap<(String, String), MaturityCalendarMonth> = BTreeMap::new();
+    let waqfs = list_docs("waqfs".to_string(), ListParams::default());
+
+    for (key, doc) in waqfs.items {
+        let waqf: WaqfData = match decode_doc_data(&doc.data) {
+            Ok(waqf) => waqf,
+            Err(e) => {
+                ic_cdk::println!("WARN - Skipping waqf {} in maturity calendar: {}", key, e);
+                continue;
+            }
+        };
+
+        for (currency, item) in waqf_obligations(&waqf, owner_filter, from_nanos, to_nanos) {
+            let month = calendar::month_key(item.due_date.parse::<u64>().unwrap_or(from_nanos));
+            let entry = grouped
+                .entry((month.clone(), currency.clone()))
+                .or_insert_with(|| MaturityCalendarMonth {
+                    month,
+                    currency,
+                    maturing_principal: 0.0,
+                    installments_due: 0.0,
+                    total: 0.0,
+                    items: Vec::new(),
+                });
+
+            if item.installment_id.is_some() {
+                entry.installments_due += item.amount;
+            } else {
+                entry.maturing_principal += item.amount;
+            }
+            entry.total += item.amount;
+            entry.items.push(item);
+        }
+    }
+
+    let months = grouped
+        .into_values()
+        .map(|mut month| {
+            month.items.sort_by_key(|item| item.due_date.parse::<u64>().unwrap_or(0));
+            month
+        })
+        .collect();
+
+    Ok(MaturityCalendar {
+        from: from.to_string(),
+        to: to.to_string(),
+        months,
+    })
+}
@@ -0,0 +1,372 @@
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::notification_types::{
+    DeliveryChannel, EmailStatus, NotificationData, NotificationPayload, NotificationPriority, NotificationType,
+};
+use crate::waqf_types::{ContributionTranche, DeliveryMethod, NotificationPreferences, WaqfData};
+use junobuild_satellite::{
+    del_doc, get_doc, list_docs, set_doc, AssertDeleteDocContext, AssertSetDocContext, DelDoc, Doc, SetDoc,
+};
+use junobuild_shared::types::list::{ListMatcher, ListPaginate, ListParams, TimestampMatcher};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Delivery state is kept in the document description so the scheduled jobs can list only
+// the notifications they act on instead of decoding the whole collection
+const PENDING_DELIVERY: &str = "pending";
+const DELIVERED: &str = "delivered";
+
+// Notifications handled per timer callback
+const DELIVERY_PAGE_SIZE: usize = 100;
+
+// Delivered notifications are removed once they have not changed for this long
+const NOTIFICATION_RETENTION_NANOS: u64 = 90 * 24 * 60 * 60 * 1_000_000_000;
+
+thread_local! {
+    // Distinguishes notifications created within the same call (they share a timestamp)
+    static SEQUENCE: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A notification to send once the change it describes has been saved
+#[derive(Debug, Clone)]
+pub struct Notice {
+    pub recipient: String,
+    pub waqf_id: String,
+    pub notification_type: NotificationType,
+    pub payload: NotificationPayload,
+}
+
+impl Notice {
+    pub fn new(
+        recipient: &str,
+        waqf_id: &str,
+        notification_type: NotificationType,
+        payload: NotificationPayload,
+    ) -> Self {
+        Notice {
+            recipient: recipient.to_string(),
+            waqf_id: waqf_id.to_string(),
+            notification_type,
+            payload,
+        }
+    }
+}
+
+/// Donor who owns a tranche's principal: the recorded owner for cause pool tranches,
+/// otherwise the donor of the waqf
+pub fn tranche_owner(waqf: &WaqfData, tranche: &ContributionTranche) -> String {
+    tranche.pool_origin.as_ref()
+        .map(|origin| origin.owner.clone())
+        .unwrap_or_else(|| waqf.created_by.clone())
+}
+
+fn default_priority(notification_type: NotificationType) -> NotificationPriority {
+    match notification_type {
+        // The donor has to act (change a preference) or something went wrong
+        NotificationType::TrancheMatured
+        | NotificationType::ExpirationFailed
+        | NotificationType::InstallmentMissed
+        | NotificationType::WaqfTerminated => NotificationPriority::High,
+        NotificationType::CausePoolCredited => NotificationPriority::Low,
+        _ => NotificationPriority::Normal,
+    }
+}
+
+/// Write a notification to the notifications collection
+pub fn send(notice: Notice) {
+    let now = ic_cdk::api::time();
+    let sequence = SEQUENCE.with(|seq| {
+        let next = seq.get().wrapping_add(1);
+        seq.set(next);
+        next
+    });
+
+    let notification = NotificationData {
+        id: format!("notif_{}_{}", now, sequence),
+        recipient: notice.recipient,
+        waqf_id: notice.waqf_id,
+        priority: default_priority(notice.notification_type),
+        notification_type: notice.notification_type,
+        payload: notice.payload,
+        created_at: now.to_string(),
+        read_at: None,
+        delivered_at: None,
+        channels: Vec::new(),
+        email_status: None,
+    };
+
+    match encode_doc_data(&notification) {
+        Ok(data) => {
+            let set_doc_data = SetDoc {
+                data,
+                description: Some(PENDING_DELIVERY.to_string()),
+                version: None,
+            };
+            let _ = set_doc("notifications".to_string(), notification.id.clone(), set_doc_data);
+        }
+        Err(e) => ic_cdk::println!("ERROR - Cannot encode notification {}: {}", notification.id, e),
+    }
+}
+
+pub fn send_all(notices: impl IntoIterator<Item = Notice>) {
+    for notice in notices {
+        send(notice);
+    }
+}
+
+// ============================================
+// ASSERTIONS
+// ============================================
+
+pub fn assert_notification_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let caller = context.caller.to_text();
+    let proposed: NotificationData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid notification data structure: {}", e))?;
+
+    // Hooks and the delivery job write as the satellite
+    if is_satellite_caller(&caller) {
+        return Ok(());
+    }
+
+    let current_doc = context.data.data.current.as_ref()
+        .ok_or("Notifications are created by the satellite only")?;
+    let current: NotificationData = decode_doc_data(&current_doc.data)
+        .map_err(|e| format!("Cannot decode current notification: {}", e))?;
+
+    if context.data.data.proposed.description != current_doc.description {
+        return Err("The delivery state of a notification is managed by the satellite".into());
+    }
+
+    if caller == current.recipient {
+        // The donor can only mark the notification as read
+        let mut expected = current.clone();
+        expected.read_at = proposed.read_at.clone();
+        if expected != proposed {
+            return Err("Donors can only mark notifications as read".into());
+        }
+        if current.read_at.is_some() && current.read_at != proposed.read_at {
+            return Err("Notification has already been marked as read".into());
+        }
+        if let Some(read_at) = &proposed.read_at {
+            read_at.parse::<u64>().map_err(|_| "Invalid read at format")?;
+        }
+        return Ok(());
+    }
+
+    if caller_has_role(&caller, &[AdminRole::SupportAgent, AdminRole::PlatformAdmin]) {
+        // Staff running the mailer record the outcome of email delivery
+        let mut expected = current.clone();
+        expected.email_status = proposed.email_status;
+        if expected != proposed {
+            return Err("Staff can only update the email delivery status of a notification".into());
+        }
+        if current.email_status != Some(EmailStatus::Pending) {
+            return Err("Notification has no pending email delivery".into());
+        }
+        match proposed.email_status {
+            Some(EmailStatus::Sent) | Some(EmailStatus::Failed) => return Ok(()),
+            _ => return Err("Email status must be 'sent' or 'failed'".into()),
+        }
+    }
+
+    Err("Only the recipient can update this notification".into())
+}
+
+pub fn assert_notification_deletion(context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    let caller = context.caller.to_text();
+    let current_doc = context.data.data.current.as_ref()
+        .ok_or("No current notification found for deletion")?;
+    let current: NotificationData = decode_doc_data(&current_doc.data)
+        .map_err(|e| format!("Cannot decode notification for deletion: {}", e))?;
+
+    if is_satellite_caller(&caller) {
+        return Ok(());
+    }
+
+    if caller != current.recipient {
+        return Err("Only the recipient can delete a notification".into());
+    }
+
+    if current.read_at.is_none() {
+        return Err("Mark the notification as read before deleting it".into());
+    }
+
+    Ok(())
+}
+
+// ============================================
+// DELIVERY
+// ============================================
+
+/// Whether the donor's notification preferences cover this type of notification
+fn category_enabled(preferences: &NotificationPreferences, notification_type: NotificationType) -> bool {
+    match notification_type {
+        NotificationType::InstallmentDue | NotificationType::InstallmentMissed => {
+            preferences.financial_updates || preferences.contribution_reminders
+        }
+        NotificationType::ReportReady => preferences.impact_reports || preferences.financial_updates,
+        _ => preferences.financial_updates,
+    }
+}
+
+/// Delivery channels from the waqf's reporting delivery method. Opted-out categories stay
+/// on the platform only, unless the notification needs the donor's attention.
+fn delivery_channels(notification: &NotificationData, waqf: Option<&WaqfData>) -> Vec<DeliveryChannel> {
+    let waqf = match waqf {
+        Some(waqf) => waqf,
+        None => return vec![DeliveryChannel::Platform],
+    };
+
+    let opted_in = category_enabled(&waqf.notifications, notification.notification_type)
+        || notification.priority == NotificationPriority::High;
+    if !opted_in {
+        return vec![DeliveryChannel::Platform];
+    }
+
+    match waqf.reporting_preferences.delivery_method {
+        DeliveryMethod::Email => vec![DeliveryChannel::Email],
+        DeliveryMethod::Both => vec![DeliveryChannel::Platform, DeliveryChannel::Email],
+        DeliveryMethod::Platform => vec![DeliveryChannel::Platform],
+    }
+}
+
+fn list_notifications(state: &str, updated_before: Option<u64>, start_after: Option<String>) -> Vec<(String, Doc)> {
+    let params = ListParams {
+        matcher: Some(ListMatcher {
+            description: Some(format!("^{}$", state)),
+            updated_at: updated_before.map(TimestampMatcher::LessThan),
+            ..Default::default()
+        }),
+        paginate: Some(ListPaginate { start_after, limit: Some(DELIVERY_PAGE_SIZE) }),
+        ..Default::default()
+    };
+    list_docs("notifications".to_string(), params).items
+}
+
+/// Scheduled job: resolve delivery channels for new notifications. Platform delivery is the
+/// notification document itself; email deliveries are left pending for the mailer.
+pub fn deliver_notifications() {
+    deliver_page(None);
+}
+
+fn deliver_page(start_after: Option<String>) {
+    let now = ic_cdk::api::time();
+    let notifications = list_notifications(PENDING_DELIVERY, None, start_after);
+    let last_key = notifications.last().map(|(key, _)| key.clone());
+    let full_page = notifications.len() == DELIVERY_PAGE_SIZE;
+    let mut waqfs: HashMap<String, Option<WaqfData>> = HashMap::new();
+
+    for (key, doc) in notifications {
+        let mut notification: NotificationData = match decode_doc_data(&doc.data) {
+            Ok(notification) => notification,
+            Err(e) => {
+                ic_cdk::println!("WARN - Skipping notification {} in delivery: {}", key, e);
+                continue;
+            }
+        };
+
+        if notification.delivered_at.is_none() {
+            let waqf = waqfs
+                .entry(notification.waqf_id.clone())
+                .or_insert_with(|| {
+                    get_doc("waqfs".to_string(), notification.waqf_id.clone())
+                        .and_then(|doc| decode_doc_data::<WaqfData>(&doc.data).ok())
+                })
+                .as_ref();
+
+            notification.channels = delivery_channels(&notification, waqf);
+            if notification.channels.contains(&DeliveryChannel::Email) {
+                notification.email_status = Some(EmailStatus::Pending);
+            }
+            notification.delivered_at = Some(now.to_string());
+        }
+
+        match encode_doc_data(&notification) {
+            Ok(data) => {
+                let set_doc_data = SetDoc {
+                    data,
+                    description: Some(DELIVERED.to_string()),
+                    version: doc.version,
+                };
+                let _ = set_doc("notifications".to_string(), key.clone(), set_doc_data);
+            }
+            Err(e) => ic_cdk::println!("ERROR - Cannot encode notification {}: {}", key, e),
+        }
+    }
+
+    if full_page {
+        ic_cdk_timers::set_timer(Duration::ZERO, move || deliver_page(last_key));
+    }
+}
+
+/// Scheduled job: remove delivered notifications past the retention period. Notifications
+/// still waiting for the mailer are kept until the email outcome is recorded.
+pub fn prune_notifications() {
+    let cutoff = ic_cdk::api::time().saturating_sub(NOTIFICATION_RETENTION_NANOS);
+    prune_page(cutoff, None);
+}
+
+fn prune_page(cutoff: u64, start_after: Option<String>) {
+    let notifications = list_notifications(DELIVERED, Some(cutoff), start_after);
+    let last_key = notifications.last().map(|(key, _)| key.clone());
+    let full_page = notifications.len() == DELIVERY_PAGE_SIZE;
+    let mut pruned = 0;
+
+    for (key, doc) in notifications {
+        let email_pending = decode_doc_data::<NotificationData>(&doc.data)
+            .map(|notification| notification.email_status == Some(EmailStatus::Pending))
+            .unwrap_or(false);
+        if email_pending {
+            continue;
+        }
+
+        del_doc("notifications".to_string(), key, DelDoc { version: doc.version });
+        pruned += 1;
+    }
+
+    if pruned > 0 {
+        ic_cdk::println!("Pruned {} delivered notifications", pruned);
+    }
+
+    if full_page {
+        ic_cdk_timers::set_timer(Duration::ZERO, move || prune_page(cutoff, last_key));
+    }
+}
+
+/// Tag notifications written before the delivery state was kept in the description, one page
+/// per timer callback. Runs after upgrades; documents that already carry a state are left as they are.
+pub fn tag_untracked_notifications() {
+    ic_cdk_timers::set_timer(Duration::ZERO, || tag_page(None));
+}
+
+fn tag_page(start_after: Option<String>) {
+    let params = ListParams {
+        paginate: Some(ListPaginate { start_after, limit: Some(DELIVERY_PAGE_SIZE) }),
+        ..Default::default()
+    };
+    let notifications = list_docs("notifications".to_string(), params).items;
+    let last_key = notifications.last().map(|(key, _)| key.clone());
+    let full_page = notifications.len() == DELIVERY_PAGE_SIZE;
+
+    for (key, doc) in notifications {
+        if doc.description.is_some() {
+            continue;
+        }
+        let delivered = decode_doc_data::<NotificationData>(&doc.data)
+            .map(|notification| notification.delivered_at.is_some())
+            .unwrap_or(false);
+        let state = if delivered { DELIVERED } else { PENDING_DELIVERY };
+
+        let set_doc_data = SetDoc {
+            data: doc.data,
+            description: Some(state.to_string()),
+            version: doc.version,
+        };
+        let _ = set_doc("notifications".to_string(), key, set_doc_data);
+    }
+
+    if full_page {
+        ic_cdk_timers::set_timer(Duration::ZERO, move || tag_page(last_key));
+    }
+}
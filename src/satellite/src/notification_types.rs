@@ -0,0 +1,80 @@
+use crate::waqf_types::ConversionTarget;
+use serde::{Deserialize, Serialize};
+use candid::CandidType;
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationType {
+    TrancheMatured,       // Grace period started - the donor can still change the expiration preference
+    RolloverDone,
+    RefundScheduled,      // Principal will be returned in installments
+    TrancheReturned,
+    EarlyWithdrawal,
+    TrancheConverted,
+    InstallmentDue,
+    InstallmentMissed,
+    InstallmentPaid,
+    PenaltyWaived,
+    CausePoolCredited,
+    ExpirationFailed,
+    WaqfTerminated,       // Tranches settled and the remaining balance passed to the successor
+    ReportReady,          // Scheduled waqf report generated (reference is the report id)
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationPriority {
+    Low,
+    Normal,
+    High,
+}
+
+/// Structured details of a notification so clients can render localised text.
+/// Which fields are set depends on the notification type.
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NotificationPayload {
+    pub tranche_id: Option<String>,
+    pub amount: Option<f64>,
+    pub penalty: Option<f64>,
+    pub months: Option<u32>,
+    pub date: Option<String>,               // Nanosecond timestamp (due date, maturity date, ...)
+    pub installment_id: Option<String>,
+    pub new_tranche_id: Option<String>,     // Tranche created by a rollover, or the portion left locked by a partial withdrawal
+    pub related_waqf_id: Option<String>,    // Cause pool or converted waqf
+    pub target_waqf_type: Option<ConversionTarget>, // Type of the converted waqf
+    pub cause_id: Option<String>,
+    pub reference: Option<String>,          // Payout reference or waiver id
+    pub reason: Option<String>,             // Failure reason, or a note such as "cause_pool_unavailable"
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NotificationData {
+    pub id: String,
+    pub recipient: String,                  // Principal of the donor the notification is for
+    pub waqf_id: String,
+    pub notification_type: NotificationType,
+    pub priority: NotificationPriority,
+    pub payload: NotificationPayload,
+    pub created_at: String,                 // Nanosecond timestamps
+    pub read_at: Option<String>,
+    pub delivered_at: Option<String>,
+    #[serde(default)]
+    pub channels: Vec<DeliveryChannel>,     // Resolved from the donor's preferences at delivery
+    #[serde(default)]
+    pub email_status: Option<EmailStatus>,  // Set when delivered by email
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryChannel {
+    Platform,
+    Email,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailStatus {
+    Pending,              // Waiting for the mailer
+    Sent,
+    Failed,
+}
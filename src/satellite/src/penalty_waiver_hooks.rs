@@ -0,0 +1,136 @@
+use crate::admin_hooks::{caller_has_role, AdminRole};
+use crate::notification_hooks::{self, Notice};
+use crate::notification_types::{NotificationPayload, NotificationType};
+use crate::waqf_types::{ContributionTranche, PenaltyWaiver, PenaltyWaiverData, TrancheStatus, WaqfData};
+use junobuild_satellite::{get_doc, set_doc, AssertDeleteDocContext, AssertSetDocContext, OnSetDocContext, SetDoc};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+
+// Hardship waivers must be documented so they can be audited later
+const MIN_WAIVER_REASON_LENGTH: usize = 20;
+const MAX_WAIVER_REASON_LENGTH: usize = 2000;
+
+fn find_tranche_mut<'a>(waqf: &'a mut WaqfData, tranche_id: &str) -> std::result::Result<&'a mut ContributionTranche, String> {
+    waqf.revolving_details
+        .as_mut()
+        .and_then(|details| details.contribution_tranches.as_mut())
+        .and_then(|tranches| tranches.iter_mut().find(|t| t.id == tranche_id))
+        .ok_or_else(|| format!("Tranche {} not found", tranche_id))
+}
+
+pub fn assert_penalty_waiver_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let waiver: PenaltyWaiverData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid penalty waiver data structure: {}", e))?;
+
+    if context.data.data.current.is_some() {
+        return Err("Penalty waivers are immutable once granted".into());
+    }
+
+    let caller = context.caller.to_text();
+    if !caller_has_role(&caller, &[AdminRole::ComplianceOfficer]) {
+        return Err("Only compliance officers can grant hardship waivers".into());
+    }
+
+    if waiver.granted_by != caller {
+        return Err("Granted by must match the calling compliance officer".into());
+    }
+
+    if waiver.id != context.data.key {
+        return Err("Waiver id must match the document key".into());
+    }
+
+    let reason_length = waiver.reason.trim().len();
+    if reason_length < MIN_WAIVER_REASON_LENGTH {
+        return Err(format!(
+            "Waiver reason must document the hardship (at least {} characters)",
+            MIN_WAIVER_REASON_LENGTH
+        ));
+    }
+    if reason_length > MAX_WAIVER_REASON_LENGTH {
+        return Err(format!("Waiver reason cannot exceed {} characters", MAX_WAIVER_REASON_LENGTH));
+    }
+
+    waiver.granted_at.parse::<u64>()
+        .map_err(|_| "Invalid granted at format")?;
+
+    let doc = get_doc("waqfs".to_string(), waiver.waqf_id.clone())
+        .ok_or_else(|| format!("Waqf not found: {}", waiver.waqf_id))?;
+    let mut waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+
+    let tranche = find_tranche_mut(&mut waqf, &waiver.tranche_id)?;
+    if tranche.is_returned || tranche.penalty_applied.is_some() {
+        return Err(format!("Tranche {} has already been returned", tranche.id));
+    }
+    if tranche.status == Some(TrancheStatus::Split) {
+        return Err(format!("Tranche {} was split by a partial withdrawal - waive the remaining portion instead", tranche.id));
+    }
+    if tranche.penalty_waiver.is_some() {
+        return Err(format!("Tranche {} already has a hardship waiver", tranche.id));
+    }
+
+    ic_cdk::println!(
+        "Penalty waiver validation passed: {} - Waqf: {}, Tranche: {}, Granted by: {}",
+        waiver.id, waiver.waqf_id, waiver.tranche_id, waiver.granted_by
+    );
+
+    Ok(())
+}
+
+pub fn assert_penalty_waiver_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Penalty waivers cannot be deleted for audit purposes".into())
+}
+
+pub fn handle_penalty_waiver_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let waiver: PenaltyWaiverData = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode penalty waiver data: {}", e))?;
+
+    ic_cdk::println!(
+        "Penalty waiver CREATE: {} - Waqf: {}, Tranche: {}, Granted by: {}",
+        waiver.id, waiver.waqf_id, waiver.tranche_id, waiver.granted_by
+    );
+
+    if context.data.data.before.is_some() {
+        return Ok(());
+    }
+
+    let doc = get_doc("waqfs".to_string(), waiver.waqf_id.clone())
+        .ok_or_else(|| format!("Waqf not found: {}", waiver.waqf_id))?;
+    let mut waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+
+    let tranche = find_tranche_mut(&mut waqf, &waiver.tranche_id)?;
+    let recipient = tranche.pool_origin.as_ref().map(|origin| origin.owner.clone());
+    tranche.penalty_waiver = Some(PenaltyWaiver {
+        waiver_id: waiver.id.clone(),
+        reason: waiver.reason.clone(),
+        granted_by: waiver.granted_by.clone(),
+        granted_at: waiver.granted_at.clone(),
+    });
+    let recipient = recipient.unwrap_or_else(|| waqf.created_by.clone());
+    waqf.updated_at = Some(ic_cdk::api::time().to_string());
+
+    let updated_data = encode_doc_data(&waqf)
+        .map_err(|e| format!("Failed to encode waqf data: {}", e))?;
+
+    let set_doc_data = SetDoc {
+        data: updated_data,
+        description: doc.description,
+        version: doc.version,
+    };
+
+    let _ = set_doc("waqfs".to_string(), waiver.waqf_id.clone(), set_doc_data);
+
+    // No early withdrawal penalty will be charged on this tranche
+    notification_hooks::send(Notice::new(
+        &recipient,
+        &waiver.waqf_id,
+        NotificationType::PenaltyWaived,
+        NotificationPayload {
+            tranche_id: Some(waiver.tranche_id.clone()),
+            reference: Some(waiver.id.clone()),
+            ..Default::default()
+        },
+    ));
+
+    Ok(())
+}
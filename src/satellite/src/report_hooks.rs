@@ -0,0 +1,308 @@
+use crate::admin_hooks::is_satellite_caller;
+use crate::calendar;
+use crate::impact_event_types::{ImpactEvent, VerificationStatus};
+use crate::notification_hooks::{self, Notice};
+use crate::notification_types::{NotificationPayload, NotificationType};
+use crate::scheduler;
+use crate::report_types::{ReportCauseImpact, ReportFinancialSection, ReportImpactSection, ReportTotals, WaqfReportData};
+use crate::waqf_types::{AllocationData, DonationData, DonationStatus, InvestmentReturnData, ReportType, WaqfData, WaqfStatus};
+use junobuild_satellite::{list_docs, set_doc, AssertDeleteDocContext, AssertSetDocContext, Doc, SetDoc};
+use junobuild_shared::types::list::{ListMatcher, ListParams, TimestampMatcher};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// ============================================
+// ASSERTIONS
+// ============================================
+
+pub fn assert_report_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    if !is_satellite_caller(&context.caller.to_text()) {
+        return Err("Reports are generated by the satellite only".into());
+    }
+
+    let report: WaqfReportData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid report data structure: {}", e))?;
+    if report.id != context.data.key {
+        return Err("Report id must match the document key".into());
+    }
+    if context.data.data.current.is_some() {
+        return Err(format!("Report {} has already been generated", report.id));
+    }
+
+    Ok(())
+}
+
+pub fn assert_report_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Reports cannot be deleted for audit purposes".into())
+}
+
+// ============================================
+// GENERATION
+// ============================================
+
+/// Documents a report is built from, grouped by waqf with their creation time. Only the
+/// documents of the waqfs being reported on, created within the reporting window, are loaded
+struct ReportSources {
+    donations: HashMap<String, Vec<(u64, DonationData)>>,
+    allocations: HashMap<String, Vec<(u64, AllocationData)>>,
+    returns: HashMap<String, Vec<(u64, InvestmentReturnData)>>,
+    impact_events: HashMap<String, Vec<(u64, ImpactEvent)>>,
+}
+
+/// Source document keys are random, so the store cannot be queried per waqf; the creation time
+/// filter keeps the listing to the reporting window and the waqf filter is applied on decode
+fn docs_by_waqf<T: DeserializeOwned>(
+    collection: &str,
+    waqf_id: fn(&T) -> &str,
+    waqf_ids: &HashSet<&str>,
+    window: (u64, u64),
+) -> HashMap<String, Vec<(u64, T)>> {
+    let params = ListParams {
+        matcher: Some(ListMatcher {
+            created_at: Some(TimestampMatcher::Between(window.0, window.1)),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let mut grouped: HashMap<String, Vec<(u64, T)>> = HashMap::new();
+    for (key, doc) in list_docs(collection.to_string(), params).items {
+        match decode_doc_data::<T>(&doc.data) {
+            Ok(item) if waqf_ids.contains(waqf_id(&item)) => {
+                grouped.entry(waqf_id(&item).to_string()).or_default().push((doc.created_at, item))
+            }
+            Ok(_) => {}
+            Err(e) => ic_cdk::println!("WARN - Skipping {} {} in report generation: {}", collection, key, e),
+        }
+    }
+    grouped
+}
+
+impl ReportSources {
+    fn load(waqf_ids: &HashSet<&str>, window: (u64, u64)) -> Self {
+        ReportSources {
+            donations: docs_by_waqf("donations", |d: &DonationData| &d.waqf_id, waqf_ids, window),
+            allocations: docs_by_waqf("allocations", |a: &AllocationData| &a.waqf_id, waqf_ids, window),
+            returns: docs_by_waqf("investment_returns", |r: &InvestmentReturnData| &r.waqf_id, waqf_ids, window),
+            impact_events: docs_by_waqf("impact_events", |e: &ImpactEvent| &e.waqf_id, waqf_ids, window),
+        }
+    }
+}
+
+/// Items of the waqf recorded within [start, end)
+fn in_period<'a, T>(
+    grouped: &'a HashMap<String, Vec<(u64, T)>>,
+    waqf_id: &str,
+    start: u64,
+    end: u64,
+) -> impl Iterator<Item = &'a T> {
+    grouped
+        .get(waqf_id)
+        .into_iter()
+        .flatten()
+        .filter(move |(created_at, _)| (start..end).contains(created_at))
+        .map(|(_, item)| item)
+}
+
+fn totals(amounts: impl Iterator<Item = f64>) -> ReportTotals {
+    amounts.fold(ReportTotals::default(), |totals, amount| ReportTotals {
+        count: totals.count + 1,
+        total: totals.total + amount,
+    })
+}
+
+fn financial_section(waqf: &WaqfData, sources: &ReportSources, start: u64, end: u64) -> ReportFinancialSection {
+    let opening_balance = waqf.financial.last_reported_balance.unwrap_or(0.0);
+    let closing_balance = waqf.financial.current_balance;
+
+    ReportFinancialSection {
+        donations: totals(
+            in_period(&sources.donations, &waqf.id, start, end)
+                .filter(|donation| donation.status == DonationStatus::Completed)
+                .map(|donation| donation.amount),
+        ),
+        distributions: totals(in_period(&sources.allocations, &waqf.id, start, end).map(|allocation| allocation.amount)),
+        returns: totals(in_period(&sources.returns, &waqf.id, start, end).map(|record| record.amount)),
+        opening_balance,
+        closing_balance,
+        balance_change: closing_balance - opening_balance,
+    }
+}
+
+fn impact_section(waqf: &WaqfData, sources: &ReportSources, start: u64, end: u64) -> ReportImpactSection {
+    let mut section = ReportImpactSection::default();
+    let mut by_cause: BTreeMap<String, ReportCauseImpact> = BTreeMap::new();
+
+    let verified = in_period(&sources.impact_events, &waqf.id, start, end)
+        .filter(|event| matches!(event.verification.status, VerificationStatus::Verified));
+    for event in verified {
+        section.verified_events += 1;
+        section.beneficiaries += event.beneficiary_count as u64;
+        section.projects_completed += event.projects_completed.unwrap_or(0);
+        section.amount += event.amount;
+        section.event_ids.push(event.id.clone());
+
+        let cause = by_cause.entry(event.cause_id.clone()).or_insert_with(|| ReportCauseImpact {
+            cause_id: event.cause_id.clone(),
+            cause_name: event.cause_name.clone(),
+            events: 0,
+            beneficiaries: 0,
+            amount: 0.0,
+        });
+        cause.events += 1;
+        cause.beneficiaries += event.beneficiary_count as u64;
+        cause.amount += event.amount;
+    }
+
+    section.by_cause = by_cause.into_values().collect();
+    section
+}
+
+fn build_report(waqf: &WaqfData, sources: &ReportSources, start: u64, end: u64, now: u64) -> Option<WaqfReportData> {
+    let preferences = &waqf.reporting_preferences;
+    let wants = |report_type: ReportType| preferences.report_types.contains(&report_type);
+
+    let financial = wants(ReportType::Financial).then(|| financial_section(waqf, sources, start, end));
+    let impact = wants(ReportType::Impact).then(|| impact_section(waqf, sources, start, end));
+    if financial.is_none() && impact.is_none() {
+        return None;
+    }
+
+    Some(WaqfReportData {
+        id: format!("report_{}_{}", waqf.id, end),
+        waqf_id: waqf.id.clone(),
+        recipient: waqf.created_by.clone(),
+        frequency: preferences.frequency,
+        period_start: start.to_string(),
+        period_end: end.to_string(),
+        generated_at: now.to_string(),
+        financial,
+        impact,
+        delivery_method: preferences.delivery_method,
+    })
+}
+
+fn save_waqf(key: &str, doc: &Doc, waqf: &WaqfData) {
+    match encode_doc_data(waqf) {
+        Ok(data) => {
+            let set_doc_data = SetDoc {
+                data,
+                description: doc.description.clone(),
+                version: doc.version,
+            };
+            let _ = set_doc("waqfs".to_string(), key.to_string(), set_doc_data);
+        }
+        Err(e) => ic_cdk::println!("ERROR - Cannot encode waqf {}: {}", key, e),
+    }
+}
+
+/// Start of the next report period: the end of the last report, or the waqf's creation
+fn period_start(waqf: &WaqfData, created_at: u64) -> u64 {
+    waqf.financial.last_report_period_end.as_deref()
+        .and_then(|end| end.parse::<u64>().ok())
+        .unwrap_or(created_at)
+}
+
+/// Scheduled job: generate reports for waqfs whose next report date has passed, then move the
+/// date on by the donor's reporting frequency and notify the donor
+pub fn generate_due_reports() {
+    scheduler::sweep_waqfs(generate_reports_page);
+}
+
+fn generate_reports_page(waqfs: Vec<(String, Doc)>) {
+    let now = ic_cdk::api::time();
+    let mut due = Vec::new();
+
+    for (key, doc) in waqfs {
+        let waqf: WaqfData = match decode_doc_data(&doc.data) {
+            Ok(waqf) => waqf,
+            Err(e) => {
+                ic_cdk::println!("WARN - Skipping waqf {} in report generation: {}", key, e);
+                continue;
+            }
+        };
+
+        if !matches!(waqf.status, WaqfStatus::Active | WaqfStatus::Paused) {
+            continue;
+        }
+
+        // Waqfs created before report scheduling (or with an invalid date) are due one period
+        // after the last report; the date is only written once that report is generated
+        let months = waqf.reporting_preferences.frequency.months();
+        let report_date = waqf.next_report_date.as_deref()
+            .and_then(|date| date.parse::<u64>().ok())
+            .unwrap_or_else(|| calendar::add_months(period_start(&waqf, doc.created_at), months));
+
+        if report_date <= now {
+            let start = period_start(&waqf, doc.created_at).min(report_date);
+            due.push((key, doc, waqf, start, report_date));
+        }
+    }
+
+    if due.is_empty() {
+        return;
+    }
+
+    let waqf_ids: HashSet<&str> = due.iter().map(|(_, _, waqf, _, _)| waqf.id.as_str()).collect();
+    let window_start = due.iter().map(|(_, _, _, start, _)| *start).min().unwrap_or(0);
+    let window_end = due.iter().map(|(_, _, _, _, end)| *end).max().unwrap_or(now);
+    let sources = ReportSources::load(&waqf_ids, (window_start, window_end));
+
+    for (key, doc, mut waqf, start, report_date) in due {
+        let report = build_report(&waqf, &sources, start, report_date, now);
+
+        if let Some(report) = &report {
+            match encode_doc_data(report) {
+                Ok(data) => {
+                    let set_doc_data = SetDoc {
+                        data,
+                        description: None,
+                        version: None,
+                    };
+                    let _ = set_doc("reports".to_string(), report.id.clone(), set_doc_data);
+                }
+                Err(e) => {
+                    ic_cdk::println!("ERROR - Cannot encode report {}: {}", report.id, e);
+                    continue;
+                }
+            }
+        }
+
+        // Skip any periods missed while the satellite was idle; count from the original date
+        // so month-end clamping does not drift the schedule
+        let months = waqf.reporting_preferences.frequency.months();
+        let mut periods = 1;
+        let mut next = calendar::add_months(report_date, months);
+        while next <= now {
+            periods += 1;
+            next = calendar::add_months(report_date, months * periods);
+        }
+
+        waqf.financial.last_report_period_end = Some(report_date.to_string());
+        waqf.financial.last_reported_balance = Some(waqf.financial.current_balance);
+        waqf.next_report_date = Some(next.to_string());
+        waqf.updated_at = Some(now.to_string());
+        save_waqf(&key, &doc, &waqf);
+
+        match report {
+            Some(report) => {
+                notification_hooks::send(Notice::new(
+                    &report.recipient,
+                    &report.waqf_id,
+                    NotificationType::ReportReady,
+                    NotificationPayload {
+                        reference: Some(report.id.clone()),
+                        date: Some(report.period_end.clone()),
+                        ..Default::default()
+                    },
+                ));
+                ic_cdk::println!(
+                    "Generated {} report {} for waqf {} ({} to {}), next report at {}",
+                    report.frequency, report.id, key, report.period_start, report.period_end, next
+                );
+            }
+            None => ic_cdk::println!("Waqf {} has no report types selected - skipped report, next at {}", key, next),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::waqf_types::{DeliveryMethod, ReportingFrequency};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// Count and total of the documents of one kind recorded during the period
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ReportTotals {
+    pub count: u32,
+    pub total: f64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ReportFinancialSection {
+    pub donations: ReportTotals,      // Completed donations
+    pub distributions: ReportTotals,  // Allocations to causes
+    pub returns: ReportTotals,        // Investment returns recorded (negative totals are losses)
+    pub opening_balance: f64,         // Balance at the previous report, zero before the first one
+    pub closing_balance: f64,
+    pub balance_change: f64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReportCauseImpact {
+    pub cause_id: String,
+    pub cause_name: String,
+    pub events: u32,
+    pub beneficiaries: u64,
+    pub amount: f64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ReportImpactSection {
+    pub verified_events: u32,
+    pub beneficiaries: u64,
+    pub projects_completed: u32,
+    pub amount: f64,
+    pub by_cause: Vec<ReportCauseImpact>, // Sorted by cause id
+    pub event_ids: Vec<String>,
+}
+
+/// Periodic waqf report generated by the satellite from the donor's reporting preferences
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WaqfReportData {
+    pub id: String,
+    pub waqf_id: String,
+    pub recipient: String,                       // Donor of the waqf
+    pub frequency: ReportingFrequency,
+    pub period_start: String,                    // Nanosecond timestamps
+    pub period_end: String,
+    pub generated_at: String,
+    pub financial: Option<ReportFinancialSection>, // Set when the donor asked for "financial" reports
+    pub impact: Option<ReportImpactSection>,       // Set when the donor asked for "impact" reports
+    pub delivery_method: DeliveryMethod,
+}
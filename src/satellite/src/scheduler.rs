@@ -0,0 +1,61 @@
+use crate::installment_hooks::queue_due_installments;
+use crate::investment_return_hooks::distribute_due_returns;
+use crate::management_fee_hooks::accrue_management_fees;
+use crate::notification_hooks::{deliver_notifications, prune_notifications};
+use crate::report_hooks::generate_due_reports;
+use crate::tranche_hooks::process_matured_tranches;
+use junobuild_satellite::{list_docs, Doc};
+use junobuild_shared::types::list::{ListPaginate, ListParams};
+use std::time::Duration;
+
+// How often the scheduled jobs sweep the waqfs collection
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Waqfs handled per timer callback by a sweep
+const SWEEP_PAGE_SIZE: usize = 100;
+
+// Register the recurring sweep (timers do not survive upgrades, so this runs on init and post-upgrade)
+pub fn start_scheduler() {
+    ic_cdk_timers::set_timer_interval(SWEEP_INTERVAL, run_scheduled_jobs);
+    ic_cdk::println!("Scheduler started - sweeping every {} seconds", SWEEP_INTERVAL.as_secs());
+}
+
+// Each job runs in its own message (timers fire in the order they were set), so a trap in
+// one job rolls back only that job and the others still run. Notifications go out last.
+const SCHEDULED_JOBS: [fn(); 7] = [
+    process_matured_tranches,
+    queue_due_installments,
+    distribute_due_returns,
+    accrue_management_fees,
+    generate_due_reports,
+    deliver_notifications,
+    prune_notifications,
+];
+
+fn run_scheduled_jobs() {
+    for job in SCHEDULED_JOBS {
+        ic_cdk_timers::set_timer(Duration::ZERO, job);
+    }
+}
+
+/// Run a sweep over the waqfs collection one page per timer callback, so no message decodes
+/// the whole collection and a trap only rolls back the page it happened in
+pub fn sweep_waqfs(sweep_page: fn(Vec<(String, Doc)>)) {
+    run_sweep_page(sweep_page, None);
+}
+
+fn run_sweep_page(sweep_page: fn(Vec<(String, Doc)>), start_after: Option<String>) {
+    let params = ListParams {
+        paginate: Some(ListPaginate { start_after, limit: Some(SWEEP_PAGE_SIZE) }),
+        ..Default::default()
+    };
+    let waqfs = list_docs("waqfs".to_string(), params).items;
+    let last_key = waqfs.last().map(|(key, _)| key.clone());
+    let full_page = waqfs.len() == SWEEP_PAGE_SIZE;
+
+    sweep_page(waqfs);
+
+    if full_page {
+        ic_cdk_timers::set_timer(Duration::ZERO, move || run_sweep_page(sweep_page, last_key));
+    }
+}
@@ -0,0 +1,52 @@
+use crate::cause_pool_hooks::{validate_cause_pool_settings, CAUSE_POOL_SETTINGS_KEY};
+use crate::deed_hooks::{validate_deed_signing_keys, DEED_SIGNING_KEYS_SETTINGS_KEY};
+use crate::deed_types::DeedSigningKeysConfig;
+use crate::management_fee_hooks::{self, MANAGEMENT_FEE_SETTINGS_KEY};
+use crate::waqf_types::CausePoolConfig;
+use junobuild_satellite::{AssertDeleteDocContext, AssertSetDocContext, OnSetDocContext};
+use junobuild_utils::decode_doc_data;
+
+// Each platform_settings document is owned by the module that reads it; this routes by key
+
+pub fn assert_platform_settings_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    match context.data.key.as_str() {
+        MANAGEMENT_FEE_SETTINGS_KEY => management_fee_hooks::assert_fee_settings_operations(context),
+        CAUSE_POOL_SETTINGS_KEY => {
+            let config: CausePoolConfig = decode_doc_data(&context.data.data.proposed.data)
+                .map_err(|e| format!("Invalid cause pool settings structure: {}", e))?;
+            validate_cause_pool_settings(&config, &context.caller.to_text())
+        }
+        DEED_SIGNING_KEYS_SETTINGS_KEY => {
+            let config: DeedSigningKeysConfig = decode_doc_data(&context.data.data.proposed.data)
+                .map_err(|e| format!("Invalid deed signing keys structure: {}", e))?;
+            validate_deed_signing_keys(&config, &context.caller.to_text())
+        }
+        other => Err(format!("Unknown platform setting: {}", other)),
+    }
+}
+
+pub fn assert_platform_settings_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Platform settings cannot be deleted - update them instead".into())
+}
+
+pub fn handle_platform_settings_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    match context.data.key.as_str() {
+        MANAGEMENT_FEE_SETTINGS_KEY => management_fee_hooks::handle_fee_settings_changes(context),
+        CAUSE_POOL_SETTINGS_KEY => {
+            let config: CausePoolConfig = decode_doc_data(&context.data.data.after.data)
+                .map_err(|e| format!("Cannot decode cause pool settings: {}", e))?;
+            ic_cdk::println!("Cause pool set to waqf {} by {}", config.waqf_id, config.updated_by);
+            Ok(())
+        }
+        DEED_SIGNING_KEYS_SETTINGS_KEY => {
+            let config: DeedSigningKeysConfig = decode_doc_data(&context.data.data.after.data)
+                .map_err(|e| format!("Cannot decode deed signing keys: {}", e))?;
+            ic_cdk::println!("Deed signing keys updated by {}: {} keys", config.updated_by, config.public_keys.len());
+            Ok(())
+        }
+        other => {
+            ic_cdk::println!("Platform setting {} changed without a handler", other);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::activity_log_hooks;
+use crate::admin_hooks::{caller_has_role, AdminRole};
+use crate::cause_hooks;
+use crate::template_types::WaqfTemplateData;
+use crate::waqf_types::{HybridAllocation, WaqfData, WaqfType};
+use junobuild_satellite::{get_doc, AssertDeleteDocContext, AssertSetDocContext, OnSetDocContext};
+use junobuild_utils::decode_doc_data;
+
+const MIN_NAME_LENGTH: usize = 2;
+const MAX_NAME_LENGTH: usize = 100;
+const MAX_DESCRIPTION_LENGTH: usize = 2000;
+
+// Percentages are compared to the template within this margin (client-side rounding)
+const SPLIT_TOLERANCE: f64 = 0.01;
+
+const TEMPLATE_MANAGERS: &[AdminRole] = &[AdminRole::WaqfManager, AdminRole::PlatformAdmin];
+
+fn split_parts(split: &HybridAllocation) -> [f64; 3] {
+    [
+        split.permanent.unwrap_or(0.0),
+        split.temporary_consumable.unwrap_or(0.0),
+        split.temporary_revolving.unwrap_or(0.0),
+    ]
+}
+
+fn has_revolving_part(template: &WaqfTemplateData) -> bool {
+    match template.waqf_type {
+        WaqfType::TemporaryRevolving => true,
+        WaqfType::Hybrid => template.hybrid_split.as_ref().is_some_and(|split| split_parts(split)[2] > 0.0),
+        WaqfType::Permanent | WaqfType::TemporaryConsumable => false,
+    }
+}
+
+fn validate_template_data(template: &WaqfTemplateData) -> std::result::Result<(), String> {
+    let name_length = template.name.trim().len();
+    if !(MIN_NAME_LENGTH..=MAX_NAME_LENGTH).contains(&name_length) {
+        return Err(format!("Template name must be between {} and {} characters", MIN_NAME_LENGTH, MAX_NAME_LENGTH));
+    }
+    if template.description.len() > MAX_DESCRIPTION_LENGTH {
+        return Err(format!("Template description cannot exceed {} characters", MAX_DESCRIPTION_LENGTH));
+    }
+
+    if template.causes.is_empty() {
+        return Err("A template must list at least one cause".into());
+    }
+    for (index, cause_id) in template.causes.iter().enumerate() {
+        if template.causes[..index].contains(cause_id) {
+            return Err(format!("Cause {} is listed twice", cause_id));
+        }
+        cause_hooks::load_cause(cause_id)?;
+    }
+
+    match (&template.waqf_type, &template.hybrid_split) {
+        (WaqfType::Hybrid, Some(split)) => {
+            let parts = split_parts(split);
+            if parts.iter().any(|part| *part < 0.0) {
+                return Err("Hybrid split percentages cannot be negative".into());
+            }
+            let total: f64 = parts.iter().sum();
+            if (total - 100.0).abs() > SPLIT_TOLERANCE {
+                return Err(format!("Hybrid split must sum to 100%, got {:.2}%", total));
+            }
+        }
+        (WaqfType::Hybrid, None) => return Err("Hybrid templates must define the hybrid split".into()),
+        (_, Some(_)) => return Err("Only hybrid templates can define a hybrid split".into()),
+        (_, None) => {}
+    }
+
+    match &template.lock_terms {
+        Some(terms) => {
+            if !has_revolving_part(template) {
+                return Err("Lock terms only apply to templates with a revolving part".into());
+            }
+            if !(1..=240).contains(&terms.lock_period_months) {
+                return Err("Template lock period must be between 1 and 240 months".into());
+            }
+        }
+        None if has_revolving_part(template) => {
+            return Err("Templates with a revolving part must define the lock terms".into());
+        }
+        None => {}
+    }
+
+    template.updated_at.parse::<u64>()
+        .map_err(|_| "Invalid updated at format")?;
+
+    Ok(())
+}
+
+pub fn assert_waqf_template_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let template: WaqfTemplateData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid waqf template data structure: {}", e))?;
+
+    let caller = context.caller.to_text();
+    if !caller_has_role(&caller, TEMPLATE_MANAGERS) {
+        return Err("Only waqf managers can maintain waqf templates".into());
+    }
+    if template.updated_by != caller {
+        return Err("Updated by must match the calling waqf manager".into());
+    }
+    if template.id != context.data.key {
+        return Err("Template id must match the document key".into());
+    }
+
+    // Each change is a new version so waqfs keep pointing at the terms they were checked against
+    let expected_version = match &context.data.data.current {
+        Some(doc) => {
+            let current: WaqfTemplateData = decode_doc_data(&doc.data)
+                .map_err(|e| format!("Failed to decode current template: {}", e))?;
+            current.version + 1
+        }
+        None => 1,
+    };
+    if template.version != expected_version {
+        return Err(format!("Template version must be {}", expected_version));
+    }
+
+    validate_template_data(&template)?;
+
+    ic_cdk::println!(
+        "Waqf template validation passed: {} v{} - Type: {:?}, Causes: {}, Active: {}",
+        template.id, template.version, template.waqf_type, template.causes.len(), template.is_active
+    );
+
+    Ok(())
+}
+
+pub fn assert_waqf_template_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Waqf templates cannot be deleted - deactivate them instead".into())
+}
+
+pub fn handle_waqf_template_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let template: WaqfTemplateData = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode waqf template data: {}", e))?;
+
+    let action = if context.data.data.before.is_none() { "waqf_template_created" } else { "waqf_template_updated" };
+    activity_log_hooks::record_audit_log(
+        action,
+        &template.updated_by,
+        "waqf_template",
+        &template.id,
+        format!(
+            "Template {} version {} ({:?}, causes {:?}, active: {})",
+            template.name, template.version, template.waqf_type, template.causes, template.is_active
+        ),
+    );
+
+    Ok(())
+}
+
+/// Check a new waqf created from a template matches the template version it names
+pub fn check_template_conformance(waqf: &WaqfData) -> std::result::Result<(), String> {
+    let reference = match &waqf.template {
+        Some(reference) => reference,
+        None => return Ok(()),
+    };
+
+    let template: WaqfTemplateData = get_doc("waqf_templates".to_string(), reference.template_id.clone())
+        .ok_or_else(|| format!("Waqf template not found: {}", reference.template_id))
+        .and_then(|doc| decode_doc_data(&doc.data).map_err(|e| format!("Failed to decode waqf template: {}", e)))?;
+
+    if !template.is_active {
+        return Err(format!("Waqf template {} is no longer offered", template.id));
+    }
+    if reference.version != template.version {
+        return Err(format!(
+            "Waqf template {} has changed (version {} is current, the waqf names version {})",
+            template.id, template.version, reference.version
+        ));
+    }
+
+    if waqf.waqf_type != template.waqf_type {
+        return Err(format!("Template {} creates {:?} waqfs", template.id, template.waqf_type));
+    }
+
+    if let Some(cause_id) = waqf.selected_causes.iter().find(|id| !template.causes.contains(id)) {
+        return Err(format!("Cause {} is not part of template {}", cause_id, template.id));
+    }
+    if template.require_all_causes && template.causes.iter().any(|id| !waqf.selected_causes.contains(id)) {
+        return Err(format!("Template {} requires all of its causes to be selected", template.id));
+    }
+
+    if let Some(split) = &template.hybrid_split {
+        let expected = split_parts(split);
+        for cause_id in &waqf.selected_causes {
+            let allocation = waqf.hybrid_allocations.as_ref()
+                .and_then(|allocations| allocations.iter().find(|a| &a.cause_id == cause_id))
+                .ok_or_else(|| format!("Cause {} has no hybrid split", cause_id))?;
+            let matches = split_parts(&allocation.allocations)
+                .iter()
+                .zip(expected)
+                .all(|(part, expected)| (part - expected).abs() <= SPLIT_TOLERANCE);
+            if !matches {
+                return Err(format!("The hybrid split of cause {} does not match template {}", cause_id, template.id));
+            }
+        }
+    }
+
+    if let Some(terms) = &template.lock_terms {
+        let details = waqf.revolving_details.as_ref()
+            .ok_or_else(|| format!("Template {} requires revolving details", template.id))?;
+        if details.lock_period_months != terms.lock_period_months {
+            return Err(format!("Template {} locks principal for {} months", template.id, terms.lock_period_months));
+        }
+        if terms.principal_return_method.is_some_and(|method| method != details.principal_return_method) {
+            return Err(format!("The principal return method does not match template {}", template.id));
+        }
+        if terms.early_withdrawal_allowed.is_some_and(|allowed| allowed != details.early_withdrawal_allowed) {
+            return Err(format!("The early withdrawal terms do not match template {}", template.id));
+        }
+    }
+
+    ic_cdk::println!("Waqf {} conforms to template {} v{}", waqf.id, template.id, template.version);
+
+    Ok(())
+}
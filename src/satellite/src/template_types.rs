@@ -0,0 +1,29 @@
+use crate::waqf_types::{HybridAllocation, PrincipalReturnMethod, WaqfType};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// Lock terms a template fixes for the revolving part of a waqf
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TemplateLockTerms {
+    pub lock_period_months: u32,
+    pub principal_return_method: Option<PrincipalReturnMethod>, // Any method when not set
+    pub early_withdrawal_allowed: Option<bool>,
+}
+
+/// Curated waqf template maintained by waqf managers. Every change bumps the version, and
+/// waqfs record the version they were created from.
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WaqfTemplateData {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub version: u32,
+    pub waqf_type: WaqfType,
+    pub causes: Vec<String>,                    // Causes a waqf from this template may select
+    pub require_all_causes: bool,               // The waqf must select every listed cause
+    pub hybrid_split: Option<HybridAllocation>, // Permanent/consumable/revolving split for every cause (hybrid templates)
+    pub lock_terms: Option<TemplateLockTerms>,
+    pub is_active: bool,                        // Inactive templates cannot be used for new waqfs
+    pub updated_by: String,
+    pub updated_at: String,                     // Nanosecond timestamp
+}
@@ -0,0 +1,382 @@
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::bucket_accounting;
+use crate::cause_hooks;
+use crate::cause_pool_hooks;
+use crate::notification_hooks::{self, Notice};
+use crate::notification_types::{NotificationPayload, NotificationType};
+use crate::termination_types::{TerminationGrounds, TerminationSettlement, TerminationStatus, WaqfTerminationData};
+use crate::tranche_hooks::{self, SweepEffects};
+use crate::waqf_types::{CauseStatus, WaqfData, WaqfStatus, WaqfType};
+use junobuild_satellite::{get_doc, list_docs, set_doc, AssertDeleteDocContext, AssertSetDocContext, OnSetDocContext, SetDoc};
+use junobuild_shared::types::list::ListParams;
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+
+// Terminations end a waqf for good, so the grounds must be documented for audit
+const MIN_REASON_LENGTH: usize = 50;
+const MAX_REASON_LENGTH: usize = 5000;
+const MAX_REVIEW_NOTES_LENGTH: usize = 2000;
+
+fn load_waqf(waqf_id: &str) -> std::result::Result<(WaqfData, junobuild_satellite::Doc), String> {
+    let doc = get_doc("waqfs".to_string(), waqf_id.to_string())
+        .ok_or_else(|| format!("Waqf not found: {}", waqf_id))?;
+    let waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+    Ok((waqf, doc))
+}
+
+/// Terminations of a waqf that are still open or being carried out
+fn open_terminations(waqf_id: &str) -> Vec<WaqfTerminationData> {
+    list_docs("waqf_terminations".to_string(), ListParams::default())
+        .items
+        .into_iter()
+        .filter_map(|(_, doc)| decode_doc_data::<WaqfTerminationData>(&doc.data).ok())
+        .filter(|termination| {
+            termination.waqf_id == waqf_id
+                && matches!(termination.status, TerminationStatus::Pending | TerminationStatus::Approved)
+        })
+        .collect()
+}
+
+/// New donations are refused once a waqf is terminated or its termination has been approved
+pub fn check_accepts_donations(waqf_id: &str) -> std::result::Result<(), String> {
+    if let Some(doc) = get_doc("waqfs".to_string(), waqf_id.to_string()) {
+        let waqf: WaqfData = decode_doc_data(&doc.data)
+            .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+        if waqf.status == WaqfStatus::Terminated {
+            return Err(format!("Waqf {} has been terminated and no longer accepts donations", waqf_id));
+        }
+    }
+
+    if open_terminations(waqf_id).iter().any(|t| t.status == TerminationStatus::Approved) {
+        return Err(format!("Waqf {} is being terminated and no longer accepts donations", waqf_id));
+    }
+
+    Ok(())
+}
+
+/// Only temporary waqfs that are still running can be terminated
+fn validate_terminable_waqf(waqf: &WaqfData) -> std::result::Result<(), String> {
+    if !matches!(waqf.waqf_type, WaqfType::TemporaryConsumable | WaqfType::TemporaryRevolving) {
+        return Err(format!("Only temporary waqfs can be terminated; waqf {} is {:?}", waqf.id, waqf.waqf_type));
+    }
+    if !matches!(waqf.status, WaqfStatus::Active | WaqfStatus::Paused | WaqfStatus::Inactive) {
+        return Err(format!("Waqf {} cannot be terminated from status {}", waqf.id, waqf.status));
+    }
+    if cause_pool_hooks::configured_pool_waqf_id().as_deref() == Some(waqf.id.as_str()) {
+        return Err("The cause pool waqf holds other donors' principal and cannot be terminated".into());
+    }
+    Ok(())
+}
+
+/// The successor must be able to take the remaining consumable balance for good
+fn validate_successor(termination: &WaqfTerminationData) -> std::result::Result<(), String> {
+    match (&termination.successor_cause_id, &termination.successor_waqf_id) {
+        (Some(cause_id), None) => {
+            let cause = cause_hooks::load_cause(cause_id)?;
+            if !cause.is_active || cause.status != CauseStatus::Approved {
+                return Err(format!("Successor cause {} is not an active approved cause", cause_id));
+            }
+        }
+        (None, Some(waqf_id)) => {
+            if *waqf_id == termination.waqf_id {
+                return Err("A waqf cannot be its own successor".into());
+            }
+            let (successor, _) = load_waqf(waqf_id)?;
+            if successor.status != WaqfStatus::Active {
+                return Err(format!("Successor waqf {} is not active", waqf_id));
+            }
+            if !matches!(successor.waqf_type, WaqfType::Permanent | WaqfType::TemporaryConsumable) {
+                return Err("The successor waqf must be a permanent or consumable waqf".into());
+            }
+        }
+        _ => return Err("Exactly one successor cause or successor waqf must be designated".into()),
+    }
+    Ok(())
+}
+
+fn validate_new_termination(termination: &WaqfTerminationData, caller: &str) -> std::result::Result<(), String> {
+    if !caller_has_role(caller, &[AdminRole::ComplianceOfficer]) {
+        return Err("Only compliance officers can open a waqf termination".into());
+    }
+    if termination.opened_by != caller {
+        return Err("Opened by must match the calling compliance officer".into());
+    }
+    if termination.status != TerminationStatus::Pending {
+        return Err("A termination must be opened as pending".into());
+    }
+    if termination.reviewed_by.is_some() || termination.reviewed_at.is_some() || termination.settlement.is_some() {
+        return Err("A new termination cannot carry a review or settlement".into());
+    }
+
+    let reason_length = termination.reason.trim().len();
+    if reason_length < MIN_REASON_LENGTH {
+        return Err(format!(
+            "Termination reason must document why the waqf is ended (at least {} characters)",
+            MIN_REASON_LENGTH
+        ));
+    }
+    if reason_length > MAX_REASON_LENGTH {
+        return Err(format!("Termination reason cannot exceed {} characters", MAX_REASON_LENGTH));
+    }
+
+    let has_order_reference = termination.order_reference.as_ref().is_some_and(|r| !r.trim().is_empty());
+    if termination.grounds != TerminationGrounds::PurposeFailed && !has_order_reference {
+        return Err(format!("Terminations on {} grounds must cite the order reference", termination.grounds));
+    }
+
+    termination.opened_at.parse::<u64>()
+        .map_err(|_| "Invalid opened at format")?;
+
+    let (waqf, _) = load_waqf(&termination.waqf_id)?;
+    validate_terminable_waqf(&waqf)?;
+    validate_successor(termination)?;
+
+    if !open_terminations(&termination.waqf_id).is_empty() {
+        return Err(format!("Waqf {} already has an open termination", termination.waqf_id));
+    }
+
+    Ok(())
+}
+
+fn validate_review(
+    current: &WaqfTerminationData,
+    proposed: &WaqfTerminationData,
+    caller: &str,
+) -> std::result::Result<(), String> {
+    if !caller_has_role(caller, &[AdminRole::PlatformAdmin]) {
+        return Err("Only platform admins can review a waqf termination".into());
+    }
+    if current.status != TerminationStatus::Pending {
+        return Err(format!("Termination {} has already been {}", current.id, current.status));
+    }
+    if !matches!(proposed.status, TerminationStatus::Approved | TerminationStatus::Rejected) {
+        return Err("A termination review must approve or reject it".into());
+    }
+    if proposed.reviewed_by.as_deref() != Some(caller) {
+        return Err("Reviewed by must match the calling platform admin".into());
+    }
+    if current.opened_by == caller {
+        return Err("A termination cannot be reviewed by the officer who opened it".into());
+    }
+    proposed.reviewed_at.as_deref().unwrap_or_default().parse::<u64>()
+        .map_err(|_| "Invalid reviewed at format")?;
+
+    let notes_length = proposed.review_notes.as_ref().map_or(0, |notes| notes.trim().len());
+    if proposed.status == TerminationStatus::Rejected && notes_length == 0 {
+        return Err("Rejecting a termination requires review notes".into());
+    }
+    if notes_length > MAX_REVIEW_NOTES_LENGTH {
+        return Err(format!("Review notes cannot exceed {} characters", MAX_REVIEW_NOTES_LENGTH));
+    }
+
+    // Only the review itself may be added
+    let expected = WaqfTerminationData {
+        status: proposed.status,
+        reviewed_by: proposed.reviewed_by.clone(),
+        reviewed_at: proposed.reviewed_at.clone(),
+        review_notes: proposed.review_notes.clone(),
+        ..current.clone()
+    };
+    if *proposed != expected {
+        return Err("Only the status and review fields can change when reviewing a termination".into());
+    }
+
+    // The waqf or successor may have changed since the termination was opened
+    if proposed.status == TerminationStatus::Approved {
+        let (waqf, _) = load_waqf(&current.waqf_id)?;
+        validate_terminable_waqf(&waqf)?;
+        validate_successor(current)?;
+    }
+
+    Ok(())
+}
+
+pub fn assert_waqf_termination_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let termination: WaqfTerminationData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid waqf termination data structure: {}", e))?;
+
+    if termination.id != context.data.key {
+        return Err("Termination id must match the document key".into());
+    }
+
+    let caller = context.caller.to_text();
+    let current: Option<WaqfTerminationData> = match &context.data.data.current {
+        Some(doc) => Some(decode_doc_data(&doc.data)
+            .map_err(|e| format!("Failed to decode current termination: {}", e))?),
+        None => None,
+    };
+
+    match current {
+        None => validate_new_termination(&termination, &caller)?,
+        // The satellite records the settlement once the waqf has been wound down
+        Some(current) if is_satellite_caller(&caller) => {
+            if current.status != TerminationStatus::Approved
+                || termination.status != TerminationStatus::Completed
+                || termination.settlement.is_none()
+            {
+                return Err("The satellite can only complete an approved termination".into());
+            }
+        }
+        Some(current) => validate_review(&current, &termination, &caller)?,
+    }
+
+    ic_cdk::println!(
+        "Waqf termination validation passed: {} - Waqf: {}, Grounds: {}, Status: {}",
+        termination.id, termination.waqf_id, termination.grounds, termination.status
+    );
+
+    Ok(())
+}
+
+pub fn assert_waqf_termination_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Waqf terminations cannot be deleted for audit purposes".into())
+}
+
+pub fn handle_waqf_termination_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let termination: WaqfTerminationData = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode waqf termination data: {}", e))?;
+    let previous_status = context.data.data.before.as_ref()
+        .and_then(|doc| decode_doc_data::<WaqfTerminationData>(&doc.data).ok())
+        .map(|previous| previous.status);
+
+    ic_cdk::println!(
+        "Waqf termination {}: {} - Waqf: {}, Status: {}",
+        if previous_status.is_none() { "CREATE" } else { "UPDATE" },
+        termination.id, termination.waqf_id, termination.status
+    );
+
+    if previous_status != Some(TerminationStatus::Pending) || termination.status != TerminationStatus::Approved {
+        return Ok(());
+    }
+
+    let settlement = wind_down(&termination)?;
+
+    let mut completed = termination.clone();
+    completed.status = TerminationStatus::Completed;
+    completed.settlement = Some(settlement);
+
+    let data = encode_doc_data(&completed)
+        .map_err(|e| format!("Failed to encode waqf termination data: {}", e))?;
+    set_doc(
+        "waqf_terminations".to_string(),
+        termination.id.clone(),
+        SetDoc {
+            data,
+            description: context.data.data.after.description.clone(),
+            version: context.data.data.after.version,
+        },
+    );
+
+    Ok(())
+}
+
+/// Settle the waqf's tranches, pass the remaining consumable balance to the successor and
+/// mark the waqf terminated, all in this message.
+fn wind_down(termination: &WaqfTerminationData) -> std::result::Result<TerminationSettlement, String> {
+    let now = ic_cdk::api::time();
+    let (mut waqf, doc) = load_waqf(&termination.waqf_id)?;
+    validate_terminable_waqf(&waqf)?;
+
+    let mut effects = SweepEffects::default();
+    let outcome = tranche_hooks::settle_tranches_for_termination(&mut waqf, now, &mut effects);
+
+    // Cy-près: whatever is left to spend goes to the closest remaining purpose
+    bucket_accounting::ensure_bucket_balances(&mut waqf);
+    let redirected: f64 = waqf.financial.bucket_balances.values_mut()
+        .map(|balances| std::mem::take(&mut balances.consumable))
+        .sum();
+    let redirected = (redirected * 100.0).round() / 100.0;
+
+    if redirected > 0.0 {
+        waqf.financial.total_distributed += redirected;
+        waqf.financial.current_balance = (waqf.financial.current_balance - redirected).max(0.0);
+    }
+
+    // Credited first: nothing has been written yet if the successor cannot take the balance
+    if redirected > 0.0 {
+        match (&termination.successor_cause_id, &termination.successor_waqf_id) {
+            (Some(cause_id), _) => {
+                cause_hooks::record_allocation_funding(cause_id, redirected, &[])?;
+            }
+            (None, Some(successor_id)) => credit_successor_waqf(successor_id, redirected, now)?,
+            (None, None) => return Err("Termination has no successor".into()),
+        }
+    }
+
+    waqf.status = WaqfStatus::Terminated;
+    waqf.updated_at = Some(now.to_string());
+
+    let data = encode_doc_data(&waqf)
+        .map_err(|e| format!("Failed to encode waqf data: {}", e))?;
+    set_doc(
+        "waqfs".to_string(),
+        waqf.id.clone(),
+        SetDoc { data, description: doc.description, version: doc.version },
+    );
+
+    // Credited after the waqf is saved so the pool is not overwritten by a stale copy
+    for rollover in effects.pooled {
+        if let Err(e) = cause_pool_hooks::credit_cause_pool(&rollover) {
+            ic_cdk::println!(
+                "ERROR - Cannot credit cause pool {} with tranche {}: {}",
+                rollover.pool_waqf_id, rollover.tranche.id, e
+            );
+        }
+    }
+
+    effects.notices.push(Notice::new(
+        &waqf.created_by,
+        &waqf.id,
+        NotificationType::WaqfTerminated,
+        NotificationPayload {
+            amount: Some(redirected),
+            related_waqf_id: termination.successor_waqf_id.clone(),
+            cause_id: termination.successor_cause_id.clone(),
+            reference: Some(termination.id.clone()),
+            reason: Some(termination.grounds.to_string()),
+            ..Default::default()
+        },
+    ));
+    notification_hooks::send_all(effects.notices);
+
+    ic_cdk::println!(
+        "Waqf {} terminated ({}): {} tranches settled, {} unsettled, {:.2} redirected to {}",
+        waqf.id,
+        termination.id,
+        outcome.settled.len(),
+        outcome.unsettled.len(),
+        redirected,
+        termination.successor_cause_id.as_ref().or(termination.successor_waqf_id.as_ref()).cloned().unwrap_or_default()
+    );
+
+    Ok(TerminationSettlement {
+        settled_tranches: outcome.settled,
+        unsettled_tranches: outcome.unsettled,
+        accelerated_installments: outcome.accelerated_installments,
+        redirected_amount: redirected,
+        completed_at: now.to_string(),
+    })
+}
+
+/// Add the redirected balance to the successor waqf as if it had been donated
+fn credit_successor_waqf(waqf_id: &str, amount: f64, now: u64) -> std::result::Result<(), String> {
+    let (mut successor, doc) = load_waqf(waqf_id)?;
+
+    bucket_accounting::ensure_bucket_balances(&mut successor);
+    let split = bucket_accounting::split_amount(&successor, amount);
+    bucket_accounting::credit(&mut successor.financial, &split);
+    successor.financial.total_donations += amount;
+    successor.financial.current_balance += amount;
+    successor.last_contribution_date = Some(now.to_string());
+    successor.updated_at = Some(now.to_string());
+
+    let data = encode_doc_data(&successor)
+        .map_err(|e| format!("Failed to encode successor waqf data: {}", e))?;
+    set_doc(
+        "waqfs".to_string(),
+        waqf_id.to_string(),
+        SetDoc { data, description: doc.description, version: doc.version },
+    );
+
+    Ok(())
+}
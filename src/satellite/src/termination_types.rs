@@ -0,0 +1,52 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TerminationGrounds {
+        PurposeFailed => "purpose_failed",      // The waqf's purpose can no longer be served
+        CourtOrder => "court_order",
+        ShariahBoardOrder => "shariah_board_order",
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TerminationStatus {
+        Pending => "pending",    // Opened by a compliance officer, awaiting platform-admin review
+        Approved => "approved",   // Wind-down in progress
+        Rejected => "rejected",
+        Completed => "completed",  // Set by the satellite once the waqf has been wound down
+    }
+}
+
+/// Outcome of winding a terminated waqf down
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TerminationSettlement {
+    pub settled_tranches: Vec<String>,       // Tranches whose expiration preference was carried out
+    pub unsettled_tranches: Vec<String>,     // Tranches that could not be settled and need manual follow-up
+    pub accelerated_installments: u32,       // Scheduled installments brought forward to the termination date
+    pub redirected_amount: f64,              // Consumable balance passed on to the successor
+    pub completed_at: String,                // Nanosecond timestamp
+}
+
+/// Termination of a temporary waqf, opened by a compliance officer and approved by a
+/// platform admin. The remaining consumable balance goes to the successor (cy-près).
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WaqfTerminationData {
+    pub id: String,
+    pub waqf_id: String,
+    pub grounds: TerminationGrounds,
+    pub reason: String,                        // Documented reason for the termination
+    pub order_reference: Option<String>,       // Court or Shariah-board order number
+    pub successor_cause_id: Option<String>,    // Exactly one successor cause or waqf
+    pub successor_waqf_id: Option<String>,
+    pub status: TerminationStatus,
+    pub opened_by: String,
+    pub opened_at: String,                     // Nanosecond timestamps
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<String>,
+    pub review_notes: Option<String>,
+    #[serde(default)]
+    pub settlement: Option<TerminationSettlement>,
+}
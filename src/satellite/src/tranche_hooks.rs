@@ -1,7 +1,28 @@
-use crate::waqf_types::{WaqfData, ContributionTranche, InstallmentPayment};
-use junobuild_satellite::{OnSetDocContext, AssertSetDocContext, AssertDeleteDocContext};
+use crate::bucket_accounting;
+use crate::calendar;
+use crate::cause_hooks;
+use crate::cause_pool_hooks::{self, PooledRollover};
+use crate::notification_hooks::{self, Notice};
+use crate::notification_types::{NotificationPayload, NotificationType};
+use crate::scheduler;
+use crate::waqf_types::{
+    AutoRolloverPreference, ConsumableWaqfDetails, ContributionTranche, ConversionDetails, ConversionOrigin,
+    ConversionTarget, ExpirationAction, ExpirationFailure, FinancialMetrics, InstallmentPayment, InstallmentSchedule,
+    InstallmentStatus, PenaltyDestination, PoolOrigin, PrincipalReturnMethod, RevolvingWaqfDetails, RolloverTarget,
+    SpendingSchedule, TrancheExpirationPreference, TrancheStatus, TrusteePower, WaqfBucket, WaqfData, WaqfStatus, WaqfType,
+};
+use junobuild_satellite::{get_doc, set_doc, AssertDeleteDocContext, AssertSetDocContext, Doc, OnSetDocContext, SetDoc};
 use junobuild_utils::{decode_doc_data, encode_doc_data};
 use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+// Time the donor has after maturity to change the expiration preference before it is carried out
+const EXPIRATION_GRACE_PERIOD_NANOS: u64 = 14 * NANOS_PER_DAY;
+
+// Spending period for converted consumable waqfs when the preference does not give one
+const DEFAULT_CONSUMABLE_DURATION_MONTHS: u32 = 12;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TrancheReturnRequest {
@@ -9,9 +30,19 @@ pub struct TrancheReturnRequest {
     pub tranche_id: String,
     pub requested_by: String,
     pub timestamp: u64,
+    #[serde(default)]
+    pub amount: Option<f64>, // Partial withdrawal - the whole tranche is returned when omitted
 }
 
-/// Validates and processes a tranche return request
+fn load_waqf(waqf_id: &str) -> std::result::Result<(WaqfData, junobuild_satellite::Doc), String> {
+    let doc = get_doc("waqfs".to_string(), waqf_id.to_string())
+        .ok_or_else(|| format!("Waqf not found: {}", waqf_id))?;
+    let waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+    Ok((waqf, doc))
+}
+
+/// Validates a donor's request to take back a tranche (early withdrawal or matured return)
 pub fn assert_tranche_return_operations(
     context: AssertSetDocContext,
 ) -> std::result::Result<(), String> {
@@ -19,7 +50,7 @@ pub fn assert_tranche_return_operations(
     
     // Decode the tranche return request
     let request_result: std::result::Result<TrancheReturnRequest, _> = 
-        decode_doc_data(&context.data.doc.data);
+        decode_doc_data(&context.data.data.proposed.data);
     
     let request = match request_result {
         Ok(req) => req,
@@ -33,10 +64,31 @@ pub fn assert_tranche_return_operations(
         "TRANCHE RETURN: Request from {} to return tranche {} for waqf {}",
         caller, request.tranche_id, request.waqf_id
     );
+
+    if context.data.data.current.is_some() {
+        return Err("Tranche return requests are immutable once submitted".to_string());
+    }
+
+    if request.requested_by != caller {
+        return Err("Requested by must match the caller".to_string());
+    }
     
-    // Verify caller owns the waqf
-    // Note: In production, you'd fetch the waqf and verify ownership
-    // For now, we trust the request contains valid waqf_id
+    // Verify caller owns the principal: the waqf donor, or the donor who pooled a cause pool tranche.
+    // Trustees with the withdrawal power may file for the donor, but not for pooled principal.
+    let (mut waqf, _) = load_waqf(&request.waqf_id)?;
+    let pool_owner = waqf.revolving_details.as_ref()
+        .and_then(|d| d.contribution_tranches.as_ref())
+        .and_then(|tranches| tranches.iter().find(|t| t.id == request.tranche_id))
+        .and_then(|t| t.pool_origin.as_ref())
+        .map(|origin| origin.owner.clone());
+    let trustee_may_file = pool_owner.is_none() && waqf.trustee_has_power(&caller, TrusteePower::ApproveWithdrawals);
+    let owner = pool_owner.unwrap_or_else(|| waqf.created_by.clone());
+    if owner != caller && !trustee_may_file {
+        return Err("Only the donor who owns the principal, or a trustee approving withdrawals, can request a tranche return".to_string());
+    }
+
+    // Dry run on a copy so the request is rejected when the return cannot be processed
+    process_return_request(&mut waqf, &request, ic_cdk::api::time())?;
     
     Ok(())
 }
@@ -52,7 +104,7 @@ pub fn handle_tranche_return_changes(
     context: OnSetDocContext,
 ) -> std::result::Result<(), String> {
     let request_result: std::result::Result<TrancheReturnRequest, _> = 
-        decode_doc_data(&context.data.doc.data);
+        decode_doc_data(&context.data.data.after.data);
     
     let request = match request_result {
         Ok(req) => req,
@@ -61,24 +113,247 @@ pub fn handle_tranche_return_changes(
             return Ok(()); // Don't fail the operation, just log
         }
     };
+
+    if context.data.data.before.is_some() {
+        return Ok(());
+    }
+
+    let (mut waqf, doc) = load_waqf(&request.waqf_id)?;
+    let notices = process_return_request(&mut waqf, &request, ic_cdk::api::time())?;
+    waqf.updated_at = Some(ic_cdk::api::time().to_string());
+
+    let data = encode_doc_data(&waqf)
+        .map_err(|e| format!("Failed to encode waqf data: {}", e))?;
+    let _ = set_doc(
+        "waqfs".to_string(),
+        request.waqf_id.clone(),
+        SetDoc {
+            data,
+            description: doc.description,
+            version: doc.version,
+        },
+    );
+
+    notification_hooks::send_all(notices);
     
     ic_cdk::println!(
         "TRANCHE RETURN PROCESSED: Tranche {} returned for waqf {} by {}",
         request.tranche_id, request.waqf_id, request.requested_by
     );
     
-    // The actual tranche status update happens on the waqf document itself
-    // This is just an audit record
-    
     Ok(())
 }
 
-/// Helper function to mark a tranche as returned in a waqf document
+/// Carry out a tranche return request: the whole tranche, or for a partial withdrawal the
+/// portion split off from it
+fn process_return_request(
+    waqf: &mut WaqfData,
+    request: &TrancheReturnRequest,
+    now: u64,
+) -> std::result::Result<Vec<Notice>, String> {
+    let tranche_amount = waqf.revolving_details.as_ref()
+        .and_then(|d| d.contribution_tranches.as_ref())
+        .and_then(|tranches| tranches.iter().find(|t| t.id == request.tranche_id))
+        .map(|t| t.amount)
+        .ok_or_else(|| format!("Tranche {} not found", request.tranche_id))?;
+
+    let amount = match request.amount {
+        Some(amount) if bucket_accounting::to_cents(amount) != bucket_accounting::to_cents(tranche_amount) => amount,
+        _ => return mark_tranche_as_returned(waqf, &request.tranche_id, now),
+    };
+
+    let (withdrawn_id, remaining_id) = split_tranche(waqf, &request.tranche_id, amount, now)?;
+    let mut notices = mark_tranche_as_returned(waqf, &withdrawn_id, now)?;
+    for notice in notices.iter_mut() {
+        notice.payload.new_tranche_id = Some(remaining_id.clone());
+    }
+    Ok(notices)
+}
+
+/// Split a locked tranche for a partial withdrawal into a withdrawn portion and a remaining
+/// portion that keeps the original maturity. The parent stays on the waqf with status "split".
+/// Returns the ids of the withdrawn and remaining portions.
+fn split_tranche(
+    waqf: &mut WaqfData,
+    tranche_id: &str,
+    amount: f64,
+    now: u64,
+) -> std::result::Result<(String, String), String> {
+    let details = waqf.revolving_details.as_mut().ok_or("Waqf is not a revolving waqf")?;
+    let tranches = details.contribution_tranches.as_mut().ok_or("Waqf has no contribution tranches")?;
+    let index = tranches.iter().position(|t| t.id == tranche_id)
+        .ok_or_else(|| format!("Tranche {} not found", tranche_id))?;
+    let parent = &tranches[index];
+
+    if is_settled(parent) {
+        return Err(format!("Tranche {} has already been returned, rolled over or split", tranche_id));
+    }
+    let maturity_date = parent.maturity_date.parse::<u64>().map_err(|_| "Invalid maturity date format")?;
+    if now >= maturity_date {
+        return Err("Partial withdrawals are only available before maturity - the whole tranche is returned at maturity".to_string());
+    }
+
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err("Withdrawal amount must be positive".to_string());
+    }
+    let withdrawn_cents = bucket_accounting::to_cents(amount);
+    let parent_cents = bucket_accounting::to_cents(parent.amount);
+    if withdrawn_cents == 0 || withdrawn_cents >= parent_cents {
+        return Err(format!(
+            "Withdrawal amount must be less than the tranche amount ({:.2})",
+            parent.amount
+        ));
+    }
+
+    let portion = |id: String, cents: u64| ContributionTranche {
+        id,
+        amount: cents as f64 / 100.0,
+        status: Some(TrancheStatus::Locked),
+        installment_payments: None,
+        matured_at: None,
+        rollover_origin_id: None,
+        rollover_target_id: None,
+        rollover_target_waqf_id: None,
+        split_from: Some(tranche_id.to_string()),
+        split_into: None,
+        expiration_failure: None,
+        ..parent.clone()
+    };
+    let withdrawn = portion(format!("{}_w{}", tranche_id, now), withdrawn_cents);
+    let remaining = portion(format!("{}_r{}", tranche_id, now), parent_cents - withdrawn_cents);
+    let ids = (withdrawn.id.clone(), remaining.id.clone());
+
+    let parent = &mut tranches[index];
+    parent.status = Some(TrancheStatus::Split);
+    parent.split_into = Some(vec![ids.0.clone(), ids.1.clone()]);
+    tranches.push(withdrawn);
+    tranches.push(remaining);
+
+    let tranches: &[ContributionTranche] = tranches;
+    for tranche in tranches.iter().filter(|t| t.id == tranche_id || t.split_from.as_deref() == Some(tranche_id)) {
+        validate_tranche_data(tranche, tranches)?;
+    }
+
+    ic_cdk::println!(
+        "Tranche {} split for a partial withdrawal: {:.2} withdrawn as {}, {:.2} remains locked as {}",
+        tranche_id,
+        withdrawn_cents as f64 / 100.0,
+        ids.0,
+        (parent_cents - withdrawn_cents) as f64 / 100.0,
+        ids.1
+    );
+
+    Ok(ids)
+}
+
+/// Installment schedule for returning a tranche's principal, starting one interval from now
+fn build_installment_schedule(
+    tranche_id: &str,
+    amount: f64,
+    schedule: &InstallmentSchedule,
+    now: u64,
+) -> Vec<InstallmentPayment> {
+    let installments = schedule.number_of_installments.max(1);
+    let interval_months = calendar::frequency_months(&schedule.frequency).unwrap_or(1);
+
+    // Whole-cent installments that add up exactly to the amount being returned
+    let weights = vec![1.0 / installments as f64; installments as usize];
+    let amounts = bucket_accounting::apportion_cents(bucket_accounting::to_cents(amount), &weights);
+
+    amounts
+        .into_iter()
+        .enumerate()
+        .map(|(index, cents)| InstallmentPayment {
+            id: format!("inst_{}_{}", tranche_id, index + 1),
+            amount: cents as f64 / 100.0,
+            due_date: calendar::add_months(now, interval_months * (index as u32 + 1)).to_string(),
+            status: InstallmentStatus::Scheduled,
+            paid_date: None,
+            payout_reference: None,
+        })
+        .collect()
+}
+
+/// Early withdrawal penalty rate for a tranche withdrawn at `now`.
+/// A hardship waiver removes the penalty; otherwise the first tier whose `until_month` has not
+/// been reached applies (no penalty once past the last tier), falling back to the flat rate.
+pub fn early_withdrawal_penalty_rate(
+    details: &RevolvingWaqfDetails,
+    tranche: &ContributionTranche,
+    now: u64,
+) -> f64 {
+    if tranche.penalty_waiver.is_some() {
+        return 0.0;
+    }
+
+    match &details.early_withdrawal_penalty_tiers {
+        Some(tiers) if !tiers.is_empty() => {
+            let contributed_at = tranche.contribution_date.parse::<u64>().unwrap_or(now);
+            let elapsed_months = calendar::whole_months_between(contributed_at, now);
+            tiers
+                .iter()
+                .find(|tier| elapsed_months < tier.until_month)
+                .map(|tier| tier.rate)
+                .unwrap_or(0.0)
+        }
+        _ => details.early_withdrawal_penalty.unwrap_or(0.0),
+    }
+}
+
+fn penalty_bucket(details: &RevolvingWaqfDetails) -> WaqfBucket {
+    match details.penalty_destination {
+        Some(PenaltyDestination::Causes) => WaqfBucket::Consumable,
+        Some(PenaltyDestination::Permanent) | None => WaqfBucket::Permanent,
+    }
+}
+
+/// Validates the early withdrawal penalty settings of a revolving waqf
+pub fn validate_penalty_settings(details: &RevolvingWaqfDetails) -> std::result::Result<(), String> {
+    if let Some(rate) = details.early_withdrawal_penalty {
+        if !(0.0..=1.0).contains(&rate) {
+            return Err("Early withdrawal penalty must be between 0 and 1".to_string());
+        }
+    }
+
+    if let Some(tiers) = &details.early_withdrawal_penalty_tiers {
+        if tiers.len() > 12 {
+            return Err("At most 12 early withdrawal penalty tiers are allowed".to_string());
+        }
+        let mut previous: Option<&crate::waqf_types::PenaltyTier> = None;
+        for tier in tiers {
+            if !(0.0..=1.0).contains(&tier.rate) {
+                return Err(format!("Penalty tier rate {} must be between 0 and 1", tier.rate));
+            }
+            if tier.until_month == 0 || tier.until_month > 240 {
+                return Err("Penalty tier month must be between 1 and 240".to_string());
+            }
+            if let Some(prev) = previous {
+                if tier.until_month <= prev.until_month {
+                    return Err("Penalty tiers must be ordered by increasing month".to_string());
+                }
+                if tier.rate > prev.rate {
+                    return Err("Penalty tier rates must decline over the lock period".to_string());
+                }
+            }
+            previous = Some(tier);
+        }
+    }
+
+    Ok(())
+}
+
+fn uses_installments(details: &RevolvingWaqfDetails) -> bool {
+    details.principal_return_method == PrincipalReturnMethod::Installments && details.installment_schedule.is_some()
+}
+
+/// Helper function to mark a tranche as returned in a waqf document on the donor's request.
+/// The legacy auto-rollover setting only applies in the maturity sweep, never to an explicit
+/// return. Returns the donor notifications; the caller sends them after saving the waqf.
 pub fn mark_tranche_as_returned(
     waqf: &mut WaqfData,
     tranche_id: &str,
     return_date: u64,
-) -> std::result::Result<(), String> {
+) -> std::result::Result<Vec<Notice>, String> {
     // Check if waqf has revolving details
     let revolving_details = match &mut waqf.revolving_details {
         Some(details) => details,
@@ -87,163 +362,87 @@ pub fn mark_tranche_as_returned(
         }
     };
     
+    let returns_in_installments = uses_installments(revolving_details);
+    
     // Find the tranche
-    let tranche = revolving_details
+    let index = revolving_details
         .contribution_tranches
-        .iter_mut()
-        .find(|t| t.id == tranche_id)
+        .as_ref()
+        .and_then(|tranches| tranches.iter().position(|t| t.id == tranche_id))
         .ok_or_else(|| format!("Tranche {} not found", tranche_id))?;
-    
-    // Check if already returned
-    if tranche.is_returned {
-        return Err("Tranche has already been returned".to_string());
-    }
-    
-    // Check if matured
     let now = ic_cdk::api::time();
-    let maturity_date = tranche.maturity_date
-        .parse::<u64>()
-        .map_err(|_| "Invalid maturity date format")?;
-    
-    let is_early_withdrawal = now < maturity_date;
-    if is_early_withdrawal && !revolving_details.early_withdrawal_allowed {
-        return Err(format!(
-            "Early withdrawals are not allowed for this waqf. Tranche matures at: {}",
-            tranche.maturity_date
-        ));
-    }
+    let (is_early_withdrawal, penalty_rate, owner) = {
+        let tranche = &revolving_details.contribution_tranches.as_ref().unwrap()[index];
+        let owner = tranche.pool_origin.as_ref()
+            .map(|origin| origin.owner.clone())
+            .unwrap_or_else(|| waqf.created_by.clone());
 
-    let penalty_rate = if is_early_withdrawal {
-        revolving_details.early_withdrawal_penalty.unwrap_or(0.0)
-    } else {
-        0.0
+        // Check if already returned (or otherwise settled, e.g. split by a partial withdrawal)
+        if is_settled(tranche) {
+            return Err("Tranche has already been returned, rolled over, converted or split".to_string());
+        }
+
+        // Check if matured
+        let maturity_date = tranche.maturity_date
+            .parse::<u64>()
+            .map_err(|_| "Invalid maturity date format")?;
+
+        // Pooled principal can only be reclaimed once the pool tranche matures
+        let is_pooled = tranche.pool_origin.is_some();
+        let is_early_withdrawal = now < maturity_date;
+        if is_early_withdrawal && (is_pooled || !revolving_details.early_withdrawal_allowed) {
+            return Err(format!(
+                "Early withdrawals are not allowed for this waqf. Tranche matures at: {}",
+                tranche.maturity_date
+            ));
+        }
+
+        let penalty_rate = if is_early_withdrawal {
+            early_withdrawal_penalty_rate(revolving_details, tranche, now)
+        } else {
+            0.0
+        };
+        (is_early_withdrawal, penalty_rate, owner)
     };
+    let penalty_bucket = penalty_bucket(revolving_details);
 
-    let penalty_amount = (tranche.amount * penalty_rate).max(0.0);
+    let revolving_details = waqf.revolving_details.as_mut().unwrap();
+    let tranches = revolving_details.contribution_tranches.as_mut().unwrap();
+    let tranche = &mut tranches[index];
+    let penalty_amount = bucket_accounting::to_cents((tranche.amount * penalty_rate).max(0.0)) as f64 / 100.0;
     let amount_to_return_total = (tranche.amount - penalty_amount).max(0.0);
-    let uses_installments = matches!(
-        revolving_details.principal_return_method.as_str(),
-        "installments"
-    ) && revolving_details.installment_schedule.is_some();
-
     let mut amount_returned_now = amount_to_return_total;
-    let mut tranche_status = "returned".to_string();
+    let mut tranche_status = TrancheStatus::Returned;
     let mut is_returned_flag = true;
     let mut returned_date_value: Option<String> = Some(return_date.to_string());
+    let mut notices: Vec<Notice> = Vec::new();
+    let payload = NotificationPayload {
+        tranche_id: Some(tranche_id.to_string()),
+        amount: Some(amount_to_return_total),
+        penalty: if penalty_amount > 0.0 { Some(penalty_amount) } else { None },
+        reference: tranche.penalty_waiver.as_ref().map(|waiver| waiver.waiver_id.clone()),
+        ..Default::default()
+    };
 
-    if uses_installments {
+    if returns_in_installments {
         if tranche.installment_payments.is_none() {
             if let Some(schedule) = &revolving_details.installment_schedule {
-                let installments = schedule.number_of_installments.max(1);
-                let interval_days = match schedule.frequency.as_str() {
-                    "monthly" => 30_u64,
-                    "quarterly" => 90_u64,
-                    "annually" => 365_u64,
-                    _ => 30_u64,
-                };
-                let interval_nanos =
-                    interval_days * 24 * 60 * 60 * 1_000_000_000;
-                let per_installment_amount = amount_to_return_total / installments as f64;
-
-                let mut payments: Vec<InstallmentPayment> = Vec::new();
-                for index in 0..installments {
-                    let due_date_nanos =
-                        now + interval_nanos.saturating_mul((index + 1) as u64);
-                    payments.push(InstallmentPayment {
-                        id: format!("inst_{}_{}", tranche_id, index + 1),
-                        amount: per_installment_amount,
-                        due_date: due_date_nanos.to_string(),
-                        status: "scheduled".to_string(),
-                        paid_date: None,
-                    });
-                }
-                tranche.installment_payments = Some(payments);
+                tranche.installment_payments =
+                    Some(build_installment_schedule(tranche_id, amount_to_return_total, schedule, now));
             }
         }
 
-        tranche_status = "return_scheduled".to_string();
+        tranche_status = TrancheStatus::ReturnScheduled;
         is_returned_flag = false;
         returned_date_value = None;
         amount_returned_now = 0.0;
 
-        let notification = format!(
-            "Installment schedule created for tranche {}. Total to return: {:.2}",
-            tranche_id, amount_to_return_total
-        );
-        if let Some(ref mut notifications) = revolving_details.pending_notifications {
-            notifications.push(notification);
-        } else {
-            revolving_details.pending_notifications = Some(vec![notification]);
-        }
-    } else if !is_early_withdrawal {
-        if let Some(pref) = &revolving_details.auto_rollover_preference {
-            if pref != "none" {
-                let lock_period_nanos = (revolving_details.lock_period_months as u64)
-                    * 30 * 24 * 60 * 60 * 1_000_000_000;
-                let new_maturity = now + lock_period_nanos;
-                let new_tranche_id =
-                    format!("tranche_rollover_{}_{}", tranche_id, now);
-                let new_tranche = ContributionTranche {
-                    id: new_tranche_id.clone(),
-                    amount: amount_to_return_total,
-                    contribution_date: now.to_string(),
-                    maturity_date: new_maturity.to_string(),
-                    is_returned: false,
-                    returned_date: None,
-                    status: Some("locked".to_string()),
-                    penalty_applied: None,
-                    rollover_origin_id: Some(tranche_id.to_string()),
-                    rollover_target_id: None,
-                    installment_payments: None,
-                    // Preserve expiration preference from original tranche
-                    expiration_preference: tranche.expiration_preference.clone(),
-                    conversion_details: None,
-                };
-
-                if let Some(target_cause) =
-                    &revolving_details.auto_rollover_target_cause
-                {
-                    let notification = format!(
-                        "Matured tranche {} rolled over into {} for cause {}",
-                        tranche_id, new_tranche_id, target_cause
-                    );
-                    if let Some(ref mut notifications) =
-                        revolving_details.pending_notifications
-                    {
-                        notifications.push(notification);
-                    } else {
-                        revolving_details.pending_notifications =
-                            Some(vec![notification]);
-                    }
-                } else {
-                    let notification = format!(
-                        "Matured tranche {} rolled over into {} (strategy: {})",
-                        tranche_id, new_tranche_id, pref
-                    );
-                    if let Some(ref mut notifications) =
-                        revolving_details.pending_notifications
-                    {
-                        notifications.push(notification);
-                    } else {
-                        revolving_details.pending_notifications =
-                            Some(vec![notification]);
-                    }
-                }
-
-                if let Some(ref mut tranches) = revolving_details.contribution_tranches {
-                    tranches.push(new_tranche);
-                } else {
-                    revolving_details.contribution_tranches = Some(vec![new_tranche]);
-                }
-
-                tranche.rollover_target_id = Some(new_tranche_id);
-                tranche_status = "rolled_over".to_string();
-                amount_returned_now = 0.0;
-            }
-        }
+        notices.push(Notice::new(&owner, &waqf.id, NotificationType::RefundScheduled, payload.clone()));
     }
 
     // Mark tranche metadata
+    let tranche_amount = tranche.amount;
+    let tranche_cause = tranche.cause_id.clone();
     tranche.is_returned = is_returned_flag;
     tranche.returned_date = returned_date_value;
     tranche.penalty_applied = if penalty_amount > 0.0 {
@@ -251,34 +450,694 @@ pub fn mark_tranche_as_returned(
     } else {
         None
     };
-    tranche.status = Some(tranche_status.clone());
+    tranche.status = Some(tranche_status);
 
     if is_early_withdrawal {
-        let notification = format!(
-            "Early withdrawal processed for tranche {}. Penalty applied: {:.2}",
-            tranche_id, penalty_amount
-        );
-        if let Some(ref mut notifications) = revolving_details.pending_notifications {
-            notifications.push(notification);
-        } else {
-            revolving_details.pending_notifications = Some(vec![notification]);
-        }
+        notices.push(Notice::new(&owner, &waqf.id, NotificationType::EarlyWithdrawal, payload));
+    } else if !returns_in_installments {
+        notices.push(Notice::new(&owner, &waqf.id, NotificationType::TrancheReturned, payload));
     }
 
     // Update waqf financial state
+    if amount_returned_now > 0.0 || penalty_amount > 0.0 {
+        bucket_accounting::ensure_bucket_balances(waqf);
+    }
+    if amount_returned_now > 0.0 {
+        bucket_accounting::release_revolving(&mut waqf.financial, tranche_cause.as_deref(), None, amount_returned_now)?;
+    }
+    // The penalty stays in the waqf: it leaves the donor's revolving principal and
+    // is credited to the permanent endowment or the causes' consumable funds
+    if penalty_amount > 0.0 {
+        bucket_accounting::release_revolving(&mut waqf.financial, tranche_cause.as_deref(), Some(penalty_bucket), penalty_amount)?;
+        waqf.financial.penalties_collected += penalty_amount;
+    }
     waqf.financial.current_balance =
         (waqf.financial.current_balance - amount_returned_now).max(0.0);
+    waqf.financial.principal_released += amount_returned_now;
 
     ic_cdk::println!(
         "SUCCESS: Tranche {} (${:.2}) processed for waqf {}. Returned: ${:.2}, Penalty: ${:.2}",
         tranche_id,
-        tranche.amount,
+        tranche_amount,
         waqf.id,
         amount_returned_now,
         penalty_amount
     );
 
-    Ok(())
+    Ok(notices)
+}
+
+/// Rollover preference implied by the waqf-level auto-rollover setting, if enabled
+fn legacy_rollover_preference(details: &RevolvingWaqfDetails) -> Option<TrancheExpirationPreference> {
+    let pref = details.auto_rollover_preference.filter(|pref| *pref != AutoRolloverPreference::None)?;
+
+    Some(TrancheExpirationPreference {
+        action: ExpirationAction::Rollover,
+        rollover_months: Some(details.lock_period_months),
+        rollover_cause_id: details.auto_rollover_target_cause.clone(),
+        rollover_target: if pref == AutoRolloverPreference::CausePool { Some(RolloverTarget::CausePool) } else { None },
+        consumable_schedule: None,
+        consumable_duration: None,
+    })
+}
+
+/// Roll a matured tranche over for another lock period. Cause pool rollovers release the
+/// principal from this waqf and return it as a PooledRollover for the caller to credit;
+/// other rollovers stay in this waqf, re-pointed to the rollover cause when one is set.
+fn rollover_tranche(
+    waqf: &mut WaqfData,
+    index: usize,
+    preference: &TrancheExpirationPreference,
+    now: u64,
+) -> std::result::Result<(Notice, Option<PooledRollover>), String> {
+    let details = waqf.revolving_details.as_ref().ok_or("Waqf is not a revolving waqf")?;
+    let tranche = details.contribution_tranches.as_ref()
+        .map(|tranches| tranches[index].clone())
+        .ok_or("Waqf has no contribution tranches")?;
+    let months = preference.rollover_months.unwrap_or(details.lock_period_months);
+    let maturity_date = calendar::add_months(now, months);
+    let new_tranche_id = format!("tranche_rollover_{}_{}", tranche.id, now);
+    // Funded or deactivated causes no longer take new principal
+    let target_cause = preference.rollover_cause_id.clone()
+        .filter(|cause_id| cause_hooks::load_cause(cause_id).is_ok_and(|cause| cause.is_active));
+    let cause_id = target_cause.clone().or_else(|| tranche.cause_id.clone());
+
+    let wants_pool = preference.rollover_target == Some(RolloverTarget::CausePool);
+    // A pool tranche rolling over again simply stays in the pool
+    let pool_waqf_id = if wants_pool {
+        cause_pool_hooks::configured_pool_waqf_id().filter(|pool_id| *pool_id != waqf.id)
+    } else {
+        None
+    };
+
+    let mut new_tranche = ContributionTranche {
+        id: new_tranche_id.clone(),
+        amount: tranche.amount,
+        contribution_date: now.to_string(),
+        maturity_date: maturity_date.to_string(),
+        is_returned: false,
+        returned_date: None,
+        status: Some(TrancheStatus::Locked),
+        penalty_applied: None,
+        rollover_origin_id: Some(tranche.id.clone()),
+        rollover_target_id: None,
+        installment_payments: None,
+        expiration_preference: tranche.expiration_preference.clone(),
+        conversion_details: None,
+        matured_at: None,
+        penalty_waiver: None,
+        cause_id: cause_id.clone(),
+        rollover_target_waqf_id: None,
+        pool_origin: tranche.pool_origin.clone(),
+        currency: tranche.currency.clone(),
+        split_from: None,
+        split_into: None,
+        expiration_failure: None,
+    };
+
+    bucket_accounting::ensure_bucket_balances(waqf);
+
+    let owner = notification_hooks::tranche_owner(waqf, &tranche);
+    let mut payload = NotificationPayload {
+        tranche_id: Some(tranche.id.clone()),
+        amount: Some(tranche.amount),
+        months: Some(months),
+        date: Some(maturity_date.to_string()),
+        new_tranche_id: Some(new_tranche_id.clone()),
+        cause_id: cause_id.clone(),
+        ..Default::default()
+    };
+
+    let (pooled, kept_tranche) = match pool_waqf_id {
+        Some(pool_waqf_id) => {
+            // Keep the donor's causes: the rollover cause, else the causes the principal backed here
+            let cause_split = match &cause_id {
+                Some(cause_id) => HashMap::from([(cause_id.clone(), tranche.amount)]),
+                None => bucket_accounting::split_by_cause(waqf, tranche.amount),
+            };
+
+            bucket_accounting::release_revolving(&mut waqf.financial, tranche.cause_id.as_deref(), None, tranche.amount)?;
+            waqf.financial.current_balance = (waqf.financial.current_balance - tranche.amount).max(0.0);
+            waqf.financial.principal_released += tranche.amount;
+
+            // The original donor keeps ownership of the principal
+            new_tranche.pool_origin = Some(PoolOrigin {
+                waqf_id: waqf.id.clone(),
+                tranche_id: tranche.id.clone(),
+                owner: waqf.created_by.clone(),
+                pooled_at: now.to_string(),
+            });
+
+            // The donor keeps ownership and can reclaim the principal when the pool tranche matures
+            payload.related_waqf_id = Some(pool_waqf_id.clone());
+            (Some(PooledRollover { pool_waqf_id, tranche: new_tranche, cause_split }), None)
+        }
+        None => {
+            if let Some(target_cause) = &target_cause {
+                if tranche.cause_id.as_ref() != Some(target_cause) {
+                    bucket_accounting::repoint_revolving(
+                        &mut waqf.financial,
+                        tranche.cause_id.as_deref(),
+                        target_cause,
+                        tranche.amount,
+                    )?;
+                }
+            }
+
+            if target_cause.is_none() && preference.rollover_cause_id.is_some() {
+                payload.reason = Some("rollover_cause_inactive".to_string());
+            }
+            if wants_pool && tranche.pool_origin.is_none() {
+                ic_cdk::println!("WARN - No cause pool is configured; tranche {} rolled over in waqf {}", tranche.id, waqf.id);
+                payload.reason = Some("cause_pool_unavailable".to_string());
+            }
+            (None, Some(new_tranche))
+        }
+    };
+
+    let tranches = waqf.revolving_details.as_mut()
+        .and_then(|d| d.contribution_tranches.as_mut())
+        .ok_or("Waqf has no contribution tranches")?;
+    let rolled = &mut tranches[index];
+    rolled.rollover_target_id = Some(new_tranche_id);
+    rolled.rollover_target_waqf_id = pooled.as_ref().map(|p| p.pool_waqf_id.clone());
+    rolled.status = Some(TrancheStatus::RolledOver);
+    tranches.extend(kept_tranche);
+
+    ic_cdk::println!(
+        "Rolled over tranche {} of waqf {} ({:.2}) for {} months{}",
+        tranche.id,
+        waqf.id,
+        tranche.amount,
+        months,
+        pooled.as_ref().map(|p| format!(" into cause pool {}", p.pool_waqf_id)).unwrap_or_default()
+    );
+
+    Ok((Notice::new(&owner, &waqf.id, NotificationType::RolloverDone, payload), pooled))
+}
+
+// ============================================
+// MATURITY SWEEP
+// ============================================
+
+/// Preference to carry out for a matured tranche: its own, else the waqf default,
+/// else the legacy auto-rollover setting (rollover for the waqf lock period, or a refund)
+pub fn effective_expiration_preference(
+    tranche: &ContributionTranche,
+    details: &RevolvingWaqfDetails,
+) -> TrancheExpirationPreference {
+    if let Some(pref) = tranche.expiration_preference.as_ref().or(details.default_expiration_preference.as_ref()) {
+        return pref.clone();
+    }
+
+    legacy_rollover_preference(details).unwrap_or(TrancheExpirationPreference {
+        action: ExpirationAction::Refund,
+        rollover_months: None,
+        rollover_cause_id: None,
+        rollover_target: None,
+        consumable_schedule: None,
+        consumable_duration: None,
+    })
+}
+
+pub fn is_settled(tranche: &ContributionTranche) -> bool {
+    tranche.is_returned
+        || tranche.conversion_details.is_some()
+        || match tranche.status {
+            Some(TrancheStatus::Returned)
+            | Some(TrancheStatus::RolledOver)
+            | Some(TrancheStatus::ReturnScheduled)
+            | Some(TrancheStatus::Converted)
+            | Some(TrancheStatus::Split) => true,
+            Some(TrancheStatus::Locked) | Some(TrancheStatus::Matured) | None => false,
+        }
+}
+
+/// Carry out a matured tranche's expiration preference
+fn execute_preference(
+    waqf: &mut WaqfData,
+    index: usize,
+    preference: &TrancheExpirationPreference,
+    now: u64,
+) -> std::result::Result<(Notice, Option<PooledRollover>), String> {
+    match preference.action {
+        ExpirationAction::ConvertPermanent | ExpirationAction::ConvertConsumable => {
+            return convert_tranche(waqf, index, preference, now).map(|notice| (notice, None));
+        }
+        ExpirationAction::Rollover => return rollover_tranche(waqf, index, preference, now),
+        ExpirationAction::Refund => {}
+    }
+
+    let WaqfData { id: waqf_id, created_by, financial, revolving_details, .. } = waqf;
+    let details = revolving_details.as_mut().ok_or("Waqf is not a revolving waqf")?;
+    let installments = uses_installments(details);
+    let tranches = details.contribution_tranches.as_mut().unwrap();
+    let tranche = &mut tranches[index];
+    let tranche_id = tranche.id.clone();
+    let owner = tranche.pool_origin.as_ref().map(|origin| origin.owner.clone()).unwrap_or_else(|| created_by.clone());
+    let payload = NotificationPayload {
+        tranche_id: Some(tranche_id.clone()),
+        amount: Some(tranche.amount),
+        ..Default::default()
+    };
+
+    if installments {
+        if let Some(schedule) = details.installment_schedule.as_ref() {
+            tranche.installment_payments =
+                Some(build_installment_schedule(&tranche_id, tranche.amount, schedule, now));
+        }
+        tranche.status = Some(TrancheStatus::ReturnScheduled);
+        Ok((Notice::new(&owner, waqf_id, NotificationType::RefundScheduled, payload), None))
+    } else {
+        bucket_accounting::release_revolving(financial, tranche.cause_id.as_deref(), None, tranche.amount)?;
+        financial.current_balance = (financial.current_balance - tranche.amount).max(0.0);
+        financial.principal_released += tranche.amount;
+        tranche.is_returned = true;
+        tranche.returned_date = Some(now.to_string());
+        tranche.status = Some(TrancheStatus::Returned);
+        Ok((Notice::new(&owner, waqf_id, NotificationType::TrancheReturned, payload), None))
+    }
+}
+
+/// Target waqf for a converted tranche. It inherits the donor, causes and allocation of the
+/// source waqf and links back to it through converted_from.
+fn build_converted_waqf(
+    source: &WaqfData,
+    tranche: &ContributionTranche,
+    preference: &TrancheExpirationPreference,
+    now: u64,
+) -> WaqfData {
+    let to_permanent = preference.action == ExpirationAction::ConvertPermanent;
+
+    let consumable_details = if to_permanent {
+        None
+    } else {
+        let duration = preference.consumable_duration.unwrap_or(DEFAULT_CONSUMABLE_DURATION_MONTHS).max(1);
+        let schedule = match preference.consumable_schedule {
+            // Milestones cannot be derived from the tranche, so spend it in phases instead
+            Some(SpendingSchedule::MilestoneBased) | None => SpendingSchedule::Phased,
+            Some(schedule) => schedule,
+        };
+        let monthly = (tranche.amount / duration as f64 * 100.0).round() / 100.0;
+
+        Some(ConsumableWaqfDetails {
+            spending_schedule: schedule,
+            start_date: Some(now.to_string()),
+            end_date: Some(calendar::add_months(now, duration).to_string()),
+            target_amount: Some(tranche.amount),
+            target_beneficiaries: None,
+            milestones: None,
+            minimum_monthly_distribution: if monthly > 0.0 { Some(monthly) } else { None },
+        })
+    };
+
+    let name = format!("{} (converted)", source.name);
+
+    WaqfData {
+        id: format!("waqf_conv_{}", tranche.id),
+        name: if name.len() > 100 { source.name.clone() } else { name },
+        description: format!(
+            "Converted from matured tranche {} of waqf {} ({})",
+            tranche.id, source.id, source.name
+        ),
+        waqf_asset: tranche.amount,
+        donor: source.donor.clone(),
+        selected_causes: source.selected_causes.clone(),
+        cause_allocation: source.cause_allocation.clone(),
+        status: WaqfStatus::Active,
+        is_donated: source.is_donated,
+        notifications: source.notifications.clone(),
+        reporting_preferences: source.reporting_preferences.clone(),
+        financial: FinancialMetrics {
+            total_donations: tranche.amount,
+            current_balance: tranche.amount,
+            ..Default::default()
+        },
+        waqf_type: if to_permanent { WaqfType::Permanent } else { WaqfType::TemporaryConsumable },
+        is_hybrid: false,
+        hybrid_allocations: None,
+        consumable_details,
+        revolving_details: None,
+        investment_strategy: if to_permanent { source.investment_strategy.clone() } else { None },
+        deed_document: None,
+        created_by: source.created_by.clone(),
+        created_at: now.to_string(),
+        updated_at: None,
+        last_contribution_date: None,
+        next_contribution_date: None,
+        next_report_date: None,
+        causes_pending_reassignment: None,
+        deed_reference: None,
+        trustees: Vec::new(),
+        template: None,
+        converted_from: Some(ConversionOrigin {
+            waqf_id: source.id.clone(),
+            tranche_id: tranche.id.clone(),
+            converted_at: now.to_string(),
+        }),
+    }
+}
+
+/// Move a matured tranche into a new permanent or consumable waqf
+fn convert_tranche(
+    waqf: &mut WaqfData,
+    index: usize,
+    preference: &TrancheExpirationPreference,
+    now: u64,
+) -> std::result::Result<Notice, String> {
+    let tranche = waqf.revolving_details.as_ref().and_then(|d| d.contribution_tranches.as_ref())
+        .map(|tranches| tranches[index].clone())
+        .ok_or("Waqf is not a revolving waqf")?;
+
+    validate_tranche_conversion(&tranche, waqf)?;
+
+    let target = build_converted_waqf(waqf, &tranche, preference, now);
+    let target_type = if preference.action == ExpirationAction::ConvertPermanent {
+        ConversionTarget::Permanent
+    } else {
+        ConversionTarget::TemporaryConsumable
+    };
+
+    // Checked before the target is written so an unbacked tranche creates nothing
+    bucket_accounting::release_revolving(&mut waqf.financial, tranche.cause_id.as_deref(), None, tranche.amount)?;
+
+    // A previous sweep may have created the target before the source could be saved
+    if get_doc("waqfs".to_string(), target.id.clone()).is_none() {
+        let data = encode_doc_data(&target)
+            .map_err(|e| format!("Failed to encode converted waqf data: {}", e))?;
+
+        let set_doc_data = SetDoc {
+            data,
+            description: None,
+            version: None,
+        };
+
+        let _ = set_doc("waqfs".to_string(), target.id.clone(), set_doc_data);
+    }
+
+    waqf.financial.current_balance = (waqf.financial.current_balance - tranche.amount).max(0.0);
+    waqf.financial.principal_released += tranche.amount;
+
+    if let Some(converted) = waqf.revolving_details.as_mut()
+        .and_then(|d| d.contribution_tranches.as_mut())
+        .map(|tranches| &mut tranches[index])
+    {
+        converted.conversion_details = Some(ConversionDetails {
+            converted_at: now.to_string(),
+            new_waqf_id: target.id.clone(),
+            target_waqf_type: target_type,
+            notes: Some(format!("Converted at maturity from waqf {}", waqf.id)),
+        });
+        converted.status = Some(TrancheStatus::Converted);
+    }
+
+    ic_cdk::println!(
+        "Converted tranche {} of waqf {} into {} waqf {} ({:.2})",
+        tranche.id, waqf.id, target_type, target.id, tranche.amount
+    );
+
+    Ok(Notice::new(
+        &notification_hooks::tranche_owner(waqf, &tranche),
+        &waqf.id,
+        NotificationType::TrancheConverted,
+        NotificationPayload {
+            tranche_id: Some(tranche.id.clone()),
+            amount: Some(tranche.amount),
+            related_waqf_id: Some(target.id.clone()),
+            target_waqf_type: Some(target_type),
+            ..Default::default()
+        },
+    ))
+}
+
+/// Work left for after the swept waqfs have been saved
+#[derive(Default)]
+pub struct SweepEffects {
+    pub pooled: Vec<PooledRollover>,
+    pub notices: Vec<Notice>,
+}
+
+fn tranche_at_mut(waqf: &mut WaqfData, index: usize) -> Option<&mut ContributionTranche> {
+    waqf.revolving_details.as_mut()
+        .and_then(|details| details.contribution_tranches.as_mut())
+        .and_then(|tranches| tranches.get_mut(index))
+}
+
+/// Record a failed expiration on the tranche so the sweep stops retrying (and re-notifying) it
+fn record_expiration_failure(waqf: &mut WaqfData, index: usize, preference: &TrancheExpirationPreference, reason: &str, now: u64) {
+    if let Some(tranche) = tranche_at_mut(waqf, index) {
+        tranche.expiration_failure = Some(ExpirationFailure {
+            failed_at: now.to_string(),
+            reason: reason.to_string(),
+            preference: preference.clone(),
+        });
+    }
+}
+
+/// Mark matured tranches and carry out expiration preferences once the grace period has passed.
+/// Cause pool rollovers and donor notifications are added to `effects`. Returns whether the waqf was changed.
+pub fn process_waqf_tranches(waqf: &mut WaqfData, now: u64, effects: &mut SweepEffects) -> bool {
+    let tranche_count = match waqf.revolving_details.as_ref().and_then(|d| d.contribution_tranches.as_ref()) {
+        Some(tranches) => tranches.len(),
+        None => return false,
+    };
+
+    bucket_accounting::ensure_bucket_balances(waqf);
+    let terminated = waqf.status == WaqfStatus::Terminated;
+
+    let mut changed = false;
+    for index in 0..tranche_count {
+        let owner = waqf.revolving_details.as_ref()
+            .and_then(|d| d.contribution_tranches.as_ref())
+            .map(|tranches| notification_hooks::tranche_owner(waqf, &tranches[index]))
+            .unwrap_or_default();
+        let details = waqf.revolving_details.as_mut().unwrap();
+        let preference = effective_expiration_preference(&details.contribution_tranches.as_ref().unwrap()[index], details);
+        let tranche = &mut details.contribution_tranches.as_mut().unwrap()[index];
+
+        if is_settled(tranche) {
+            continue;
+        }
+
+        // A failed expiration waits for a new preference or for staff to clear the failure. Tranches a
+        // termination could not settle keep the failure until staff clear it.
+        if tranche.expiration_failure.as_ref().is_some_and(|failure| failure.preference == preference || terminated) {
+            continue;
+        }
+
+        let maturity_date = match tranche.maturity_date.parse::<u64>() {
+            Ok(date) => date,
+            Err(_) => {
+                ic_cdk::println!("WARN - Tranche {} of waqf {} has an invalid maturity date", tranche.id, waqf.id);
+                continue;
+            }
+        };
+
+        if now < maturity_date {
+            continue;
+        }
+
+        let matured_at = match tranche.matured_at.as_ref().and_then(|at| at.parse::<u64>().ok()) {
+            Some(at) => at,
+            None => {
+                tranche.status = Some(TrancheStatus::Matured);
+                tranche.matured_at = Some(now.to_string());
+                // The preference is carried out at the end of the grace period unless the donor changes it
+                effects.notices.push(Notice::new(
+                    &owner,
+                    &waqf.id,
+                    NotificationType::TrancheMatured,
+                    NotificationPayload {
+                        tranche_id: Some(tranche.id.clone()),
+                        amount: Some(tranche.amount),
+                        date: Some((now + EXPIRATION_GRACE_PERIOD_NANOS).to_string()),
+                        ..Default::default()
+                    },
+                ));
+                changed = true;
+                continue;
+            }
+        };
+
+        if now < matured_at + EXPIRATION_GRACE_PERIOD_NANOS {
+            continue;
+        }
+
+        let tranche_id = tranche.id.clone();
+        let notice = match execute_preference(waqf, index, &preference, now) {
+            Ok((notice, pooled_rollover)) => {
+                effects.pooled.extend(pooled_rollover);
+                if let Some(tranche) = tranche_at_mut(waqf, index) {
+                    tranche.expiration_failure = None;
+                }
+                notice
+            }
+            Err(e) => {
+                ic_cdk::println!("ERROR - Expiration of tranche {} failed for waqf {}: {}", tranche_id, waqf.id, e);
+                record_expiration_failure(waqf, index, &preference, &e, now);
+                Notice::new(
+                    &owner,
+                    &waqf.id,
+                    NotificationType::ExpirationFailed,
+                    NotificationPayload {
+                        tranche_id: Some(tranche_id),
+                        reason: Some(e),
+                        ..Default::default()
+                    },
+                )
+            }
+        };
+
+        ic_cdk::println!("Waqf {}: {:?} for tranche {:?}", waqf.id, notice.notification_type, notice.payload.tranche_id);
+        effects.notices.push(notice);
+        changed = true;
+    }
+
+    changed
+}
+
+/// Tranches of a waqf being terminated, once wound down
+#[derive(Default)]
+pub struct TerminationOutcome {
+    pub settled: Vec<String>,
+    pub unsettled: Vec<String>,
+    pub accelerated_installments: u32,
+}
+
+/// Settle every open tranche of a waqf that is being terminated: locked tranches mature now and
+/// each expiration preference is carried out at once, without grace period or penalty. Principal
+/// cannot roll over inside a terminated waqf, so rollovers that would stay here are refunded.
+/// Installments still scheduled fall due at the termination date.
+pub fn settle_tranches_for_termination(waqf: &mut WaqfData, now: u64, effects: &mut SweepEffects) -> TerminationOutcome {
+    let mut outcome = TerminationOutcome::default();
+    let tranche_count = match waqf.revolving_details.as_ref().and_then(|d| d.contribution_tranches.as_ref()) {
+        Some(tranches) => tranches.len(),
+        None => return outcome,
+    };
+
+    bucket_accounting::ensure_bucket_balances(waqf);
+    let pool_available = cause_pool_hooks::configured_pool_waqf_id().is_some_and(|pool_id| pool_id != waqf.id);
+
+    for index in 0..tranche_count {
+        let details = waqf.revolving_details.as_mut().unwrap();
+        let mut preference = effective_expiration_preference(&details.contribution_tranches.as_ref().unwrap()[index], details);
+        let tranche = &mut details.contribution_tranches.as_mut().unwrap()[index];
+
+        if is_settled(tranche) {
+            continue;
+        }
+
+        if tranche.maturity_date.parse::<u64>().map_or(true, |date| date > now) {
+            tranche.maturity_date = now.to_string();
+        }
+        tranche.status = Some(TrancheStatus::Matured);
+        if tranche.matured_at.is_none() {
+            tranche.matured_at = Some(now.to_string());
+        }
+        let tranche_id = tranche.id.clone();
+
+        let to_pool = preference.rollover_target == Some(RolloverTarget::CausePool) && pool_available;
+        if preference.action == ExpirationAction::Rollover && !to_pool {
+            preference = TrancheExpirationPreference {
+                action: ExpirationAction::Refund,
+                rollover_months: None,
+                rollover_cause_id: None,
+                rollover_target: None,
+                consumable_schedule: None,
+                consumable_duration: None,
+            };
+        }
+
+        match execute_preference(waqf, index, &preference, now) {
+            Ok((notice, pooled_rollover)) => {
+                effects.pooled.extend(pooled_rollover);
+                effects.notices.push(notice);
+                outcome.settled.push(tranche_id);
+            }
+            Err(e) => {
+                ic_cdk::println!("ERROR - Tranche {} of terminated waqf {} could not be settled: {}", tranche_id, waqf.id, e);
+                record_expiration_failure(waqf, index, &preference, &e, now);
+                let owner = waqf.revolving_details.as_ref()
+                    .and_then(|d| d.contribution_tranches.as_ref())
+                    .map(|tranches| notification_hooks::tranche_owner(waqf, &tranches[index]))
+                    .unwrap_or_default();
+                effects.notices.push(Notice::new(
+                    &owner,
+                    &waqf.id,
+                    NotificationType::ExpirationFailed,
+                    NotificationPayload {
+                        tranche_id: Some(tranche_id.clone()),
+                        reason: Some(e),
+                        ..Default::default()
+                    },
+                ));
+                outcome.unsettled.push(tranche_id);
+            }
+        }
+    }
+
+    let now_text = now.to_string();
+    let tranches = waqf.revolving_details.as_mut().and_then(|d| d.contribution_tranches.as_mut());
+    for tranche in tranches.into_iter().flatten() {
+        for installment in tranche.installment_payments.iter_mut().flatten() {
+            if installment.status == InstallmentStatus::Scheduled
+                && installment.due_date.parse::<u64>().map_or(true, |date| date > now)
+            {
+                installment.due_date = now_text.clone();
+                outcome.accelerated_installments += 1;
+            }
+        }
+    }
+
+    outcome
+}
+
+/// Scheduled job: run the tranche maturity sweep across revolving and hybrid waqfs
+pub fn process_matured_tranches() {
+    scheduler::sweep_waqfs(process_tranche_page);
+}
+
+fn process_tranche_page(waqfs: Vec<(String, Doc)>) {
+    let now = ic_cdk::api::time();
+    let mut effects = SweepEffects::default();
+
+    for (key, doc) in waqfs {
+        let mut waqf: WaqfData = match decode_doc_data(&doc.data) {
+            Ok(waqf) => waqf,
+            Err(e) => {
+                ic_cdk::println!("WARN - Skipping waqf {} in tranche sweep: {}", key, e);
+                continue;
+            }
+        };
+
+        if !process_waqf_tranches(&mut waqf, now, &mut effects) {
+            continue;
+        }
+
+        waqf.updated_at = Some(now.to_string());
+        match encode_doc_data(&waqf) {
+            Ok(data) => {
+                let set_doc_data = SetDoc {
+                    data,
+                    description: doc.description,
+                    version: doc.version,
+                };
+                let _ = set_doc("waqfs".to_string(), key.clone(), set_doc_data);
+            }
+            Err(e) => ic_cdk::println!("ERROR - Cannot encode waqf {}: {}", key, e),
+        }
+    }
+
+    // Credited after the page so the pool waqf is not overwritten by its own stale copy
+    for rollover in effects.pooled {
+        if let Err(e) = cause_pool_hooks::credit_cause_pool(&rollover) {
+            ic_cdk::println!(
+                "ERROR - Cannot credit cause pool {} with tranche {}: {}",
+                rollover.pool_waqf_id, rollover.tranche.id, e
+            );
+        }
+    }
+    notification_hooks::send_all(effects.notices);
 }
 
 /// Validate expiration preference settings
@@ -304,6 +1163,10 @@ pub fn validate_expiration_preference(
             } else {
                 return Err("Rollover action requires rollover_months to be specified".to_string());
             }
+
+            if let Some(ref cause_id) = preference.rollover_cause_id {
+                cause_hooks::load_cause(cause_id)?;
+            }
             Ok(())
         },
         ExpirationAction::ConvertPermanent => {
@@ -312,14 +1175,6 @@ pub fn validate_expiration_preference(
             Ok(())
         },
         ExpirationAction::ConvertConsumable => {
-            // Validate consumable schedule if present
-            if let Some(ref schedule) = preference.consumable_schedule {
-                match schedule.as_str() {
-                    "immediate" | "phased" | "milestone-based" | "ongoing" => {},
-                    _ => return Err(format!("Invalid consumable schedule: {}", schedule)),
-                }
-            }
-            
             // Validate consumable duration if present
             if let Some(duration) = preference.consumable_duration {
                 if duration < 1 {
@@ -335,6 +1190,19 @@ pub fn validate_expiration_preference(
     }
 }
 
+/// Validates the auto-rollover and default expiration settings of a revolving waqf
+pub fn validate_rollover_settings(details: &RevolvingWaqfDetails) -> std::result::Result<(), String> {
+    if let Some(ref cause_id) = details.auto_rollover_target_cause {
+        cause_hooks::load_cause(cause_id)?;
+    }
+
+    if let Some(ref preference) = details.default_expiration_preference {
+        validate_expiration_preference(preference)?;
+    }
+
+    Ok(())
+}
+
 /// Validate that a tranche can be converted
 pub fn validate_tranche_conversion(
     tranche: &ContributionTranche,
@@ -351,10 +1219,8 @@ pub fn validate_tranche_conversion(
     }
     
     // Check if tranche is rolled over
-    if let Some(ref status) = tranche.status {
-        if status == "rolled_over" {
-            return Err("Cannot convert a rolled-over tranche".to_string());
-        }
+    if tranche.status == Some(TrancheStatus::RolledOver) {
+        return Err("Cannot convert a rolled-over tranche".to_string());
     }
     
     // Check if tranche has matured
@@ -400,10 +1266,8 @@ pub fn validate_tranche_rollover(
     }
     
     // Check if tranche has already been rolled over
-    if let Some(ref status) = tranche.status {
-        if status == "rolled_over" {
-            return Err("Tranche has already been rolled over".to_string());
-        }
+    if tranche.status == Some(TrancheStatus::RolledOver) {
+        return Err("Tranche has already been rolled over".to_string());
     }
     
     // Check if tranche has matured
@@ -434,7 +1298,12 @@ pub fn validate_tranche_rollover(
 }
 
 /// Validate that all required fields are present in revolving waqf tranches
-pub fn validate_tranche_data(tranche: &ContributionTranche) -> std::result::Result<(), String> {
+/// Validates a tranche. `tranches` holds every tranche of the waqf and is used to check that
+/// the portions of a partial withdrawal add up to the tranche they were split from.
+pub fn validate_tranche_data(
+    tranche: &ContributionTranche,
+    tranches: &[ContributionTranche],
+) -> std::result::Result<(), String> {
     if tranche.id.is_empty() {
         return Err("Tranche ID cannot be empty".to_string());
     }
@@ -468,15 +1337,6 @@ pub fn validate_tranche_data(tranche: &ContributionTranche) -> std::result::Resu
         }
     }
 
-    if let Some(ref status) = tranche.status {
-        match status.as_str() {
-            "locked" | "matured" | "return_scheduled" | "returned" | "rolled_over" => {}
-            other => {
-                return Err(format!("Invalid tranche status: {}", other));
-            }
-        }
-    }
-
     if let Some(penalty) = tranche.penalty_applied {
         if penalty < 0.0 {
             return Err("Penalty applied cannot be negative".to_string());
@@ -505,16 +1365,44 @@ pub fn validate_tranche_data(tranche: &ContributionTranche) -> std::result::Resu
                         .map_err(|_| "Invalid installment payment paid date format")?;
                 }
             }
-
-            match payment.status.as_str() {
-                "scheduled" | "paid" | "missed" => {}
-                other => {
-                    return Err(format!("Invalid installment payment status: {}", other));
-                }
-            }
         }
     }
     
+    // A split parent is fully carried by its portions
+    if let Some(ref children) = tranche.split_into {
+        if tranche.status != Some(TrancheStatus::Split) {
+            return Err("Only split tranches can list split portions".to_string());
+        }
+        if children.len() != 2 {
+            return Err("A split tranche must have a withdrawn and a remaining portion".to_string());
+        }
+        let mut child_cents: u64 = 0;
+        for child_id in children {
+            let child = tranches.iter().find(|t| &t.id == child_id)
+                .ok_or_else(|| format!("Split portion {} not found", child_id))?;
+            if child.split_from.as_deref() != Some(tranche.id.as_str()) {
+                return Err(format!("Split portion {} does not reference tranche {}", child_id, tranche.id));
+            }
+            child_cents += bucket_accounting::to_cents(child.amount);
+        }
+        if child_cents != bucket_accounting::to_cents(tranche.amount) {
+            return Err(format!(
+                "Split portions of tranche {} add up to {:.2} instead of {:.2}",
+                tranche.id, child_cents as f64 / 100.0, tranche.amount
+            ));
+        }
+    } else if tranche.status == Some(TrancheStatus::Split) {
+        return Err("Split tranches must list their split portions".to_string());
+    }
+
+    if let Some(ref parent_id) = tranche.split_from {
+        let parent = tranches.iter().find(|t| &t.id == parent_id)
+            .ok_or_else(|| format!("Split parent {} not found", parent_id))?;
+        if !parent.split_into.as_ref().is_some_and(|children| children.contains(&tranche.id)) {
+            return Err(format!("Tranche {} is not a split portion of {}", tranche.id, parent_id));
+        }
+    }
+
     // Validate expiration preference if present
     if let Some(ref pref) = tranche.expiration_preference {
         validate_expiration_preference(pref)?;
@@ -529,16 +1417,124 @@ pub fn validate_tranche_data(tranche: &ContributionTranche) -> std::result::Resu
         if conversion.new_waqf_id.is_empty() {
             return Err("New waqf ID cannot be empty in conversion details".to_string());
         }
-        
-        if conversion.target_waqf_type.is_empty() {
-            return Err("Target waqf type cannot be empty in conversion details".to_string());
-        }
-        
-        match conversion.target_waqf_type.as_str() {
-            "permanent" | "temporary_consumable" => {},
-            other => return Err(format!("Invalid target waqf type in conversion: {}", other)),
-        }
+
     }
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::waqf_types::{PenaltyTier, PenaltyWaiver};
+
+    // 31 January 2024, 00:00 UTC
+    const CONTRIBUTED_AT: u64 = 19_753 * NANOS_PER_DAY;
+
+    fn details(flat_rate: Option<f64>, tiers: Option<Vec<(u32, f64)>>) -> RevolvingWaqfDetails {
+        RevolvingWaqfDetails {
+            lock_period_months: 12,
+            maturity_date: calendar::add_months(CONTRIBUTED_AT, 12).to_string(),
+            principal_return_method: PrincipalReturnMethod::LumpSum,
+            installment_schedule: None,
+            early_withdrawal_penalty: flat_rate,
+            early_withdrawal_allowed: true,
+            contribution_tranches: None,
+            auto_rollover_preference: None,
+            auto_rollover_target_cause: None,
+            pending_notifications: None,
+            default_expiration_preference: None,
+            early_withdrawal_penalty_tiers: tiers
+                .map(|tiers| tiers.into_iter().map(|(until_month, rate)| PenaltyTier { until_month, rate }).collect()),
+            penalty_destination: None,
+        }
+    }
+
+    fn tranche() -> ContributionTranche {
+        ContributionTranche {
+            id: "tranche_1".into(),
+            amount: 1_000.0,
+            contribution_date: CONTRIBUTED_AT.to_string(),
+            maturity_date: calendar::add_months(CONTRIBUTED_AT, 12).to_string(),
+            is_returned: false,
+            returned_date: None,
+            status: Some(TrancheStatus::Locked),
+            penalty_applied: None,
+            rollover_origin_id: None,
+            rollover_target_id: None,
+            installment_payments: None,
+            expiration_preference: None,
+            conversion_details: None,
+            matured_at: None,
+            penalty_waiver: None,
+            cause_id: None,
+            rollover_target_waqf_id: None,
+            pool_origin: None,
+            currency: None,
+            split_from: None,
+            split_into: None,
+            expiration_failure: None,
+        }
+    }
+
+    fn months_after_contribution(months: u32) -> u64 {
+        calendar::add_months(CONTRIBUTED_AT, months)
+    }
+
+    #[test]
+    fn penalty_tiers_decline_with_elapsed_months() {
+        let details = details(Some(0.2), Some(vec![(3, 0.1), (6, 0.05)]));
+        let tranche = tranche();
+
+        assert_eq!(early_withdrawal_penalty_rate(&details, &tranche, CONTRIBUTED_AT), 0.1);
+        assert_eq!(early_withdrawal_penalty_rate(&details, &tranche, months_after_contribution(3) - 1), 0.1);
+        assert_eq!(early_withdrawal_penalty_rate(&details, &tranche, months_after_contribution(3)), 0.05);
+        assert_eq!(early_withdrawal_penalty_rate(&details, &tranche, months_after_contribution(6) - 1), 0.05);
+        // Past the last tier there is no penalty, even with a flat rate configured
+        assert_eq!(early_withdrawal_penalty_rate(&details, &tranche, months_after_contribution(6)), 0.0);
+    }
+
+    #[test]
+    fn flat_rate_applies_without_tiers() {
+        let tranche = tranche();
+        let now = months_after_contribution(4);
+
+        assert_eq!(early_withdrawal_penalty_rate(&details(Some(0.2), None), &tranche, now), 0.2);
+        assert_eq!(early_withdrawal_penalty_rate(&details(Some(0.2), Some(Vec::new())), &tranche, now), 0.2);
+        assert_eq!(early_withdrawal_penalty_rate(&details(None, None), &tranche, now), 0.0);
+    }
+
+    #[test]
+    fn hardship_waiver_removes_the_penalty() {
+        let mut tranche = tranche();
+        tranche.penalty_waiver = Some(PenaltyWaiver {
+            waiver_id: "waiver_1".into(),
+            reason: "Hardship".into(),
+            granted_by: "admin".into(),
+            granted_at: CONTRIBUTED_AT.to_string(),
+        });
+
+        assert_eq!(early_withdrawal_penalty_rate(&details(Some(0.2), Some(vec![(3, 0.1)])), &tranche, CONTRIBUTED_AT), 0.0);
+    }
+
+    #[test]
+    fn legacy_auto_rollover_only_fills_a_missing_preference() {
+        let mut details = details(None, None);
+        details.auto_rollover_preference = Some(AutoRolloverPreference::SameCause);
+        let mut tranche = tranche();
+
+        let preference = effective_expiration_preference(&tranche, &details);
+        assert_eq!(preference.action, ExpirationAction::Rollover);
+        assert_eq!(preference.rollover_months, Some(12));
+
+        tranche.expiration_preference = Some(TrancheExpirationPreference {
+            action: ExpirationAction::Refund,
+            rollover_months: None,
+            rollover_cause_id: None,
+            rollover_target: None,
+            consumable_schedule: None,
+            consumable_duration: None,
+        });
+        assert_eq!(effective_expiration_preference(&tranche, &details).action, ExpirationAction::Refund);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::admin_hooks::get_admin_role;
+use crate::waqf_types::{
+    ContributionTranche, InstallmentStatus, TrancheLineage, TrancheLineageEntry, TrancheLineageSummary,
+    TrancheStatus, WaqfData,
+};
+use junobuild_satellite::get_doc;
+use junobuild_utils::decode_doc_data;
+use std::collections::{HashMap, HashSet};
+
+// Guards against malformed chains that point back at themselves
+const MAX_LINEAGE_LENGTH: usize = 200;
+
+struct LineageWalker {
+    waqfs: HashMap<String, Option<WaqfData>>,
+}
+
+impl LineageWalker {
+    fn waqf(&mut self, waqf_id: &str) -> Option<&WaqfData> {
+        self.waqfs
+            .entry(waqf_id.to_string())
+            .or_insert_with(|| {
+                get_doc("waqfs".to_string(), waqf_id.to_string())
+                    .and_then(|doc| decode_doc_data::<WaqfData>(&doc.data).ok())
+            })
+            .as_ref()
+    }
+
+    fn tranche(&mut self, waqf_id: &str, tranche_id: &str) -> Option<ContributionTranche> {
+        self.waqf(waqf_id)?
+            .revolving_details
+            .as_ref()?
+            .contribution_tranches
+            .as_ref()?
+            .iter()
+            .find(|t| t.id == tranche_id)
+            .cloned()
+    }
+
+    /// Tranche this one was split from or rolled over from. Cause pool tranches first look in
+    /// the pool (rollovers inside the pool), then in the waqf the principal was pooled from.
+    fn origin(&mut self, waqf_id: &str, tranche: &ContributionTranche) -> Option<(String, ContributionTranche)> {
+        if let Some(parent_id) = &tranche.split_from {
+            return self.tranche(waqf_id, parent_id).map(|parent| (waqf_id.to_string(), parent));
+        }
+
+        let origin_id = tranche.rollover_origin_id.as_ref()?;
+
+        if let Some(origin) = self.tranche(waqf_id, origin_id) {
+            return Some((waqf_id.to_string(), origin));
+        }
+
+        let pool_origin = tranche.pool_origin.as_ref()?;
+        self.tranche(&pool_origin.waqf_id, origin_id)
+            .map(|origin| (pool_origin.waqf_id.clone(), origin))
+    }
+
+    /// Tranche this one was rolled over into, in this waqf or in the cause pool. The principal
+    /// of a split tranche carries on in its remaining portion.
+    fn target(&mut self, waqf_id: &str, tranche: &ContributionTranche) -> Option<(String, ContributionTranche)> {
+        if let Some(remaining_id) = tranche.split_into.as_ref().and_then(|children| children.last()) {
+            return self.tranche(waqf_id, remaining_id).map(|remaining| (waqf_id.to_string(), remaining));
+        }
+
+        let target_id = tranche.rollover_target_id.as_ref()?;
+        let target_waqf_id = tranche.rollover_target_waqf_id.clone().unwrap_or_else(|| waqf_id.to_string());
+
+        self.tranche(&target_waqf_id, target_id)
+            .map(|target| (target_waqf_id, target))
+    }
+}
+
+/// How a tranche came about, relative to the entries before it
+fn relation(tranche: &ContributionTranche, chain: &[(String, ContributionTranche)]) -> &'static str {
+    if let Some(parent_id) = &tranche.split_from {
+        let withdrawn = chain.iter()
+            .find(|(_, t)| &t.id == parent_id)
+            .and_then(|(_, parent)| parent.split_into.as_ref())
+            .is_some_and(|children| children.first() == Some(&tranche.id));
+        return if withdrawn { "split_withdrawn" } else { "split_remaining" };
+    }
+
+    match (&tranche.rollover_origin_id, &tranche.pool_origin) {
+        (Some(origin_id), Some(pool_origin)) if *origin_id == pool_origin.tranche_id => "cause_pool_rollover",
+        (Some(_), _) => "rollover",
+        (None, _) => "contribution",
+    }
+}
+
+fn lineage_entry(waqf_id: String, relation: &str, tranche: ContributionTranche) -> TrancheLineageEntry {
+    let penalty = tranche.penalty_applied.unwrap_or(0.0);
+    let paid: f64 = tranche.installment_payments.iter().flatten()
+        .filter(|i| i.status == InstallmentStatus::Paid)
+        .map(|i| i.amount)
+        .sum();
+    let unpaid: f64 = tranche.installment_payments.iter().flatten()
+        .filter(|i| i.status != InstallmentStatus::Paid)
+        .map(|i| i.amount)
+        .sum();
+
+    let (returned, converted, outstanding) = if tranche.conversion_details.is_some() {
+        (0.0, tranche.amount, 0.0)
+    } else if matches!(tranche.status, Some(TrancheStatus::RolledOver) | Some(TrancheStatus::Split)) {
+        // The principal carried on into the next tranche of the chain (or the split portions)
+        (0.0, 0.0, 0.0)
+    } else if tranche.installment_payments.is_some() {
+        (paid, 0.0, if tranche.is_returned { 0.0 } else { unpaid })
+    } else if tranche.is_returned {
+        ((tranche.amount - penalty).max(0.0), 0.0, 0.0)
+    } else {
+        (0.0, 0.0, tranche.amount)
+    };
+
+    TrancheLineageEntry {
+        waqf_id,
+        relation: relation.to_string(),
+        tranche,
+        principal_returned: returned,
+        principal_converted: converted,
+        principal_outstanding: outstanding,
+    }
+}
+
+fn summarize(entries: &[TrancheLineageEntry]) -> TrancheLineageSummary {
+    let mut summary = TrancheLineageSummary {
+        original_amount: entries.first().map(|e| e.tranche.amount).unwrap_or(0.0),
+        current_status: entries.last().and_then(|e| e.tranche.status),
+        ..Default::default()
+    };
+
+    for entry in entries {
+        summary.total_principal_returned += entry.principal_returned;
+        summary.total_penalties += entry.tranche.penalty_applied.unwrap_or(0.0);
+        summary.total_converted += entry.principal_converted;
+        summary.principal_outstanding += entry.principal_outstanding;
+        for installment in entry.tranche.installment_payments.iter().flatten() {
+            if installment.status == InstallmentStatus::Paid {
+                summary.installments_paid += 1;
+            } else {
+                summary.installments_pending += 1;
+            }
+        }
+    }
+
+    summary
+}
+
+/// Full rollover chain of a tranche, oldest contribution first, with a summary of what was
+/// returned, converted and is still outstanding. Partial withdrawals appear as the split
+/// tranche followed by its withdrawn portion, with the chain carrying on in the remaining
+/// portion. Visible to the donor who owns the principal and to platform staff.
+pub fn get_tranche_lineage(
+    caller: &str,
+    waqf_id: &str,
+    tranche_id: &str,
+) -> std::result::Result<TrancheLineage, String> {
+    let mut walker = LineageWalker { waqfs: HashMap::new() };
+
+    let start = walker
+        .tranche(waqf_id, tranche_id)
+        .ok_or_else(|| format!("Tranche {} not found in waqf {}", tranche_id, waqf_id))?;
+
+    // Walk back to the original contribution
+    let mut visited: HashSet<(String, String)> = HashSet::new();
+    visited.insert((waqf_id.to_string(), start.id.clone()));
+    let (mut root_waqf, mut root) = (waqf_id.to_string(), start);
+    while let Some((origin_waqf, origin)) = walker.origin(&root_waqf, &root) {
+        if visited.len() >= MAX_LINEAGE_LENGTH || !visited.insert((origin_waqf.clone(), origin.id.clone())) {
+            break;
+        }
+        (root_waqf, root) = (origin_waqf, origin);
+    }
+
+    // Walk forward from it to the latest rollover, through the remaining portion of any split
+    let mut path: Vec<(String, ContributionTranche)> = Vec::new();
+    let mut on_path: HashSet<(String, String)> = HashSet::new();
+    let mut next = Some((root_waqf, root));
+    while let Some((current_waqf, current)) = next.take() {
+        if path.len() >= MAX_LINEAGE_LENGTH || !on_path.insert((current_waqf.clone(), current.id.clone())) {
+            break;
+        }
+        next = walker.target(&current_waqf, &current);
+        path.push((current_waqf, current));
+    }
+
+    // Withdrawn portions follow the tranche they were split from
+    let mut chain: Vec<(String, ContributionTranche)> = Vec::new();
+    for (chain_waqf, tranche) in path {
+        let withdrawn: Vec<ContributionTranche> = tranche.split_into.iter().flatten()
+            .filter(|child_id| !on_path.contains(&(chain_waqf.clone(), (*child_id).clone())))
+            .filter_map(|child_id| walker.tranche(&chain_waqf, child_id))
+            .collect();
+        chain.push((chain_waqf.clone(), tranche));
+        chain.extend(withdrawn.into_iter().map(|child| (chain_waqf.clone(), child)));
+    }
+
+    // The principal belongs to the donor of the original waqf (or the recorded pool owner)
+    let is_owner = chain.iter().any(|(chain_waqf, tranche)| {
+        tranche.pool_origin.as_ref().is_some_and(|origin| origin.owner == caller)
+            || (tranche.pool_origin.is_none()
+                && walker.waqf(chain_waqf).is_some_and(|waqf| waqf.created_by == caller))
+    });
+    if !is_owner && get_admin_role(caller).is_none() {
+        return Err("Only the donor who owns this principal or platform staff can view its lineage".to_string());
+    }
+
+    let relations: Vec<&str> = chain.iter().map(|(_, tranche)| relation(tranche, &chain)).collect();
+    let entries: Vec<TrancheLineageEntry> = chain
+        .into_iter()
+        .zip(relations)
+        .map(|((chain_waqf, tranche), relation)| lineage_entry(chain_waqf, relation, tranche))
+        .collect();
+    let summary = summarize(&entries);
+
+    Ok(TrancheLineage { entries, summary })
+}
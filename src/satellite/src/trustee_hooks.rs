@@ -0,0 +1,267 @@
+use crate::activity_log_hooks;
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::trustee_types::{AppointmentStatus, TrusteeAction, TrusteeAppointmentData};
+use crate::waqf_types::{WaqfData, WaqfTrustee};
+use candid::Principal;
+use junobuild_satellite::{get_doc, set_doc, AssertDeleteDocContext, AssertSetDocContext, OnSetDocContext, SetDoc};
+use junobuild_utils::{decode_doc_data, encode_doc_data};
+
+const MIN_JUSTIFICATION_LENGTH: usize = 20;
+const MAX_JUSTIFICATION_LENGTH: usize = 1000;
+const MAX_TRUSTEE_NAME_LENGTH: usize = 100;
+const MAX_TRUSTEES_PER_WAQF: usize = 10;
+
+const TRUSTEE_REVIEWERS: &[AdminRole] = &[AdminRole::WaqfManager, AdminRole::PlatformAdmin];
+
+fn load_waqf(waqf_id: &str) -> std::result::Result<(WaqfData, junobuild_satellite::Doc), String> {
+    let doc = get_doc("waqfs".to_string(), waqf_id.to_string())
+        .ok_or_else(|| format!("Waqf not found: {}", waqf_id))?;
+    let waqf: WaqfData = decode_doc_data(&doc.data)
+        .map_err(|e| format!("Failed to decode waqf data: {}", e))?;
+    Ok((waqf, doc))
+}
+
+/// Whether the appointment can still be applied to the waqf as it is now
+fn validate_against_waqf(appointment: &TrusteeAppointmentData, waqf: &WaqfData) -> std::result::Result<(), String> {
+    let is_trustee = waqf.trustees.iter().any(|trustee| trustee.principal == appointment.trustee);
+
+    match appointment.action {
+        TrusteeAction::Appoint => {
+            if is_trustee {
+                return Err(format!("{} is already a trustee of waqf {}", appointment.trustee, waqf.id));
+            }
+            if waqf.trustees.len() >= MAX_TRUSTEES_PER_WAQF {
+                return Err(format!("A waqf can have at most {} trustees", MAX_TRUSTEES_PER_WAQF));
+            }
+        }
+        TrusteeAction::Remove => {
+            if !is_trustee {
+                return Err(format!("{} is not a trustee of waqf {}", appointment.trustee, waqf.id));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_new_appointment(appointment: &TrusteeAppointmentData, caller: &str) -> std::result::Result<(), String> {
+    if appointment.requested_by != caller {
+        return Err("Requested by must match the caller".into());
+    }
+    if appointment.status != AppointmentStatus::Pending {
+        return Err("Trustee appointments must be submitted as pending".into());
+    }
+    if appointment.reviewed_by.is_some() || appointment.reviewed_at.is_some() {
+        return Err("A new trustee appointment cannot carry a review".into());
+    }
+
+    let (waqf, _) = load_waqf(&appointment.waqf_id)?;
+    if waqf.created_by != caller && !caller_has_role(caller, TRUSTEE_REVIEWERS) {
+        return Err("Only the waqf donor or a waqf manager can request trustee changes".into());
+    }
+
+    Principal::from_text(&appointment.trustee)
+        .map_err(|_| format!("Invalid trustee principal: {}", appointment.trustee))?;
+    if appointment.trustee == waqf.created_by {
+        return Err("The waqf donor cannot be appointed as its trustee".into());
+    }
+
+    match appointment.action {
+        TrusteeAction::Appoint if appointment.powers.is_empty() => {
+            return Err("A trustee must be granted at least one power".into());
+        }
+        TrusteeAction::Remove if !appointment.powers.is_empty() => {
+            return Err("Trustee removals cannot grant powers".into());
+        }
+        _ => {}
+    }
+    if appointment.powers.iter().enumerate().any(|(index, power)| appointment.powers[..index].contains(power)) {
+        return Err("Trustee powers cannot be listed twice".into());
+    }
+
+    if appointment.trustee_name.as_ref().is_some_and(|name| name.trim().is_empty() || name.len() > MAX_TRUSTEE_NAME_LENGTH) {
+        return Err(format!("Trustee name must be between 1 and {} characters", MAX_TRUSTEE_NAME_LENGTH));
+    }
+
+    let justification_length = appointment.justification.trim().len();
+    if !(MIN_JUSTIFICATION_LENGTH..=MAX_JUSTIFICATION_LENGTH).contains(&justification_length) {
+        return Err(format!(
+            "Justification must be between {} and {} characters",
+            MIN_JUSTIFICATION_LENGTH, MAX_JUSTIFICATION_LENGTH
+        ));
+    }
+
+    appointment.requested_at.parse::<u64>()
+        .map_err(|_| "Invalid requested at format")?;
+
+    validate_against_waqf(appointment, &waqf)
+}
+
+fn validate_review(
+    current: &TrusteeAppointmentData,
+    proposed: &TrusteeAppointmentData,
+    caller: &str,
+) -> std::result::Result<(), String> {
+    if !caller_has_role(caller, TRUSTEE_REVIEWERS) {
+        return Err("Only waqf managers can review trustee appointments".into());
+    }
+    if current.status != AppointmentStatus::Pending {
+        return Err(format!("Trustee appointment {} has already been {}", current.id, current.status));
+    }
+    if current.requested_by == caller {
+        return Err("A trustee appointment cannot be reviewed by the person who requested it".into());
+    }
+    if !matches!(proposed.status, AppointmentStatus::Approved | AppointmentStatus::Rejected) {
+        return Err("A trustee appointment review must approve or reject it".into());
+    }
+    if proposed.reviewed_by.as_deref() != Some(caller) {
+        return Err("Reviewed by must match the calling reviewer".into());
+    }
+    proposed.reviewed_at.as_deref().unwrap_or_default().parse::<u64>()
+        .map_err(|_| "Invalid reviewed at format")?;
+
+    // Only the review itself may be added
+    let expected = TrusteeAppointmentData {
+        status: proposed.status,
+        reviewed_by: proposed.reviewed_by.clone(),
+        reviewed_at: proposed.reviewed_at.clone(),
+        review_notes: proposed.review_notes.clone(),
+        ..current.clone()
+    };
+    if *proposed != expected {
+        return Err("Only the status and review fields can change when reviewing a trustee appointment".into());
+    }
+
+    if proposed.status == AppointmentStatus::Approved {
+        let (waqf, _) = load_waqf(&current.waqf_id)?;
+        validate_against_waqf(current, &waqf)?;
+    }
+
+    Ok(())
+}
+
+pub fn assert_trustee_appointment_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
+    let appointment: TrusteeAppointmentData = decode_doc_data(&context.data.data.proposed.data)
+        .map_err(|e| format!("Invalid trustee appointment data structure: {}", e))?;
+
+    if appointment.id != context.data.key {
+        return Err("Trustee appointment id must match the document key".into());
+    }
+
+    let caller = context.caller.to_text();
+    if is_satellite_caller(&caller) {
+        return Err("Trustee appointments are not written by the satellite".into());
+    }
+
+    match &context.data.data.current {
+        None => validate_new_appointment(&appointment, &caller)?,
+        Some(doc) => {
+            let current: TrusteeAppointmentData = decode_doc_data(&doc.data)
+                .map_err(|e| format!("Failed to decode current trustee appointment: {}", e))?;
+            validate_review(&current, &appointment, &caller)?;
+        }
+    }
+
+    ic_cdk::println!(
+        "Trustee appointment validation passed: {} - Waqf: {}, {} {}, Status: {}",
+        appointment.id, appointment.waqf_id, appointment.action, appointment.trustee, appointment.status
+    );
+
+    Ok(())
+}
+
+pub fn assert_trustee_appointment_deletion(_context: AssertDeleteDocContext) -> std::result::Result<(), String> {
+    Err("Trustee appointments cannot be deleted for audit purposes".into())
+}
+
+pub fn handle_trustee_appointment_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
+    let appointment: TrusteeAppointmentData = decode_doc_data(&context.data.data.after.data)
+        .map_err(|e| format!("Cannot decode trustee appointment data: {}", e))?;
+
+    ic_cdk::println!(
+        "Trustee appointment {}: {} - Waqf: {}, {} {}, Status: {}",
+        if context.data.data.before.is_none() { "CREATE" } else { "UPDATE" },
+        appointment.id, appointment.waqf_id, appointment.action, appointment.trustee, appointment.status
+    );
+
+    match appointment.status {
+        AppointmentStatus::Pending => {
+            activity_log_hooks::record_audit_log(
+                &format!("trustee_{}_requested", appointment.action),
+                &appointment.requested_by,
+                "waqf",
+                &appointment.waqf_id,
+                format!("{} of trustee {} requested ({}): {}", appointment.action, appointment.trustee, appointment.id, appointment.justification),
+            );
+        }
+        AppointmentStatus::Rejected => {
+            activity_log_hooks::record_audit_log(
+                &format!("trustee_{}_rejected", appointment.action),
+                appointment.reviewed_by.as_deref().unwrap_or_default(),
+                "waqf",
+                &appointment.waqf_id,
+                format!(
+                    "{} of trustee {} rejected ({}): {}",
+                    appointment.action, appointment.trustee, appointment.id,
+                    appointment.review_notes.clone().unwrap_or_default()
+                ),
+            );
+        }
+        AppointmentStatus::Approved => apply_appointment(&appointment)?,
+    }
+
+    Ok(())
+}
+
+/// Add or remove the trustee on the waqf and record it in the audit log
+fn apply_appointment(appointment: &TrusteeAppointmentData) -> std::result::Result<(), String> {
+    let now = ic_cdk::api::time();
+    let (mut waqf, doc) = load_waqf(&appointment.waqf_id)?;
+
+    // The waqf may have changed between the review and this hook
+    validate_against_waqf(appointment, &waqf)?;
+
+    match appointment.action {
+        TrusteeAction::Appoint => waqf.trustees.push(WaqfTrustee {
+            principal: appointment.trustee.clone(),
+            name: appointment.trustee_name.clone(),
+            powers: appointment.powers.clone(),
+            appointment_id: appointment.id.clone(),
+            appointed_at: now.to_string(),
+        }),
+        TrusteeAction::Remove => waqf.trustees.retain(|trustee| trustee.principal != appointment.trustee),
+    }
+    waqf.updated_at = Some(now.to_string());
+
+    let data = encode_doc_data(&waqf)
+        .map_err(|e| format!("Failed to encode waqf data: {}", e))?;
+    set_doc(
+        "waqfs".to_string(),
+        appointment.waqf_id.clone(),
+        SetDoc { data, description: doc.description, version: doc.version },
+    );
+
+    let (action, verb) = match appointment.action {
+        TrusteeAction::Appoint => ("trustee_appointed", "appointed"),
+        TrusteeAction::Remove => ("trustee_removed", "removed"),
+    };
+    let powers: Vec<&str> = appointment.powers.iter().map(|power| power.as_str()).collect();
+    activity_log_hooks::record_audit_log(
+        action,
+        appointment.reviewed_by.as_deref().unwrap_or_default(),
+        "waqf",
+        &appointment.waqf_id,
+        format!(
+            "Trustee {} {} ({}), requested by {}{}",
+            appointment.trustee,
+            verb,
+            appointment.id,
+            appointment.requested_by,
+            if powers.is_empty() { String::new() } else { format!(", powers: {}", powers.join(", ")) }
+        ),
+    );
+
+    ic_cdk::println!("Trustee {} {} for waqf {}", appointment.trustee, verb, appointment.waqf_id);
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use crate::waqf_types::TrusteePower;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TrusteeAction {
+        Appoint => "appoint",
+        Remove => "remove",
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum AppointmentStatus {
+        Pending => "pending",
+        Approved => "approved",   // Applied to the waqf's trustees by the satellite
+        Rejected => "rejected",
+    }
+}
+
+/// Request to appoint or remove a trustee (nazir) of a waqf, reviewed by waqf managers
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrusteeAppointmentData {
+    pub id: String,
+    pub waqf_id: String,
+    pub action: TrusteeAction,
+    pub trustee: String,                  // Principal of the trustee
+    pub trustee_name: Option<String>,
+    pub powers: Vec<TrusteePower>,        // Powers granted on appointment (empty for removals)
+    pub justification: String,
+    pub requested_by: String,
+    pub requested_at: String,             // Nanosecond timestamps
+    pub status: AppointmentStatus,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<String>,
+    pub review_notes: Option<String>,
+}
@@ -1,12 +1,31 @@
 use crate::{
+    activity_log_hooks,
+    admin_hooks::{get_admin_role, is_satellite_caller},
+    bucket_accounting,
+    calendar,
+    deed_hooks,
+    deed_reference,
+    template_hooks,
+    tranche_hooks,
     waqf_utils,
-    waqf_types::{WaqfData},
+    waqf_types::{
+        ContributionTranche, PrincipalReturnMethod, SpendingSchedule, TrancheExpirationPreference, TrancheStatus,
+        WaqfBucket, WaqfData, WaqfStatus, WaqfType,
+    },
 };
 use junobuild_satellite::{OnSetDocContext, AssertSetDocContext, AssertDeleteDocContext};
 use junobuild_utils::{decode_doc_data};
 use serde::{Serialize, Deserialize};
+use std::cell::RefCell;
 use std::fmt;
 
+const INITIAL_TRANCHE_PREFIX: &str = "tranche_initial_";
+
+thread_local! {
+    // Waqf documents (key, version) saved by initialize_new_waqf whose hooks are still to run
+    static OWN_WRITES: RefCell<Vec<(String, Option<u64>)>> = const { RefCell::new(Vec::new()) };
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum WaqfAction {
     Create,
@@ -89,59 +108,53 @@ fn validate_creator_field_restrictions(
         return Err("FORBIDDEN: Creation timestamp cannot be modified.".to_string());
     }
     
-    // Only validate remaining fields if caller is the original creator
-    // Admins and system hooks can update these fields if needed
-    if previous.created_by != caller {
-        return Ok(()); // Allow admin/system operations on non-immutable fields
+    if previous.trustees != updated.trustees && !is_satellite_caller(caller) {
+        return Err("FORBIDDEN: Trustees are appointed and removed through trustee appointments.".to_string());
     }
-    
-    // Check for restricted field changes (creator restrictions only)
-    // Note: financial fields ONLY updated by donations are allowed (system hooks)
-    let financial_only_update = 
-        previous.financial != updated.financial &&
-        previous.selected_causes == updated.selected_causes &&
-        previous.status == updated.status &&
-        previous.is_donated == updated.is_donated &&
-        previous.notifications == updated.notifications &&
-        previous.reporting_preferences == updated.reporting_preferences;
-    
-    // If only financial changed, it's likely from a donation hook - allow it
-    if financial_only_update {
-        ic_cdk::println!(
-            "INFO: Financial-only update detected for waqf: {} (likely from donation hook)",
-            updated.id
-        );
+
+    // Trustees act through allocations, investment returns and tranche return requests
+    if previous.trustees.iter().any(|trustee| trustee.principal == caller)
+        && previous.created_by != caller
+        && get_admin_role(caller).is_none()
+    {
+        return Err("FORBIDDEN: Trustees cannot edit the waqf directly.".to_string());
+    }
+
+    // Hooks and scheduled jobs write as the satellite; their changes are checked where they are made
+    if is_satellite_caller(caller) {
         return Ok(());
     }
-    
-    let restricted_changes: Vec<&str> = [
-        ("selected_causes", previous.selected_causes != updated.selected_causes),
-        ("status", previous.status != updated.status),
-        ("is_donated", previous.is_donated != updated.is_donated),
-        ("notifications", previous.notifications != updated.notifications),
-        ("reporting_preferences", previous.reporting_preferences != updated.reporting_preferences),
-        ("financial", previous.financial != updated.financial),
-        ("last_contribution_date", previous.last_contribution_date != updated.last_contribution_date),
-        ("next_contribution_date", previous.next_contribution_date != updated.next_contribution_date),
-        ("next_report_date", previous.next_report_date != updated.next_report_date),
-    ]
-    .iter()
-    .filter_map(|(field, changed)| if *changed { Some(*field) } else { None })
-    .collect();
-    
-    // Reject if restricted fields were modified by creator
-    if !restricted_changes.is_empty() {
+
+    // Everyone else may only change the fields listed for them: the previous document with
+    // those fields taken from the update must match the update exactly. Everything else
+    // (causes, balances, tranche records, ...) changes through requests and hooks.
+    // Status changes are governed by the transition matrix in waqf_utils.
+    let is_creator = previous.created_by == caller;
+    let mut expected = previous.clone();
+    if is_creator {
+        apply_creator_edits(&mut expected, updated);
+    } else if get_admin_role(caller).is_some() {
+        apply_staff_edits(&mut expected, updated);
+    }
+
+    let unauthorized_changes = changed_fields(&expected, updated);
+    if !unauthorized_changes.is_empty() {
         ic_cdk::println!(
-            "SECURITY: Creator {} attempted to modify restricted fields: {} for waqf: {}",
-            caller, restricted_changes.join(", "), updated.id
+            "SECURITY: {} attempted to modify restricted fields: {} for waqf: {}",
+            caller, unauthorized_changes.join(", "), updated.id
         );
-        
+
         return Err(format!(
-            "Waqf creators can only update name, description, and donor fields. Unauthorized changes: {}",
-            restricted_changes.join(", ")
+            "Waqf creators can only update name, description, donor fields and their preferences; \
+            causes are changed through a cause reallocation request. Unauthorized changes: {}",
+            unauthorized_changes.join(", ")
         ));
     }
-    
+
+    if !is_creator {
+        return Ok(());
+    }
+
     // Log allowed changes
     let allowed_changes: Vec<&str> = [
         ("name", previous.name != updated.name),
@@ -150,6 +163,10 @@ fn validate_creator_field_restrictions(
         ("donor.email", previous.donor.email != updated.donor.email),
         ("donor.phone", previous.donor.phone != updated.donor.phone),
         ("donor.address", previous.donor.address != updated.donor.address),
+        ("notifications", previous.notifications != updated.notifications),
+        ("reporting_preferences", previous.reporting_preferences != updated.reporting_preferences),
+        ("default_expiration_preference", default_expiration_preference(previous) != default_expiration_preference(updated)),
+        ("tranche expiration_preference", tranche_expiration_preferences(previous) != tranche_expiration_preferences(updated)),
     ]
     .iter()
     .filter_map(|(field, changed)| if *changed { Some(*field) } else { None })
@@ -165,6 +182,115 @@ fn validate_creator_field_restrictions(
     Ok(())
 }
 
+// Fields the creator edits directly. The first signed deed is checked by deed_hooks.
+fn apply_creator_edits(expected: &mut WaqfData, updated: &WaqfData) {
+    expected.name = updated.name.clone();
+    expected.description = updated.description.clone();
+    expected.donor = updated.donor.clone();
+    expected.notifications = updated.notifications.clone();
+    expected.reporting_preferences = updated.reporting_preferences.clone();
+    expected.status = updated.status;
+    expected.updated_at = updated.updated_at.clone();
+    expected.deed_document = updated.deed_document.clone();
+
+    if let (Some(expected_details), Some(updated_details)) = (expected.revolving_details.as_mut(), updated.revolving_details.as_ref()) {
+        expected_details.default_expiration_preference = updated_details.default_expiration_preference.clone();
+        for tranche in expected_details.contribution_tranches.iter_mut().flatten() {
+            if let Some(updated_tranche) = find_tranche(updated_details.contribution_tranches.as_deref(), &tranche.id) {
+                tranche.expiration_preference = updated_tranche.expiration_preference.clone();
+            }
+        }
+    }
+}
+
+// Fields staff edit directly: the status, and clearing a failed expiration so the sweep retries it
+fn apply_staff_edits(expected: &mut WaqfData, updated: &WaqfData) {
+    expected.status = updated.status;
+    expected.updated_at = updated.updated_at.clone();
+
+    if let (Some(expected_details), Some(updated_details)) = (expected.revolving_details.as_mut(), updated.revolving_details.as_ref()) {
+        for tranche in expected_details.contribution_tranches.iter_mut().flatten() {
+            let cleared = find_tranche(updated_details.contribution_tranches.as_deref(), &tranche.id)
+                .is_some_and(|updated_tranche| updated_tranche.expiration_failure.is_none());
+            if cleared {
+                tranche.expiration_failure = None;
+            }
+        }
+    }
+}
+
+fn find_tranche<'a>(tranches: Option<&'a [ContributionTranche]>, tranche_id: &str) -> Option<&'a ContributionTranche> {
+    tranches.and_then(|tranches| tranches.iter().find(|tranche| tranche.id == tranche_id))
+}
+
+fn tranche_expiration_preferences(waqf: &WaqfData) -> Vec<Option<&TrancheExpirationPreference>> {
+    waqf.revolving_details.iter()
+        .flat_map(|details| details.contribution_tranches.iter().flatten())
+        .map(|tranche| tranche.expiration_preference.as_ref())
+        .collect()
+}
+
+// Names of the fields that differ between two versions of a waqf
+fn changed_fields(previous: &WaqfData, updated: &WaqfData) -> Vec<&'static str> {
+    [
+        ("id", previous.id != updated.id),
+        ("name", previous.name != updated.name),
+        ("description", previous.description != updated.description),
+        ("waqf_asset", previous.waqf_asset != updated.waqf_asset),
+        ("donor", previous.donor != updated.donor),
+        ("selected_causes", previous.selected_causes != updated.selected_causes),
+        ("cause_allocation", previous.cause_allocation != updated.cause_allocation),
+        ("status", previous.status != updated.status),
+        ("is_donated", previous.is_donated != updated.is_donated),
+        ("notifications", previous.notifications != updated.notifications),
+        ("reporting_preferences", previous.reporting_preferences != updated.reporting_preferences),
+        ("financial", previous.financial != updated.financial),
+        ("waqf_type", previous.waqf_type != updated.waqf_type),
+        ("is_hybrid", previous.is_hybrid != updated.is_hybrid),
+        ("hybrid_allocations", previous.hybrid_allocations != updated.hybrid_allocations),
+        ("consumable_details", previous.consumable_details != updated.consumable_details),
+        ("revolving_details", previous.revolving_details != updated.revolving_details),
+        ("investment_strategy", previous.investment_strategy != updated.investment_strategy),
+        ("deed_document", previous.deed_document != updated.deed_document),
+        ("created_by", previous.created_by != updated.created_by),
+        ("created_at", previous.created_at != updated.created_at),
+        ("updated_at", previous.updated_at != updated.updated_at),
+        ("last_contribution_date", previous.last_contribution_date != updated.last_contribution_date),
+        ("next_contribution_date", previous.next_contribution_date != updated.next_contribution_date),
+        ("next_report_date", previous.next_report_date != updated.next_report_date),
+        ("causes_pending_reassignment", previous.causes_pending_reassignment != updated.causes_pending_reassignment),
+        ("converted_from", previous.converted_from != updated.converted_from),
+        ("deed_reference", previous.deed_reference != updated.deed_reference),
+        ("trustees", previous.trustees != updated.trustees),
+        ("template", previous.template != updated.template),
+    ]
+    .iter()
+    .filter_map(|(field, changed)| if *changed { Some(*field) } else { None })
+    .collect()
+}
+
+fn default_expiration_preference(waqf: &WaqfData) -> Option<&TrancheExpirationPreference> {
+    waqf.revolving_details.as_ref().and_then(|details| details.default_expiration_preference.as_ref())
+}
+
+/// Audit entry for donor preference changes (notifications, reporting, default expiration)
+fn record_preference_changes(previous: &WaqfData, updated: &WaqfData, caller: &str) {
+    let mut changes = Vec::new();
+    if previous.notifications != updated.notifications {
+        changes.push(format!("notifications: {:?}", updated.notifications));
+    }
+    if previous.reporting_preferences != updated.reporting_preferences {
+        changes.push(format!("reporting_preferences: {:?}", updated.reporting_preferences));
+    }
+    if default_expiration_preference(previous) != default_expiration_preference(updated) {
+        changes.push(format!("default_expiration_preference: {:?}", default_expiration_preference(updated)));
+    }
+
+    if !changes.is_empty() {
+        activity_log_hooks::record_audit_log("waqf_preferences_updated", caller, "waqf", &updated.id, changes.join("; "));
+    }
+}
+
 // Validate minimum initial capital for new waqf creation
 fn validate_minimum_waqf_asset(waqf: &WaqfData) -> std::result::Result<(), String> {
     const MIN_WAQF_AMOUNT: f64 = 100.0; // Minimum $100 for meaningful waqf
@@ -211,8 +337,7 @@ fn validate_hybrid_allocations(allocations: &[crate::waqf_types::HybridCauseAllo
 }
 
 // Validate waqf type and corresponding details
-fn validate_waqf_type_and_details(waqf: &WaqfData) -> std::result::Result<(), String> {
-    use crate::waqf_types::WaqfType;
+pub fn validate_waqf_type_and_details(waqf: &WaqfData) -> std::result::Result<(), String> {
     
     match &waqf.waqf_type {
         WaqfType::Permanent => {
@@ -234,30 +359,22 @@ fn validate_waqf_type_and_details(waqf: &WaqfData) -> std::result::Result<(), St
             let consumable = waqf.consumable_details.as_ref()
                 .ok_or("Consumable waqf must have consumable details")?;
             
-            // Validate spending schedule
-            match consumable.spending_schedule.as_str() {
-                "immediate" | "phased" | "milestone-based" | "ongoing" => {},
-                _ => return Err(format!(
-                    "Invalid spending schedule: {}", consumable.spending_schedule
-                )),
-            }
-            
             // Validate schedule-specific requirements
-            match consumable.spending_schedule.as_str() {
-                "milestone-based" => {
+            match consumable.spending_schedule {
+                SpendingSchedule::MilestoneBased => {
                     // Milestone-based must have milestones
                     if consumable.milestones.is_none() || consumable.milestones.as_ref().unwrap().is_empty() {
                         return Err("Milestone-based spending requires at least one milestone".to_string());
                     }
                 },
-                "phased" => {
+                SpendingSchedule::Phased => {
                     // Phased should have dates or minimum distribution
                     if consumable.start_date.is_none() && consumable.end_date.is_none() 
                         && consumable.minimum_monthly_distribution.is_none() {
                         return Err("Phased spending requires either time boundaries or minimum distribution amount".to_string());
                     }
                 },
-                "ongoing" => {
+                SpendingSchedule::Ongoing => {
                     // Ongoing should have minimum distribution or target criteria
                     if consumable.minimum_monthly_distribution.is_none() 
                         && consumable.target_amount.is_none() 
@@ -265,7 +382,7 @@ fn validate_waqf_type_and_details(waqf: &WaqfData) -> std::result::Result<(), St
                         return Err("Ongoing spending requires minimum distribution or target criteria".to_string());
                     }
                 },
-                _ => {} // immediate has no specific requirements
+                SpendingSchedule::Immediate => {} // immediate has no specific requirements
             }
             
             // Validate date logic if both dates are present
@@ -315,27 +432,14 @@ fn validate_waqf_type_and_details(waqf: &WaqfData) -> std::result::Result<(), St
                 return Err("Lock period cannot exceed 240 months (20 years)".to_string());
             }
             
-            // Validate return method
-            match revolving.principal_return_method.as_str() {
-                "lump_sum" | "installments" => {},
-                _ => return Err(format!(
-                    "Invalid principal return method: {}", revolving.principal_return_method
-                )),
-            }
-            
             // If installments, must have schedule
-            if revolving.principal_return_method == "installments" && revolving.installment_schedule.is_none() {
+            if revolving.principal_return_method == PrincipalReturnMethod::Installments && revolving.installment_schedule.is_none() {
                 return Err("Installment method requires installment schedule".to_string());
             }
             
-            // Validate penalty if present
-            if let Some(penalty) = revolving.early_withdrawal_penalty {
-                if penalty < 0.0 || penalty > 1.0 {
-                    return Err(format!(
-                        "Early withdrawal penalty must be between 0 and 1, got {}", penalty
-                    ));
-                }
-            }
+            // Validate flat penalty, declining penalty tiers and penalty destination
+            tranche_hooks::validate_penalty_settings(revolving)?;
+            tranche_hooks::validate_rollover_settings(revolving)?;
             
             ic_cdk::println!(
                 "INFO: Revolving waqf validated - {} (Lock: {} months, Return: {})",
@@ -375,153 +479,54 @@ fn validate_waqf_type_and_details(waqf: &WaqfData) -> std::result::Result<(), St
 }
 
 // Main assertion function for waqf operations
-pub fn assert_waqf_operations(mut context: AssertSetDocContext) -> std::result::Result<(), String> {
-    use junobuild_utils::encode_doc_data;
-    use crate::waqf_types::{WaqfType, ContributionTranche};
-    
+pub fn assert_waqf_operations(context: AssertSetDocContext) -> std::result::Result<(), String> {
     ic_cdk::println!("🔍 ASSERT_WAQF_OPERATIONS CALLED - Collection: {}, Has current: {}", 
         context.data.collection, 
         context.data.data.current.is_some()
     );
     
     // Decode waqf data with proper error handling
-    let mut waqf: WaqfData = decode_doc_data(&context.data.data.proposed.data)
+    let waqf: WaqfData = decode_doc_data(&context.data.data.proposed.data)
         .map_err(|e| format!("Invalid waqf data structure: {}", e))?;
     
-    // Validate the waqf data structure
-    waqf_utils::validate_waqf_data(&waqf)?;
-    
-    // Validate waqf type and temporary waqf details
-    validate_waqf_type_and_details(&waqf)?;
+    // Validate the waqf data structure (updates are validated against the previous document below)
+    if context.data.data.current.is_none() {
+        waqf_utils::validate_waqf_data(&waqf)?;
+    }
     
     // Check if this is a creation or update
     let is_new_waqf = context.data.data.current.is_none();
-    let mut needs_data_update = false;
+    
+    // Validate waqf type and temporary waqf details (satellite updates only carry its own bookkeeping)
+    if is_new_waqf || !is_satellite_caller(&context.caller.to_text()) {
+        validate_waqf_type_and_details(&waqf)?;
+    }
     
     if is_new_waqf {
         ic_cdk::println!("✨ NEW WAQF CREATION - validating minimum capital");
         // This is a new waqf creation - enforce minimum capital
-        validate_minimum_waqf_asset(&waqf)?;
-        
-        // Initialize cause allocations for new waqfs
-        let waqf_asset = waqf.waqf_asset;
-        let needs_init = waqf.financial.cause_allocations.is_empty() ||
-                         waqf.financial.cause_allocations.values().all(|&v| v == 0.0);
-        
-        if needs_init {
-            ic_cdk::println!("Initializing cause allocations for new waqf: {}", waqf.name);
-            
-            waqf.financial.cause_allocations.clear();
-            
-            for cause_id in &waqf.selected_causes {
-                let percentage = waqf.cause_allocation.get(cause_id)
-                    .copied()
-                    .unwrap_or_else(|| {
-                        if waqf.selected_causes.len() > 0 {
-                            100.0 / waqf.selected_causes.len() as f64
-                        } else {
-                            100.0
-                        }
-                    });
-                
-                let amount = (waqf_asset * percentage) / 100.0;
-                waqf.financial.cause_allocations.insert(cause_id.clone(), amount);
-                
-                ic_cdk::println!("  Cause {}: {:.2}% = {:.2}", cause_id, percentage, amount);
+        if waqf.converted_from.is_some() {
+            // Only the tranche maturity sweep creates converted waqfs
+            if !is_satellite_caller(&context.caller.to_text()) {
+                return Err("Converted waqfs can only be created by the satellite".into());
             }
-            
-            needs_data_update = true;
-            ic_cdk::println!("✅ Initialized {} cause allocations", waqf.selected_causes.len());
+        } else {
+            validate_minimum_waqf_asset(&waqf)?;
         }
-        
-        // Create initial tranche for revolving waqfs
-        let should_create_tranche = matches!(waqf.waqf_type, WaqfType::TemporaryRevolving) ||
-            (matches!(waqf.waqf_type, WaqfType::Hybrid) && waqf.revolving_details.is_some());
-        
-        if should_create_tranche {
-            if let Some(ref mut revolving_details) = waqf.revolving_details {
-                let current_time_nanos = ic_cdk::api::time();
-                let current_time = current_time_nanos.to_string();
-                
-                let lock_period_nanos = (revolving_details.lock_period_months as u64) 
-                    * 30 * 24 * 60 * 60 * 1_000_000_000;
-                let maturity_time_nanos = current_time_nanos + lock_period_nanos;
-                let maturity_date = maturity_time_nanos.to_string();
-                
-                // Calculate revolving portion
-                let revolving_amount = if matches!(waqf.waqf_type, WaqfType::Hybrid) {
-                    if let Some(ref allocations) = waqf.hybrid_allocations {
-                        let total_waqf = waqf.waqf_asset;
-                        let mut total_revolving_pct = 0.0;
-                        
-                        for alloc in allocations {
-                            total_revolving_pct += alloc.allocations.temporary_revolving.unwrap_or(0.0);
-                        }
-                        
-                        let avg_revolving_pct = if !allocations.is_empty() {
-                            total_revolving_pct / allocations.len() as f64
-                        } else {
-                            0.0
-                        };
-                        
-                        let revolving_amt = (total_waqf * avg_revolving_pct) / 100.0;
-                        
-                        ic_cdk::println!(
-                            "Hybrid waqf - Total: {}, Avg Revolving %: {:.2}, Revolving Amount: {:.2}",
-                            total_waqf, avg_revolving_pct, revolving_amt
-                        );
-                        
-                        revolving_amt
-                    } else {
-                        waqf.waqf_asset
-                    }
-                } else {
-                    waqf.waqf_asset
-                };
-                
-                if revolving_amount > 0.0 {
-                    let tranche_id = format!("tranche_initial_{}", current_time_nanos);
-                    let initial_tranche = ContributionTranche {
-                        id: tranche_id.clone(),
-                        amount: revolving_amount,
-                        contribution_date: current_time.clone(),
-                        maturity_date: maturity_date.clone(),
-                        is_returned: false,
-                        returned_date: None,
-                        status: Some("locked".to_string()),
-                        penalty_applied: None,
-                        rollover_origin_id: None,
-                        rollover_target_id: None,
-                        installment_payments: None,
-                        expiration_preference: revolving_details.default_expiration_preference.clone(),
-                        conversion_details: None,
-                    };
-                    
-                    ic_cdk::println!(
-                        "✨ Creating initial tranche: ID={}, Amount={:.2}, Maturity in {} months",
-                        tranche_id, revolving_amount, revolving_details.lock_period_months
-                    );
-                    
-                    revolving_details.contribution_tranches = Some(vec![initial_tranche]);
-                    needs_data_update = true;
-                    
-                    ic_cdk::println!("✅ Initial tranche created: Amount={:.2}", revolving_amount);
-                } else {
-                    ic_cdk::println!(
-                        "⚠️ No revolving amount for hybrid waqf {}, skipping tranche creation",
-                        waqf.name
-                    );
-                }
+
+        // A signed deed must be verifiable; an active waqf must have one
+        if !is_satellite_caller(&context.caller.to_text()) {
+            deed_hooks::check_deed_on_create(&waqf)?;
+
+            if waqf.deed_reference.is_some() {
+                return Err("Deed references are assigned by the satellite".into());
             }
+
+            template_hooks::check_template_conformance(&waqf)?;
         }
         
-        // Update the proposed data if we made changes
-        if needs_data_update {
-            let updated_data = encode_doc_data(&waqf)
-                .map_err(|e| format!("Failed to encode updated waqf data: {}", e))?;
-            context.data.data.proposed.data = updated_data;
-            ic_cdk::println!("✅ Waqf initialization complete for: {}", waqf.name);
-        }
+        // Cause allocations, buckets and the initial tranche are set up once the waqf is
+        // saved, by initialize_new_waqf in on_set_doc
     } else {
         ic_cdk::println!("📝 WAQF UPDATE - validating field restrictions");
         // This is an update - validate field restrictions
@@ -537,6 +542,28 @@ pub fn assert_waqf_operations(mut context: AssertSetDocContext) -> std::result::
             // Validate that immutable fields haven't changed
             validate_creator_field_restrictions(&previous_waqf, &waqf, &context.caller.to_string())?;
 
+            // Status transitions, who may make them, and the archived/completed locks
+            waqf_utils::validate_waqf_update(&waqf, &previous_waqf, &context.caller.to_text())?;
+
+            // The signed deed and the terms it covers are frozen
+            if !is_satellite_caller(&context.caller.to_text()) {
+                deed_hooks::check_deed_on_update(&previous_waqf, &waqf)?;
+            }
+
+            if previous_waqf.deed_reference != waqf.deed_reference
+                && (previous_waqf.deed_reference.is_some() || !is_satellite_caller(&context.caller.to_text()))
+            {
+                return Err("FORBIDDEN: The deed reference of a waqf cannot be changed.".to_string());
+            }
+
+            if previous_waqf.converted_from != waqf.converted_from {
+                return Err("FORBIDDEN: The conversion origin of a waqf cannot be changed.".to_string());
+            }
+
+            if previous_waqf.template != waqf.template {
+                return Err("FORBIDDEN: The template of a waqf cannot be changed.".to_string());
+            }
+
             // Enforce that revolving lock period for an existing waqf cannot be reduced
             if let (Some(prev_rev), Some(new_rev)) = (&previous_waqf.revolving_details, &waqf.revolving_details) {
                 if new_rev.lock_period_months < prev_rev.lock_period_months {
@@ -546,6 +573,14 @@ pub fn assert_waqf_operations(mut context: AssertSetDocContext) -> std::result::
                         new_rev.lock_period_months
                     ));
                 }
+
+                // Penalty terms were accepted by the donor when contributing and cannot change later
+                if new_rev.early_withdrawal_penalty != prev_rev.early_withdrawal_penalty
+                    || new_rev.early_withdrawal_penalty_tiers != prev_rev.early_withdrawal_penalty_tiers
+                    || new_rev.penalty_destination != prev_rev.penalty_destination
+                {
+                    return Err("Early withdrawal penalty terms cannot be changed after creation".to_string());
+                }
             }
         }
     }
@@ -568,7 +603,7 @@ pub fn assert_waqf_deletion(context: AssertDeleteDocContext) -> std::result::Res
         .map_err(|e| format!("Cannot decode waqf data for deletion: {}", e))?;
     
     // Prevent deletion of active waqfs
-    if waqf_to_delete.status == "active" {
+    if waqf_to_delete.status == WaqfStatus::Active {
         return Err("Cannot delete active waqf - change status first".into());
     }
     
@@ -581,11 +616,135 @@ pub fn assert_waqf_deletion(context: AssertDeleteDocContext) -> std::result::Res
     Ok(())
 }
 
+/// Set up a newly created waqf: cause allocations, per-cause buckets, the first report date
+/// and, for revolving and hybrid waqfs, the initial tranche. Everything is derived from the document itself (dates
+/// from its creation time), so running it twice gives the same result and never adds a second
+/// initial tranche. Returns whether the waqf changed.
+pub fn initialize_new_waqf(waqf: &mut WaqfData, created_at: u64) -> bool {
+    let mut changed = false;
+    let waqf_asset = waqf.waqf_asset;
+
+    // 1) Cause allocations, when missing or all zero
+    let needs_allocations = waqf.financial.cause_allocations.is_empty()
+        || waqf.financial.cause_allocations.values().all(|&v| v == 0.0);
+
+    if needs_allocations {
+        ic_cdk::println!("Initializing cause allocations for new waqf: {}", waqf.name);
+
+        waqf.financial.cause_allocations.clear();
+
+        for cause_id in &waqf.selected_causes {
+            let percentage = waqf
+                .cause_allocation
+                .get(cause_id)
+                .copied()
+                .unwrap_or(100.0 / waqf.selected_causes.len() as f64);
+
+            let amount = (waqf_asset * percentage) / 100.0;
+            waqf.financial.cause_allocations.insert(cause_id.clone(), amount);
+
+            ic_cdk::println!("  Cause {}: {:.2}% = {:.2}", cause_id, percentage, amount);
+        }
+
+        changed = true;
+    }
+
+    // 2) Split the initial capital into per-cause permanent/consumable/revolving buckets
+    let initial_split = bucket_accounting::split_amount(waqf, waqf_asset);
+    if waqf.financial.bucket_balances.is_empty() {
+        bucket_accounting::credit(&mut waqf.financial, &initial_split);
+        changed = true;
+    }
+    let revolving_amount = bucket_accounting::bucket_total(&initial_split, WaqfBucket::Revolving);
+
+    // 3) Initial tranche for revolving / hybrid waqfs
+    let should_create_tranche = matches!(waqf.waqf_type, WaqfType::TemporaryRevolving)
+        || (matches!(waqf.waqf_type, WaqfType::Hybrid) && waqf.revolving_details.is_some());
+
+    if should_create_tranche {
+        if let Some(ref mut revolving_details) = waqf.revolving_details {
+            let already_created = revolving_details
+                .contribution_tranches
+                .iter()
+                .flatten()
+                .any(|tranche| tranche.id.starts_with(INITIAL_TRANCHE_PREFIX));
+
+            if already_created {
+                ic_cdk::println!("Initial tranche already present for waqf {}", waqf.name);
+            } else if revolving_amount > 0.0 {
+                let tranche_id = format!("{}{}", INITIAL_TRANCHE_PREFIX, created_at);
+                let maturity_date =
+                    calendar::add_months(created_at, revolving_details.lock_period_months);
+                let initial_tranche = ContributionTranche {
+                    id: tranche_id.clone(),
+                    amount: revolving_amount,
+                    contribution_date: created_at.to_string(),
+                    maturity_date: maturity_date.to_string(),
+                    is_returned: false,
+                    returned_date: None,
+                    status: Some(TrancheStatus::Locked),
+                    penalty_applied: None,
+                    rollover_origin_id: None,
+                    rollover_target_id: None,
+                    installment_payments: None,
+                    expiration_preference: revolving_details.default_expiration_preference.clone(),
+                    conversion_details: None,
+                    matured_at: None,
+                    penalty_waiver: None,
+                    cause_id: None,
+                    rollover_target_waqf_id: None,
+                    pool_origin: None,
+                    currency: None,
+                    split_from: None,
+                    split_into: None,
+                    expiration_failure: None,
+                };
+
+                ic_cdk::println!(
+                    "✨ Creating initial tranche: ID={}, Amount={:.2}, Maturity in {} months",
+                    tranche_id, revolving_amount, revolving_details.lock_period_months
+                );
+
+                revolving_details.contribution_tranches = Some(vec![initial_tranche]);
+                changed = true;
+            } else {
+                ic_cdk::println!(
+                    "⚠️ No revolving amount for waqf {}, skipping tranche creation",
+                    waqf.name
+                );
+            }
+        }
+    }
+
+    // 4) First scheduled report, one reporting period after creation
+    if waqf.next_report_date.is_none() {
+        let first_report = calendar::add_months(created_at, waqf.reporting_preferences.frequency.months());
+        waqf.next_report_date = Some(first_report.to_string());
+        changed = true;
+    }
+
+    changed
+}
+
+/// Whether this change is the satellite's own initialization write, and forget it if so
+fn take_own_write(key: &str, version: Option<u64>) -> bool {
+    OWN_WRITES.with(|writes| {
+        let mut writes = writes.borrow_mut();
+        let position = writes.iter().position(|(k, v)| k == key && *v == version);
+        position.map(|index| writes.swap_remove(index)).is_some()
+    })
+}
+
 // Handle waqf changes (logging, initialization, etc.)
 pub fn handle_waqf_changes(context: OnSetDocContext) -> std::result::Result<(), String> {
     use junobuild_satellite::{set_doc, SetDoc};
     use junobuild_utils::encode_doc_data;
-    use crate::waqf_types::{WaqfType, ContributionTranche};
+
+    // Hooks run again for the document we save below; that round has nothing left to do
+    if take_own_write(&context.data.key, context.data.data.after.version) {
+        ic_cdk::println!("Waqf {}: initialization saved", context.data.key);
+        return Ok(());
+    }
 
     let mut waqf_data: WaqfData = decode_doc_data(&context.data.data.after.data)
         .map_err(|e| format!("Cannot decode waqf data: {}", e))?;
@@ -594,167 +753,41 @@ pub fn handle_waqf_changes(context: OnSetDocContext) -> std::result::Result<(),
     let is_new_waqf = context.data.data.before.is_none();
     let operation_type = if is_new_waqf { "CREATE" } else { "UPDATE" };
 
-    // Track whether we need to persist changes back to the document
-    let mut needs_update = false;
-
-    // Initialize cause allocations and tranches only on creation
+    // Initialize cause allocations and tranches, and number the deed, only on creation
+    let mut initialized = false;
     if is_new_waqf {
-        let waqf_asset = waqf_data.waqf_asset;
-
-        // 1) Initialize cause allocations if missing or zero
-        let needs_init = waqf_data.financial.cause_allocations.is_empty()
-            || waqf_data
-                .financial
-                .cause_allocations
-                .values()
-                .all(|&v| v == 0.0);
-
-        if needs_init {
-            ic_cdk::println!(
-                "Initializing cause allocations for new waqf (on_set_doc): {}",
-                waqf_data.name
-            );
-
-            waqf_data.financial.cause_allocations.clear();
-
-            for cause_id in &waqf_data.selected_causes {
-                let percentage = waqf_data
-                    .cause_allocation
-                    .get(cause_id)
-                    .copied()
-                    .unwrap_or_else(|| {
-                        if !waqf_data.selected_causes.is_empty() {
-                            100.0 / waqf_data.selected_causes.len() as f64
-                        } else {
-                            100.0
-                        }
-                    });
-
-                let amount = (waqf_asset * percentage) / 100.0;
-                waqf_data
-                    .financial
-                    .cause_allocations
-                    .insert(cause_id.clone(), amount);
-
-                ic_cdk::println!(
-                    "  [on_set_doc] Cause {}: {:.2}% = {:.2}",
-                    cause_id, percentage, amount
-                );
-            }
+        let created_at = context.data.data.after.created_at;
+        initialized = initialize_new_waqf(&mut waqf_data, created_at);
 
-            needs_update = true;
+        if waqf_data.deed_reference.is_none() {
+            waqf_data.deed_reference = Some(deed_reference::assign_deed_reference(&context.data.key, created_at)?);
+            initialized = true;
         }
+    }
 
-        // 2) Create initial tranche for revolving / hybrid waqfs
-        let should_create_tranche = matches!(waqf_data.waqf_type, WaqfType::TemporaryRevolving)
-            || (matches!(waqf_data.waqf_type, WaqfType::Hybrid)
-                && waqf_data.revolving_details.is_some());
-
-        if should_create_tranche {
-            if let Some(ref mut revolving_details) = waqf_data.revolving_details {
-                let current_time_nanos = ic_cdk::api::time();
-                let current_time = current_time_nanos.to_string();
-
-                // Maturity based on lock period
-                let lock_period_nanos =
-                    (revolving_details.lock_period_months as u64)
-                        * 30
-                        * 24
-                        * 60
-                        * 60
-                        * 1_000_000_000;
-                let maturity_time_nanos = current_time_nanos + lock_period_nanos;
-                let maturity_date = maturity_time_nanos.to_string();
-
-                // Revolving portion
-                let revolving_amount = if matches!(waqf_data.waqf_type, WaqfType::Hybrid) {
-                    if let Some(ref allocations) = waqf_data.hybrid_allocations {
-                        let total_waqf = waqf_data.waqf_asset;
-                        let mut total_revolving_pct = 0.0;
-
-                        for alloc in allocations {
-                            total_revolving_pct +=
-                                alloc.allocations.temporary_revolving.unwrap_or(0.0);
-                        }
-
-                        let avg_revolving_pct = if !allocations.is_empty() {
-                            total_revolving_pct / allocations.len() as f64
-                        } else {
-                            0.0
-                        };
-
-                        let revolving_amt = (total_waqf * avg_revolving_pct) / 100.0;
-
-                        ic_cdk::println!(
-                            "[on_set_doc] Hybrid waqf - Total: {}, Avg Revolving %: {:.2}, Revolving Amount: {:.2}",
-                            total_waqf, avg_revolving_pct, revolving_amt
-                        );
-
-                        revolving_amt
-                    } else {
-                        waqf_data.waqf_asset
-                    }
-                } else {
-                    // Pure revolving waqf - use entire asset
-                    waqf_data.waqf_asset
-                };
+    if initialized {
+        let updated_data = encode_doc_data(&waqf_data)
+            .map_err(|e| format!("Failed to encode updated waqf data: {}", e))?;
 
-                if revolving_amount > 0.0 {
-                    let tranche_id = format!("tranche_initial_{}", current_time_nanos);
-                    let initial_tranche = ContributionTranche {
-                        id: tranche_id.clone(),
-                        amount: revolving_amount,
-                        contribution_date: current_time.clone(),
-                        maturity_date: maturity_date.clone(),
-                        is_returned: false,
-                        returned_date: None,
-                        status: Some("locked".to_string()),
-                        penalty_applied: None,
-                        rollover_origin_id: None,
-                        rollover_target_id: None,
-                        installment_payments: None,
-                        expiration_preference: revolving_details
-                            .default_expiration_preference
-                            .clone(),
-                        conversion_details: None,
-                    };
-
-                    ic_cdk::println!(
-                        "[on_set_doc] ✨ Creating initial tranche: ID={}, Amount={:.2}, Maturity in {} months",
-                        tranche_id,
-                        revolving_amount,
-                        revolving_details.lock_period_months
-                    );
-
-                    revolving_details.contribution_tranches = Some(vec![initial_tranche]);
-                    needs_update = true;
-                } else {
-                    ic_cdk::println!(
-                        "[on_set_doc] ⚠️ No revolving amount for waqf {}, skipping tranche creation",
-                        waqf_data.name
-                    );
-                }
-            }
-        }
+        let set_doc_data = SetDoc {
+            data: updated_data,
+            description: context.data.data.after.description.clone(),
+            version: context.data.data.after.version,
+        };
 
-        // Persist our initialization back to the waqfs collection
-        if needs_update {
-            let updated_data = encode_doc_data(&waqf_data)
-                .map_err(|e| format!("Failed to encode updated waqf data: {}", e))?;
+        let saved = set_doc("waqfs".to_string(), context.data.key.clone(), set_doc_data);
+        OWN_WRITES.with(|writes| writes.borrow_mut().push((context.data.key.clone(), saved.version)));
 
-            let set_doc_data = SetDoc {
-                data: updated_data,
-                description: context.data.data.after.description.clone(),
-                version: context.data.data.after.version,
-            };
+        ic_cdk::println!("✅ Waqf initialization complete for: {}", waqf_data.name);
+    }
 
-            let _ = set_doc("waqfs".to_string(), context.data.key.clone(), set_doc_data);
+    // Keep the first signed deed in deed_versions
+    let previous_waqf: Option<WaqfData> = context.data.data.before.as_ref()
+        .and_then(|doc| decode_doc_data(&doc.data).ok());
+    deed_hooks::record_first_deed_version(previous_waqf.as_ref(), &waqf_data);
 
-            ic_cdk::println!(
-                "[on_set_doc] ✅ Waqf initialization complete for: {}",
-                waqf_data.name
-            );
-        }
+    if let Some(previous) = previous_waqf.as_ref() {
+        record_preference_changes(previous, &waqf_data, &context.caller.to_text());
     }
 
     // Enhanced logging for audit purposes (runs for create + update)
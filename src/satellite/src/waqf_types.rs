@@ -23,12 +23,13 @@ pub struct NotificationPreferences {
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ReportingPreferences {
-    pub frequency: String, // "quarterly" | "semiannually" | "yearly"
-    pub report_types: Vec<String>, // ["financial", "impact"]
-    pub delivery_method: String, // "email" | "platform" | "both"
+    pub frequency: ReportingFrequency,
+    #[serde(default)]
+    pub report_types: Vec<ReportType>,
+    pub delivery_method: DeliveryMethod,
 }
 
-#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct FinancialMetrics {
     #[serde(alias = "totalDonations")]
     pub total_donations: f64,
@@ -44,6 +45,43 @@ pub struct FinancialMetrics {
     pub growth_rate: f64,
     #[serde(alias = "causeAllocations")]
     pub cause_allocations: std::collections::HashMap<String, f64>, // Amount allocated per cause
+    #[serde(default, alias = "bucketBalances")]
+    pub bucket_balances: std::collections::HashMap<String, BucketBalances>, // Per-cause balance of each waqf bucket
+    #[serde(default, alias = "undistributedReturns")]
+    pub undistributed_returns: f64, // Realised returns waiting for the next distribution date
+    #[serde(default, alias = "pendingDistributions")]
+    pub pending_distributions: std::collections::HashMap<String, f64>, // Distributable returns per cause
+    #[serde(default, alias = "lastReturnPeriodEnd")]
+    pub last_return_period_end: Option<String>, // End of the last recorded return period (nanoseconds)
+    #[serde(default, alias = "nextDistributionDate")]
+    pub next_distribution_date: Option<String>, // When undistributed returns are next split (nanoseconds)
+    #[serde(default, alias = "managementFeesCharged")]
+    pub management_fees_charged: f64, // Management fees posted to the platform fee account
+    #[serde(default, alias = "lastFeeAccrualAt")]
+    pub last_fee_accrual_at: Option<String>, // End of the last fee accrual period (nanoseconds)
+    #[serde(default, alias = "principalReleased")]
+    pub principal_released: f64, // Revolving principal paid back to donors or moved into converted waqfs
+    #[serde(default, alias = "penaltiesCollected")]
+    pub penalties_collected: f64, // Early withdrawal penalties kept by the waqf
+    #[serde(default, alias = "lastReportPeriodEnd")]
+    pub last_report_period_end: Option<String>, // End of the period covered by the last report (nanoseconds)
+    #[serde(default, alias = "lastReportedBalance")]
+    pub last_reported_balance: Option<f64>, // Current balance when the last report was generated
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BucketBalances {
+    pub permanent: f64,   // Principal preserved forever
+    pub consumable: f64,  // Spendable on the cause
+    pub revolving: f64,   // Principal owed back to the donor
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WaqfBucket {
+    Permanent,
+    Consumable,
+    Revolving,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -54,6 +92,143 @@ pub enum WaqfType {
     Hybrid,                 // Mixed allocation across multiple waqf types
 }
 
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum WaqfStatus {
+        Active => "active",
+        Paused => "paused",
+        Completed => "completed",
+        Inactive => "inactive",
+        Archived => "archived",
+        Terminated => "terminated",
+        Matured => "matured",    // Temporary waqf reached the end of its term
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum DonationStatus {
+        Completed => "completed",
+        Pending => "pending",
+        Failed => "failed",
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum CauseStatus {
+        Pending => "pending",
+        Approved => "approved",
+        Rejected => "rejected",
+        Funded => "funded",     // Set by the satellite once the target amount is reached
+    }
+}
+
+wire_enum! {
+    /// What happens to new allocations once a cause reaches its target amount
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TargetReachedAction {
+        Reject => "reject", // Allocations beyond the target are refused
+        Redirect => "redirect", // Allocations go to the cause's redirect cause
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TrancheStatus {
+        Locked => "locked",
+        Matured => "matured",         // Grace period running before the expiration preference is carried out
+        ReturnScheduled => "return_scheduled", // Being returned in installments
+        Returned => "returned",
+        RolledOver => "rolled_over",
+        Converted => "converted",
+        Split => "split",           // Split into a withdrawn and a remaining portion by a partial withdrawal
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum InstallmentStatus {
+        Scheduled => "scheduled",
+        Paid => "paid",
+        Missed => "missed",
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum DueInstallmentStatus {
+        Due => "due",
+        Missed => "missed",
+        Paid => "paid",
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SpendingSchedule {
+        Immediate => "immediate",
+        Phased => "phased",
+        MilestoneBased => "milestone-based",
+        Ongoing => "ongoing",
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PrincipalReturnMethod {
+        LumpSum => "lump_sum",
+        Installments => "installments",
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum AutoRolloverPreference {
+        None => "none",
+        SameCause => "same_cause",
+        CausePool => "cause_pool",
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ReportingFrequency {
+        Quarterly => "quarterly",
+        Semiannually => "semiannually",
+        Yearly => "yearly",
+    }
+}
+
+impl ReportingFrequency {
+    /// Calendar months covered by one report
+    pub fn months(&self) -> u32 {
+        match self {
+            Self::Quarterly => 3,
+            Self::Semiannually => 6,
+            Self::Yearly => 12,
+        }
+    }
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum DeliveryMethod {
+        Email => "email",
+        Platform => "platform",
+        Both => "both",
+    }
+}
+
+wire_enum! {
+    /// Sections a donor asks for in scheduled waqf reports
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ReportType {
+        Financial => "financial",
+        Impact => "impact",
+    }
+}
+
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Milestone {
     pub description: String,
@@ -63,7 +238,7 @@ pub struct Milestone {
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ConsumableWaqfDetails {
-    pub spending_schedule: String,       // "immediate" | "phased" | "milestone-based" | "ongoing"
+    pub spending_schedule: SpendingSchedule,
     
     // Optional time boundaries
     pub start_date: Option<String>,      // ISO timestamp (optional for ongoing)
@@ -89,8 +264,10 @@ pub struct InstallmentPayment {
     pub id: String,
     pub amount: f64,
     pub due_date: String,
-    pub status: String,                  // "scheduled" | "paid" | "missed"
+    pub status: InstallmentStatus,
     pub paid_date: Option<String>,
+    #[serde(default)]
+    pub payout_reference: Option<String>, // Bank or payment reference recorded by the finance officer
 }
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -106,18 +283,72 @@ pub struct TrancheExpirationPreference {
     pub action: ExpirationAction,
     pub rollover_months: Option<u32>,
     pub rollover_cause_id: Option<String>,
-    pub consumable_schedule: Option<String>,  // "immediate" | "phased" | "milestone-based" | "ongoing"
+    #[serde(default)]
+    pub rollover_target: Option<RolloverTarget>, // Same waqf when not set
+    pub consumable_schedule: Option<SpendingSchedule>,
     pub consumable_duration: Option<u32>,
 }
 
+wire_enum! {
+    /// Where a rolled over tranche is locked again
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum RolloverTarget {
+        SameWaqf => "same_waqf",
+        CausePool => "cause_pool", // The platform cause pool waqf
+    }
+}
+
+wire_enum! {
+    /// Type of waqf a matured revolving tranche is converted into
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ConversionTarget {
+        Permanent => "permanent",
+        TemporaryConsumable => "temporary_consumable",
+    }
+}
+
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ConversionDetails {
     pub converted_at: String,
     pub new_waqf_id: String,
-    pub target_waqf_type: String,  // "permanent" | "temporary_consumable"
+    pub target_waqf_type: ConversionTarget,
     pub notes: Option<String>,
 }
 
+wire_enum! {
+    /// What a trustee (nazir) may do for a waqf on the donor's behalf
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TrusteePower {
+        ProposeAllocations => "propose_allocations",   // Create allocations from the waqf's balances
+        RecordReturns => "record_returns",        // Record investment returns of the waqf
+        ApproveWithdrawals => "approve_withdrawals",   // File tranche return requests for the donor
+    }
+}
+
+/// Trustee appointed to a waqf through an approved trustee appointment
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WaqfTrustee {
+    pub principal: String,
+    pub name: Option<String>,
+    pub powers: Vec<TrusteePower>,
+    pub appointment_id: String,  // Approved appointment that made this trustee
+    pub appointed_at: String,    // Nanosecond timestamp
+}
+
+/// Template a waqf was created from, and the template version it was checked against
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WaqfTemplateRef {
+    pub template_id: String,
+    pub version: u32,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConversionOrigin {
+    pub waqf_id: String,       // Revolving or hybrid waqf the tranche came from
+    pub tranche_id: String,
+    pub converted_at: String,  // Nanosecond timestamp
+}
+
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ContributionTranche {
     pub id: String,
@@ -126,35 +357,128 @@ pub struct ContributionTranche {
     pub maturity_date: String,
     pub is_returned: bool,
     pub returned_date: Option<String>,
-    pub status: Option<String>,                      // "locked" | "matured" | "return_scheduled" | "returned" | "rolled_over"
+    pub status: Option<TrancheStatus>,
     pub penalty_applied: Option<f64>,                // Penalty amount applied on early withdrawal
     pub rollover_origin_id: Option<String>,          // If created via rollover, reference original tranche
     pub rollover_target_id: Option<String>,          // If this tranche rolled over into another tranche
     pub installment_payments: Option<Vec<InstallmentPayment>>,
     pub expiration_preference: Option<TrancheExpirationPreference>,  // Expiration action preference
     pub conversion_details: Option<ConversionDetails>,                // Details if converted to another waqf type
+    #[serde(default)]
+    pub matured_at: Option<String>,                  // When the maturity sweep marked the tranche matured (starts the grace period)
+    #[serde(default)]
+    pub penalty_waiver: Option<PenaltyWaiver>,       // Hardship waiver - no early withdrawal penalty
+    #[serde(default)]
+    pub cause_id: Option<String>,                    // Cause the principal is allocated to after a cause-targeted rollover
+    #[serde(default)]
+    pub rollover_target_waqf_id: Option<String>,     // Set when the tranche rolled over into another waqf (the cause pool)
+    #[serde(default)]
+    pub pool_origin: Option<PoolOrigin>,             // Set on cause pool tranches - who owns the principal
+    #[serde(default)]
+    pub currency: Option<String>,                    // Currency of the funding donation (None for initial and older tranches)
+    #[serde(default)]
+    pub split_from: Option<String>,                  // Parent tranche this portion was split from by a partial withdrawal
+    #[serde(default)]
+    pub split_into: Option<Vec<String>>,             // Withdrawn portion then remaining portion, set on a split parent
+    #[serde(default)]
+    pub expiration_failure: Option<ExpirationFailure>, // Last failed attempt to carry out the expiration preference
+}
+
+/// Failed expiration of a matured tranche. The sweep does not retry it until the donor changes
+/// the preference or staff clear the record, so the donor is notified once.
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExpirationFailure {
+    pub failed_at: String,                        // Nanosecond timestamp
+    pub reason: String,
+    pub preference: TrancheExpirationPreference,  // Preference that could not be carried out
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PoolOrigin {
+    pub waqf_id: String,    // Waqf the matured tranche rolled over from
+    pub tranche_id: String,
+    pub owner: String,      // Donor who keeps ownership of the principal and may reclaim it at maturity
+    pub pooled_at: String,  // Nanosecond timestamp
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct CausePoolConfig {
+    pub waqf_id: String,    // Platform-managed revolving waqf that receives cause_pool rollovers
+    pub updated_by: String,
+    pub updated_at: String, // Nanosecond timestamp
 }
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct RevolvingWaqfDetails {
     pub lock_period_months: u32,         // Lock period in months
     pub maturity_date: String,           // ISO timestamp when principal will be returned (for initial)
-    pub principal_return_method: String, // "lump_sum" | "installments"
+    pub principal_return_method: PrincipalReturnMethod,
     pub installment_schedule: Option<InstallmentSchedule>,
     pub early_withdrawal_penalty: Option<f64>, // Penalty percentage (e.g., 0.1 = 10%)
     pub early_withdrawal_allowed: bool,
     pub contribution_tranches: Option<Vec<ContributionTranche>>, // Track each contribution separately
-    pub auto_rollover_preference: Option<String>,   // "none" | "same_cause" | "cause_pool"
+    pub auto_rollover_preference: Option<AutoRolloverPreference>,
     pub auto_rollover_target_cause: Option<String>, // Optional cause to target for rollover
-    pub pending_notifications: Option<Vec<String>>, // Pending notifications to surface to donor
+    pub pending_notifications: Option<Vec<String>>, // Legacy free-text notices, superseded by the notifications collection
     pub default_expiration_preference: Option<TrancheExpirationPreference>, // Default expiration preference for new tranches
+    #[serde(default)]
+    pub early_withdrawal_penalty_tiers: Option<Vec<PenaltyTier>>, // Declining penalty schedule, replaces the flat rate when set
+    #[serde(default)]
+    pub penalty_destination: Option<PenaltyDestination>, // Where collected penalties are credited, the permanent bucket when not set
+}
+
+wire_enum! {
+    /// Bucket that receives early withdrawal penalties
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PenaltyDestination {
+        Permanent => "permanent", // Added to the preserved principal
+        Causes => "causes", // Spendable on the waqf's causes
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PenaltyTier {
+    pub until_month: u32, // Tier applies while fewer than this many months have passed since the contribution
+    pub rate: f64,        // Penalty rate (e.g., 0.1 = 10%)
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PenaltyWaiver {
+    pub waiver_id: String,
+    pub reason: String,
+    pub granted_by: String,
+    pub granted_at: String, // Nanosecond timestamp
+}
+
+wire_enum! {
+    /// How often investment returns are distributed to causes
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum DistributionFrequency {
+        Monthly => "monthly",
+        Quarterly => "quarterly",
+        Semiannually => "semiannually",
+        #[serde(alias = "yearly")]
+        Annually => "annually",
+    }
+}
+
+impl DistributionFrequency {
+    /// Calendar months between distributions
+    pub fn months(&self) -> u32 {
+        match self {
+            Self::Monthly => 1,
+            Self::Quarterly => 3,
+            Self::Semiannually => 6,
+            Self::Annually => 12,
+        }
+    }
 }
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct InvestmentStrategy {
     pub asset_allocation: String,        // e.g., "60% Sukuk, 40% Equity"
     pub expected_annual_return: f64,
-    pub distribution_frequency: String,  // "monthly" | "quarterly" | "annually"
+    pub distribution_frequency: DistributionFrequency,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -177,11 +501,15 @@ pub struct HybridCauseAllocation {
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct DeedDocument {
     pub signed_at: String,
-    pub donor_signature: String,
-    pub document_version: String,
+    pub donor_signature: String,    // Hex Ed25519 signature over the terms hash
+    pub document_version: String,   // "1" when first signed, bumped by each recorded amendment
+    #[serde(default)]
+    pub public_key: Option<String>, // Hex DER-encoded Ed25519 key of the signer
+    #[serde(default)]
+    pub terms_hash: Option<String>, // Hex SHA-256 of the canonical deed terms
 }
 
-#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct WaqfData {
     pub id: String,
     pub name: String,
@@ -193,7 +521,7 @@ pub struct WaqfData {
     pub selected_causes: Vec<String>,
     #[serde(alias = "causeAllocation")]
     pub cause_allocation: std::collections::HashMap<String, f64>, // Percentage allocation per cause
-    pub status: String, // "active" | "paused" | "completed" | "inactive" | "archived" | "terminated" | "matured"
+    pub status: WaqfStatus,
     pub is_donated: Option<bool>,
     pub notifications: NotificationPreferences,
     pub reporting_preferences: ReportingPreferences,
@@ -211,6 +539,23 @@ pub struct WaqfData {
     pub last_contribution_date: Option<String>,
     pub next_contribution_date: Option<String>,
     pub next_report_date: Option<String>,
+    #[serde(default, alias = "causesPendingReassignment")]
+    pub causes_pending_reassignment: Option<Vec<String>>, // Funded causes whose allocation share the donor should move
+    #[serde(default, alias = "convertedFrom")]
+    pub converted_from: Option<ConversionOrigin>, // Source of a waqf created from a matured revolving tranche
+    #[serde(default, alias = "deedReference")]
+    pub deed_reference: Option<String>, // "WQF-2026-000123", assigned by the satellite on creation
+    #[serde(default)]
+    pub trustees: Vec<WaqfTrustee>,     // Nazirs managing the waqf, maintained through trustee appointments
+    #[serde(default)]
+    pub template: Option<WaqfTemplateRef>, // Template the waqf was created from, checked on creation
+}
+
+impl WaqfData {
+    /// Whether the caller is a trustee of this waqf holding the given power
+    pub fn trustee_has_power(&self, caller: &str, power: TrusteePower) -> bool {
+        self.trustees.iter().any(|trustee| trustee.principal == caller && trustee.powers.contains(&power))
+    }
 }
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
@@ -225,7 +570,7 @@ pub struct DonationData {
     pub date: String, // ISO timestamp
     pub amount: f64,
     pub currency: String,
-    pub status: String, // "completed" | "pending" | "failed"
+    pub status: DonationStatus,
     pub transaction_id: Option<String>,
     pub donor_name: Option<String>,
     pub lock_period_months: Option<u32>, // Optional custom lock period for this contribution
@@ -244,6 +589,148 @@ pub struct AllocationData {
     pub amount: f64,
     pub rationale: String,
     pub allocated_at: String, // ISO timestamp
+    pub bucket: Option<WaqfBucket>, // Bucket to draw from (defaults to the waqf type, consumable for hybrids)
+    #[serde(default, alias = "redirectedCauseId")]
+    pub redirected_cause_id: Option<String>, // Set by the satellite when part of the allocation went to a redirect cause
+    #[serde(default, alias = "redirectedAmount")]
+    pub redirected_amount: Option<f64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct TrancheLineageEntry {
+    pub waqf_id: String,               // Waqf holding this tranche (differs after a cause pool rollover)
+    pub relation: String,              // "contribution" | "rollover" | "cause_pool_rollover" | "split_withdrawn" | "split_remaining"
+    pub tranche: ContributionTranche,
+    pub principal_returned: f64,       // Paid back to the donor from this tranche
+    pub principal_converted: f64,      // Moved into a converted permanent or consumable waqf
+    pub principal_outstanding: f64,    // Still owed to the donor from this tranche
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TrancheLineageSummary {
+    pub original_amount: f64,
+    pub total_principal_returned: f64,
+    pub total_penalties: f64,
+    pub total_converted: f64,
+    pub principal_outstanding: f64,
+    pub installments_paid: u32,
+    pub installments_pending: u32,
+    pub current_status: Option<TrancheStatus>, // Status of the latest tranche in the chain
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct TrancheLineage {
+    pub entries: Vec<TrancheLineageEntry>, // Oldest contribution first
+    pub summary: TrancheLineageSummary,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct MaturityCalendarItem {
+    pub kind: String,                      // "tranche" (principal due at maturity) | "installment"
+    pub waqf_id: String,
+    pub waqf_name: String,
+    pub owner: String,                     // Donor who owns the principal
+    pub tranche_id: String,
+    pub installment_id: Option<String>,
+    pub amount: f64,
+    pub due_date: String,                  // Nanosecond timestamp
+    pub status: String,                    // Tranche or installment status
+    pub expiration_action: Option<ExpirationAction>, // What happens to a maturing tranche's principal
+    pub cause_id: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct MaturityCalendarMonth {
+    pub month: String,                     // "YYYY-MM" (UTC)
+    pub currency: String,                  // "unspecified" for tranches without a recorded currency
+    pub maturing_principal: f64,
+    pub installments_due: f64,
+    pub total: f64,
+    pub items: Vec<MaturityCalendarItem>,  // Earliest due first
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct MaturityCalendar {
+    pub from: String,                      // Nanosecond timestamps bounding the window
+    pub to: String,
+    pub months: Vec<MaturityCalendarMonth>, // Ordered by month, then currency
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct DueInstallmentData {
+    pub id: String,
+    pub waqf_id: String,
+    pub tranche_id: String,
+    pub installment_id: String,
+    pub amount: f64,
+    pub due_date: String,      // Nanosecond timestamp
+    pub status: DueInstallmentStatus,
+    pub updated_at: String,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct PenaltyWaiverData {
+    pub id: String,
+    pub waqf_id: String,
+    pub tranche_id: String,
+    pub reason: String,
+    pub granted_by: String,
+    pub granted_at: String,    // Nanosecond timestamp
+}
+
+wire_enum! {
+    #[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ReallocationStatus {
+        Pending => "pending",
+        Approved => "approved",   // Applied to the waqf by the satellite
+        Rejected => "rejected",
+    }
+}
+
+/// Donor request to move a waqf's causes, approved by a waqf manager. Waqfs with a signed
+/// deed need the donor's signature over the amended deed, which is recorded on approval.
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CauseReallocationData {
+    pub id: String,
+    pub waqf_id: String,
+    pub selected_causes: Vec<String>,
+    pub cause_allocation: std::collections::HashMap<String, f64>, // Percentage allocation per cause
+    pub hybrid_allocations: Option<Vec<HybridCauseAllocation>>,
+    pub reason: String,
+    pub requested_by: String,
+    pub requested_at: String,              // Nanosecond timestamps
+    pub donor_signature: Option<String>,   // Hex Ed25519 signature over the amended deed hash
+    pub public_key: Option<String>,        // Hex DER-encoded Ed25519 public key
+    pub signed_at: Option<String>,
+    pub status: ReallocationStatus,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<String>,
+    pub review_notes: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct InstallmentPayoutData {
+    pub id: String,
+    pub waqf_id: String,
+    pub tranche_id: String,
+    pub installment_id: String,
+    pub amount: f64,
+    pub payout_reference: String,
+    pub paid_by: String,
+    pub paid_at: String,       // Nanosecond timestamp
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
+pub struct InvestmentReturnData {
+    pub id: String,
+    pub waqf_id: String,
+    pub period_start: String, // Nanosecond timestamp
+    pub period_end: String,   // Nanosecond timestamp
+    pub amount: f64,          // Realised return for the period (negative for a loss)
+    pub notes: Option<String>,
+    pub recorded_by: String,
+    pub recorded_at: String,  // Nanosecond timestamp
+    pub pool_transaction_id: Option<String>, // Set when this is a member's share of a pooled return
 }
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
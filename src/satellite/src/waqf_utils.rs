@@ -1,4 +1,5 @@
-use crate::waqf_types::{WaqfData, DonorProfile};
+use crate::admin_hooks::{caller_has_role, is_satellite_caller, AdminRole};
+use crate::waqf_types::{DonorProfile, WaqfData, WaqfStatus};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -10,25 +11,50 @@ const MAX_DESCRIPTION_LENGTH: usize = 2000;
 const MIN_WAQF_ASSET: f64 = 100.0; // Minimum $100 for meaningful waqf contribution
 const MAX_WAQF_ASSET: f64 = 1_000_000_000.0;
 
-// Valid waqf statuses matching frontend
-const VALID_WAQF_STATUSES: &[&str] = &["active", "paused", "completed", "inactive", "archived"];
-
-// Valid reporting frequencies
-const VALID_FREQUENCIES: &[&str] = &["quarterly", "semiannually", "yearly"];
-
-// Valid report types
-const VALID_REPORT_TYPES: &[&str] = &["financial", "impact"];
-
-// Valid delivery methods
-const VALID_DELIVERY_METHODS: &[&str] = &["email", "platform", "both"];
+/// Who may move a waqf from one status to another
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatusActor {
+    Donor,            // The waqf's creator
+    Satellite,        // Hooks and scheduled jobs
+    Staff(AdminRole),
+}
 
-// Status transition matrix for waqfs - matches frontend
-const VALID_WAQF_TRANSITIONS: &[(&str, &[&str])] = &[
-    ("active", &["paused", "completed", "inactive", "archived"]),
-    ("paused", &["active", "inactive", "archived"]),
-    ("inactive", &["active", "archived"]),
-    ("completed", &["archived"]), // Terminal state can only be archived
-    ("archived", &[]), // Final terminal state
+const DONOR_OR_MANAGERS: &[StatusActor] = &[
+    StatusActor::Donor,
+    StatusActor::Staff(AdminRole::WaqfManager),
+    StatusActor::Staff(AdminRole::PlatformAdmin),
+];
+const MANAGERS: &[StatusActor] = &[
+    StatusActor::Staff(AdminRole::WaqfManager),
+    StatusActor::Staff(AdminRole::PlatformAdmin),
+];
+const SATELLITE_OR_MANAGERS: &[StatusActor] = &[
+    StatusActor::Satellite,
+    StatusActor::Staff(AdminRole::WaqfManager),
+    StatusActor::Staff(AdminRole::PlatformAdmin),
+];
+const PLATFORM_ADMIN: &[StatusActor] = &[StatusActor::Staff(AdminRole::PlatformAdmin)];
+
+// Status transition matrix for waqfs: (from, to, who may make the transition).
+// "completed", "terminated" and "matured" can only move on to archival; "archived" is final.
+const VALID_WAQF_TRANSITIONS: &[(WaqfStatus, WaqfStatus, &[StatusActor])] = &[
+    (WaqfStatus::Active, WaqfStatus::Paused, DONOR_OR_MANAGERS),
+    (WaqfStatus::Active, WaqfStatus::Inactive, MANAGERS),
+    (WaqfStatus::Active, WaqfStatus::Completed, SATELLITE_OR_MANAGERS),
+    (WaqfStatus::Active, WaqfStatus::Matured, &[StatusActor::Satellite]),   // Temporary waqf reached the end of its term
+    (WaqfStatus::Active, WaqfStatus::Terminated, &[StatusActor::Satellite]), // Approved termination wound down
+    (WaqfStatus::Active, WaqfStatus::Archived, PLATFORM_ADMIN),
+    (WaqfStatus::Paused, WaqfStatus::Active, DONOR_OR_MANAGERS),
+    (WaqfStatus::Paused, WaqfStatus::Inactive, MANAGERS),
+    (WaqfStatus::Paused, WaqfStatus::Terminated, &[StatusActor::Satellite]),
+    (WaqfStatus::Paused, WaqfStatus::Archived, PLATFORM_ADMIN),
+    (WaqfStatus::Inactive, WaqfStatus::Active, MANAGERS),
+    (WaqfStatus::Inactive, WaqfStatus::Terminated, &[StatusActor::Satellite]),
+    (WaqfStatus::Inactive, WaqfStatus::Archived, PLATFORM_ADMIN),
+    (WaqfStatus::Matured, WaqfStatus::Completed, SATELLITE_OR_MANAGERS),
+    (WaqfStatus::Matured, WaqfStatus::Archived, PLATFORM_ADMIN),
+    (WaqfStatus::Completed, WaqfStatus::Archived, PLATFORM_ADMIN),
+    (WaqfStatus::Terminated, WaqfStatus::Archived, PLATFORM_ADMIN),
 ];
 
 // Phone validation constants
@@ -71,11 +97,7 @@ pub enum WaqfValidationError {
     DonorAddressTooLong { max_length: usize, actual: usize },
     
     // Status and preferences validation
-    InvalidStatus { status: String, valid_statuses: Vec<String> },
     InvalidStatusTransition { from: String, to: String },
-    InvalidReportingFrequency { frequency: String, valid_frequencies: Vec<String> },
-    InvalidReportType { report_type: String, valid_types: Vec<String> },
-    InvalidDeliveryMethod { method: String, valid_methods: Vec<String> },
     
     // Causes validation
     NoCausesSelected,
@@ -155,21 +177,9 @@ impl fmt::Display for WaqfValidationError {
             }
             
             // Status and preferences
-            Self::InvalidStatus { status, valid_statuses } => {
-                write!(f, "Invalid status '{}'. Valid statuses: {}", status, valid_statuses.join(", "))
-            }
             Self::InvalidStatusTransition { from, to } => {
                 write!(f, "Invalid status transition from '{}' to '{}'", from, to)
             }
-            Self::InvalidReportingFrequency { frequency, valid_frequencies } => {
-                write!(f, "Invalid reporting frequency '{}'. Valid frequencies: {}", frequency, valid_frequencies.join(", "))
-            }
-            Self::InvalidReportType { report_type, valid_types } => {
-                write!(f, "Invalid report type '{}'. Valid types: {}", report_type, valid_types.join(", "))
-            }
-            Self::InvalidDeliveryMethod { method, valid_methods } => {
-                write!(f, "Invalid delivery method '{}'. Valid methods: {}", method, valid_methods.join(", "))
-            }
             
             // Causes validation
             Self::NoCausesSelected => write!(f, "At least one cause must be selected"),
@@ -230,8 +240,29 @@ impl WaqfValidationResult {
 
 /// Comprehensive waqf data validation with detailed error reporting
 pub fn validate_waqf_data(data: &WaqfData) -> std::result::Result<(), String> {
-    let validation_result = validate_waqf_data_detailed(data, None);
-    
+    into_result(data, validate_waqf_data_detailed(data, None))
+}
+
+/// Validate an update against the previous document: status transitions (and who may make
+/// them) and the archived/completed locks, on top of the field validation
+pub fn validate_waqf_update(
+    data: &WaqfData,
+    current: &WaqfData,
+    caller: &str,
+) -> std::result::Result<(), String> {
+    // Scheduled jobs and hooks write as the satellite: a legacy or inconsistent document must not
+    // trap their set_doc, so only the transition matrix applies to them
+    if !is_satellite_caller(caller) {
+        into_result(data, validate_waqf_data_detailed(data, Some(current)))?;
+    }
+    authorize_status_transition(current.status, data.status, current, caller)
+}
+
+fn into_result(data: &WaqfData, validation_result: WaqfValidationResult) -> std::result::Result<(), String> {
+    for warning in &validation_result.warnings {
+        ic_cdk::println!("WARN - Waqf {}: {}", data.id, warning);
+    }
+
     if validation_result.is_valid {
         Ok(())
     } else {
@@ -243,6 +274,39 @@ pub fn validate_waqf_data(data: &WaqfData) -> std::result::Result<(), String> {
     }
 }
 
+/// Check the caller may make a status transition already validated against the matrix
+fn authorize_status_transition(
+    from: WaqfStatus,
+    to: WaqfStatus,
+    current: &WaqfData,
+    caller: &str,
+) -> std::result::Result<(), String> {
+    if from == to {
+        return Ok(());
+    }
+
+    let actors = VALID_WAQF_TRANSITIONS
+        .iter()
+        .find(|(status_from, status_to, _)| *status_from == from && *status_to == to)
+        .map(|(_, _, actors)| *actors)
+        .unwrap_or(&[]);
+
+    let allowed = actors.iter().any(|actor| match actor {
+        StatusActor::Donor => caller == current.created_by,
+        StatusActor::Satellite => is_satellite_caller(caller),
+        StatusActor::Staff(role) => caller_has_role(caller, std::slice::from_ref(role)),
+    });
+
+    if !allowed {
+        return Err(format!(
+            "FORBIDDEN: Your role cannot change the status of waqf {} from '{}' to '{}'",
+            current.id, from, to
+        ));
+    }
+
+    Ok(())
+}
+
 /// Detailed validation with comprehensive error reporting for WaqfData
 pub fn validate_waqf_data_detailed(
     data: &WaqfData, 
@@ -254,7 +318,7 @@ pub fn validate_waqf_data_detailed(
     validate_waqf_id(&data.id, &mut result);
     validate_waqf_name(&data.name, &mut result);
     validate_waqf_description(&data.description, &mut result);
-    validate_waqf_asset(data.waqf_asset, &mut result);
+    validate_waqf_asset(data.waqf_asset, data.converted_from.is_some(), &mut result);
     
     // Validate donor profile
     validate_donor_profile(&data.donor, &mut result);
@@ -262,9 +326,6 @@ pub fn validate_waqf_data_detailed(
     // Validate causes
     validate_selected_causes(&data.selected_causes, &mut result);
     
-    // Validate status
-    validate_waqf_status(&data.status, &mut result);
-    
     // Validate preferences
     validate_notification_preferences(&data.notifications, &mut result);
     validate_reporting_preferences(&data.reporting_preferences, &mut result);
@@ -280,7 +341,7 @@ pub fn validate_waqf_data_detailed(
     
     // Status transition validation if updating
     if let Some(current) = current_data {
-        validate_waqf_status_transition(&current.status, &data.status, &mut result);
+        validate_waqf_status_transition(current.status, data.status, &mut result);
         validate_waqf_update_permissions(current, data, &mut result);
     }
     
@@ -420,16 +481,6 @@ fn validate_selected_causes(causes: &[String], result: &mut WaqfValidationResult
     }
 }
 
-/// Validate waqf status
-fn validate_waqf_status(status: &str, result: &mut WaqfValidationResult) {
-    if !VALID_WAQF_STATUSES.contains(&status) {
-        result.add_error(WaqfValidationError::InvalidStatus {
-            status: status.to_string(),
-            valid_statuses: VALID_WAQF_STATUSES.iter().map(|s| s.to_string()).collect(),
-        });
-    }
-}
-
 /// Validate notification preferences
 fn validate_notification_preferences(_notifications: &crate::waqf_types::NotificationPreferences, _result: &mut WaqfValidationResult) {
     // Basic validation - boolean fields are always valid
@@ -437,32 +488,8 @@ fn validate_notification_preferences(_notifications: &crate::waqf_types::Notific
 }
 
 /// Validate reporting preferences
-fn validate_reporting_preferences(prefs: &crate::waqf_types::ReportingPreferences, result: &mut WaqfValidationResult) {
-    // Validate frequency
-    if !VALID_FREQUENCIES.contains(&prefs.frequency.as_str()) {
-        result.add_error(WaqfValidationError::InvalidReportingFrequency {
-            frequency: prefs.frequency.clone(),
-            valid_frequencies: VALID_FREQUENCIES.iter().map(|s| s.to_string()).collect(),
-        });
-    }
-    
-    // Validate report types
-    for report_type in &prefs.report_types {
-        if !VALID_REPORT_TYPES.contains(&report_type.as_str()) {
-            result.add_error(WaqfValidationError::InvalidReportType {
-                report_type: report_type.clone(),
-                valid_types: VALID_REPORT_TYPES.iter().map(|s| s.to_string()).collect(),
-            });
-        }
-    }
-    
-    // Validate delivery method
-    if !VALID_DELIVERY_METHODS.contains(&prefs.delivery_method.as_str()) {
-        result.add_error(WaqfValidationError::InvalidDeliveryMethod {
-            method: prefs.delivery_method.clone(),
-            valid_methods: VALID_DELIVERY_METHODS.iter().map(|s| s.to_string()).collect(),
-        });
-    }
+fn validate_reporting_preferences(_prefs: &crate::waqf_types::ReportingPreferences, _result: &mut WaqfValidationResult) {
+    // Frequency, delivery method and report types are checked when the document is decoded
 }
 
 /// Validate financial metrics
@@ -490,7 +517,8 @@ fn validate_financial_metrics(financial: &crate::waqf_types::FinancialMetrics, r
     }
     
     // Basic consistency check
-    let expected_balance = financial.total_donations - financial.total_distributed + financial.total_investment_return;
+    let expected_balance = financial.total_donations - financial.total_distributed + financial.total_investment_return
+        - financial.management_fees_charged - financial.principal_released;
     if (expected_balance - financial.current_balance).abs() > 0.01 {
         result.add_error(WaqfValidationError::InconsistentFinancialData(
             "Current balance doesn't match calculated balance".to_string()
@@ -521,18 +549,16 @@ fn validate_waqf_creator(created_by: &str, result: &mut WaqfValidationResult) {
 }
 
 /// Validate waqf status transition
-fn validate_waqf_status_transition(from: &str, to: &str, result: &mut WaqfValidationResult) {
+fn validate_waqf_status_transition(from: WaqfStatus, to: WaqfStatus, result: &mut WaqfValidationResult) {
     if from == to {
         return; // No transition needed
     }
     
-    let allowed_transitions = VALID_WAQF_TRANSITIONS
+    let is_allowed = VALID_WAQF_TRANSITIONS
         .iter()
-        .find(|(status, _)| *status == from)
-        .map(|(_, transitions)| *transitions)
-        .unwrap_or(&[]);
+        .any(|(status_from, status_to, _)| *status_from == from && *status_to == to);
     
-    if !allowed_transitions.contains(&to) {
+    if !is_allowed {
         result.add_error(WaqfValidationError::InvalidStatusTransition {
             from: from.to_string(),
             to: to.to_string(),
@@ -543,14 +569,14 @@ fn validate_waqf_status_transition(from: &str, to: &str, result: &mut WaqfValida
 /// Validate waqf update permissions
 fn validate_waqf_update_permissions(current: &WaqfData, new: &WaqfData, result: &mut WaqfValidationResult) {
     // Check if trying to modify archived waqf
-    if current.status == "archived" {
+    if current.status == WaqfStatus::Archived {
         result.add_error(WaqfValidationError::ArchivedWaqfModification);
         return;
     }
     
     // Check if trying to modify completed waqf inappropriately
     // Allow financial updates (adding funds) to completed waqfs
-    if current.status == "completed" && new.status != "archived" {
+    if current.status == WaqfStatus::Completed && new.status != WaqfStatus::Archived {
         // Check if only financial fields are changing (donations, balance)
         let is_financial_only_update = 
             current.name == new.name &&
@@ -569,13 +595,13 @@ fn validate_waqf_update_permissions(current: &WaqfData, new: &WaqfData, result:
 /// Validate waqf business rules
 fn validate_waqf_business_rules(data: &WaqfData, current_data: Option<&WaqfData>, result: &mut WaqfValidationResult) {
     // Initial capital validation
-    if data.status == "active" && data.waqf_asset < 1.0 {
+    if data.status == WaqfStatus::Active && data.waqf_asset < 1.0 {
         result.add_warning("Active waqf with very low initial capital".to_string());
     }
     
     // Warn about changes to active waqfs
     if let Some(current) = current_data {
-        if current.status == "active" {
+        if current.status == WaqfStatus::Active {
             if current.name != data.name {
                 result.add_warning("Changing name of active waqf requires additional approval".to_string());
             }
@@ -593,7 +619,7 @@ fn is_valid_email(email: &str) -> bool {
     email.contains('@') && email.contains('.') && email.len() > 5
 }
 /// Validate initial capital amount
-fn validate_waqf_asset(amount: f64, result: &mut WaqfValidationResult) {
+fn validate_waqf_asset(amount: f64, is_conversion: bool, result: &mut WaqfValidationResult) {
     // Check for NaN and infinity
     if amount.is_nan() {
         result.add_error(WaqfValidationError::InitialCapitalInvalid(
@@ -609,8 +635,8 @@ fn validate_waqf_asset(amount: f64, result: &mut WaqfValidationResult) {
         return;
     }
     
-    // Check range
-    if amount < MIN_WAQF_ASSET {
+    // Check range (waqfs converted from a matured tranche take the tranche amount as is)
+    if amount < MIN_WAQF_ASSET && !is_conversion {
         result.add_error(WaqfValidationError::InitialCapitalTooLow {
             min_amount: MIN_WAQF_ASSET,
             actual: amount,
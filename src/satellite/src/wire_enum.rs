@@ -0,0 +1,81 @@
+/// Declare a fieldless enum stored in documents by name. Each variant's wire name is written
+/// once and used for both its serde rename and `as_str` / `Display`, so they cannot diverge.
+///
+/// ```ignore
+/// wire_enum! {
+///     #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+///     pub enum Frequency {
+///         Monthly => "monthly",
+///         #[serde(alias = "yearly")]
+///         Annually => "annually",
+///     }
+/// }
+/// ```
+macro_rules! wire_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident => $wire:literal
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                #[serde(rename = $wire)]
+                $variant,
+            )+
+        }
+
+        impl $name {
+            /// Wire name, as stored in documents
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $wire,)+
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::waqf_types::{DistributionFrequency, TrancheStatus};
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_cbor::Value;
+
+    fn wire<T: Serialize>(value: &T) -> String {
+        match serde_cbor::value::to_value(value).unwrap() {
+            Value::Text(text) => text,
+            other => panic!("expected a string, got {:?}", other),
+        }
+    }
+
+    fn parse<T: DeserializeOwned>(text: &str) -> T {
+        serde_cbor::value::from_value(Value::Text(text.to_string())).unwrap()
+    }
+
+    #[test]
+    fn as_str_matches_the_serde_wire_name() {
+        for status in [TrancheStatus::Locked, TrancheStatus::ReturnScheduled, TrancheStatus::RolledOver] {
+            assert_eq!(wire(&status), status.as_str());
+            assert_eq!(parse::<TrancheStatus>(status.as_str()), status);
+        }
+        assert_eq!(DistributionFrequency::Annually.to_string(), wire(&DistributionFrequency::Annually));
+    }
+
+    #[test]
+    fn aliases_still_deserialize() {
+        assert_eq!(parse::<DistributionFrequency>("yearly"), DistributionFrequency::Annually);
+        assert_eq!(parse::<DistributionFrequency>("annually"), DistributionFrequency::Annually);
+    }
+}